@@ -0,0 +1,105 @@
+// TMS9918A Graphics I sprite example using high-level functions
+
+use minifb::{Scale, ScaleMode, Window, WindowOptions};
+use tms9918a_emu::{Color, SpriteDef, TMS9918A, VideoMode};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // create a new TMS9918A VDP instance
+    let mut vdp = TMS9918A::new();
+
+    // create a new minifb window
+    let mut window = Window::new(
+        "TMS9918A Bouncing Sprite Example",
+        256,
+        192,
+        WindowOptions {
+            resize: true,
+            scale_mode: ScaleMode::AspectRatioStretch,
+            scale: Scale::X4,
+            ..WindowOptions::default()
+        },
+    )?;
+
+    // limit the update rate to the VDP frame rate, ~60 fps for NTSC or ~50 fps for PAL
+    window.limit_update_rate(Some(vdp.frame_duration()));
+
+    // select 16K of video memory, the sprite tables below are above the first 4K
+    vdp.write_register(1, 0x80);
+
+    // use Graphics I Mode, 32x24 tiles at 8x8 pixels each
+    vdp.set_video_mode(VideoMode::Gfx1);
+
+    // set the name table base address to 0x0000 (base address = multiplier * 0x0400)
+    vdp.set_name_table_multiplier(0);
+
+    // set the color table base address to 0x0400 (base address = multiplier * 0x0040)
+    vdp.set_color_table_multiplier(0x10);
+
+    // set the pattern table base address to 0x0800 (base address = multiplier * 0x0800)
+    vdp.set_pattern_table_multiplier(1);
+
+    // set the sprite attribute table base address to 0x1000 (base address = multiplier * 0x0080)
+    vdp.write_register(5, 0x20);
+
+    // set the sprite pattern table base address to 0x1800 (base address = multiplier * 0x0800)
+    vdp.write_register(6, 0x03);
+
+    // set the backdrop color to black
+    vdp.set_backdrop_color(Color::Black);
+
+    // use tile 0 for the whole screen, and make it a solid dark blue (0x4)
+    vdp.clear_name_table();
+    vdp.fill_pattern_table(&[0; 8], 0, 8)?;
+    vdp.write_color_table(0, 0x44)?;
+
+    // make sprite pattern 0 a ball
+    let ball: [u8; 8] = [0b00111100,
+                         0b01111110,
+                         0b11111111,
+                         0b11111111,
+                         0b11111111,
+                         0b11111111,
+                         0b01111110,
+                         0b00111100];
+    vdp.set_sprite_pattern(0, &ball)?;
+
+    // display the ball as sprite 0 in light red (0x9)
+    let (mut x, mut y): (i16, i16) = (16, 32);
+    let (mut dx, mut dy) = (1, 1);
+    vdp.define_sprite(0, SpriteDef { x: x as u8, y: y as u8, pattern: 0, color: 0x9, early_clock: false })?;
+
+    // hide all of the other sprites
+    // the sprite attribute table contains random data on startup, similar to how real memory works
+    for i in 1..32 {
+        vdp.hide_sprite(i)?;
+    }
+
+    // enable video output (sets the blanking bit in register 1)
+    // enable_video leaves the 16K bit as it is, it was set above for the sprite tables
+    vdp.enable_video(true);
+
+    // update VDP framebuffer and window contents
+    while window.is_open() {
+        // bounce the ball off the edges of the screen
+        if x == 0 || x == 256 - 8 {
+            dx = -dx;
+        }
+        // sprites are displayed one line below their vertical position
+        if y == -1 || y == 192 - 8 - 1 {
+            dy = -dy;
+        }
+        x += dx;
+        y += dy;
+        vdp.move_sprite(0, x as u8, y as u8)?;
+
+        vdp.update();
+
+        window.update_with_buffer(
+            &vdp.frame,
+            vdp.frame_width(),
+            vdp.frame_height(),
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+/*
+ * TMS9918A Text mode example in C, using the port level C bindings
+ * this is the same as the low_level_text example, but saves the frame as text_demo.ppm instead of opening a window
+ *
+ * build and run from the repository root:
+ *     cargo rustc --release --no-default-features --features ffi --crate-type staticlib
+ *     cc -std=c99 -Iinclude examples/c_text_demo/main.c target/release/libtms9918a_emu.a -lpthread -ldl -lm -o text_demo
+ *     ./text_demo
+ */
+
+#include <stdio.h>
+#include <string.h>
+#include "tms9918a.h"
+
+/* glyphs from the built-in 6x8 font for the characters in the message */
+static const struct {
+    char c;
+    uint8_t pattern[8];
+} glyphs[] = {
+    { ' ', { 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 } },
+    { '!', { 0x10, 0x38, 0x38, 0x10, 0x10, 0x00, 0x10, 0x00 } },
+    { ',', { 0x00, 0x00, 0x00, 0x00, 0x00, 0x60, 0x60, 0x40 } },
+    { 'H', { 0x88, 0x88, 0x88, 0xF8, 0x88, 0x88, 0x88, 0x00 } },
+    { 'd', { 0x08, 0x08, 0x78, 0x88, 0x88, 0x88, 0x78, 0x00 } },
+    { 'e', { 0x00, 0x00, 0x70, 0x88, 0xF0, 0x80, 0x70, 0x00 } },
+    { 'l', { 0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x30, 0x00 } },
+    { 'o', { 0x00, 0x00, 0x70, 0x88, 0x88, 0x88, 0x70, 0x00 } },
+    { 'r', { 0x00, 0x00, 0xB0, 0x48, 0x40, 0x40, 0xE0, 0x00 } },
+    { 'w', { 0x00, 0x00, 0x88, 0x88, 0xA8, 0xF8, 0x50, 0x00 } },
+};
+
+/* write a register: the data byte first, then the register number with bit 7 set */
+static void write_register(tms9918a *vdp, uint8_t reg, uint8_t data) {
+    tms9918a_write_control(vdp, data);
+    tms9918a_write_control(vdp, 0x80 | reg);
+}
+
+/* set the address pointer for writing: the low byte first, then the high byte with bit 6 set */
+static void set_write_address(tms9918a *vdp, uint16_t address) {
+    tms9918a_write_control(vdp, address & 0xFF);
+    tms9918a_write_control(vdp, 0x40 | (address >> 8));
+}
+
+int main(void) {
+    static uint32_t frame[TMS9918A_MAX_FRAME_PIXELS];
+    const char *message = "Hello, world!";
+    int width, height;
+    size_t i;
+    int j;
+
+    tms9918a *vdp = tms9918a_new();
+    if (vdp == NULL) {
+        return 1;
+    }
+
+    /* register 0: disable bitmap mode, disable external video input */
+    write_register(vdp, 0, 0x00);
+    /* register 1: 16K memory, enable video output, use Text mode */
+    write_register(vdp, 1, 0xD0);
+    /* register 2: name table at 0x0000 */
+    write_register(vdp, 2, 0x00);
+    /* register 4: pattern table at 0x0800 */
+    write_register(vdp, 4, 0x01);
+    /* register 7: light red text on a black background */
+    write_register(vdp, 7, 0x91);
+
+    /* write the glyphs to the pattern table, each at the tile with the character's ASCII code */
+    for (i = 0; i < sizeof(glyphs) / sizeof(glyphs[0]); i++) {
+        set_write_address(vdp, 0x0800 + glyphs[i].c * 8);
+        for (j = 0; j < 8; j++) {
+            tms9918a_write_data(vdp, glyphs[i].pattern[j]);
+        }
+    }
+
+    /* clear the 40x24 name table, video memory contains random data on startup */
+    set_write_address(vdp, 0x0000);
+    for (j = 0; j < 40 * 24; j++) {
+        tms9918a_write_data(vdp, ' ');
+    }
+
+    /* write the message on the second row */
+    set_write_address(vdp, 40);
+    for (i = 0; i < strlen(message); i++) {
+        tms9918a_write_data(vdp, message[i]);
+    }
+
+    if (tms9918a_render(vdp, frame, &width, &height) != 0) {
+        tms9918a_free(vdp);
+        return 1;
+    }
+    tms9918a_free(vdp);
+
+    /* save the frame as a binary PPM image */
+    FILE *file = fopen("text_demo.ppm", "wb");
+    if (file == NULL) {
+        return 1;
+    }
+    fprintf(file, "P6\n%d %d\n255\n", width, height);
+    for (j = 0; j < width * height; j++) {
+        uint8_t rgb[3] = { frame[j] >> 16, frame[j] >> 8, frame[j] };
+        fwrite(rgb, 1, 3, file);
+    }
+    fclose(file);
+    printf("saved a %dx%d frame to text_demo.ppm\n", width, height);
+    return 0;
+}
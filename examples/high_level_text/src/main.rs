@@ -1,9 +1,10 @@
 // TMS9918A Text Mode example using high-level functions
 
 use minifb::{Scale, ScaleMode, Window, WindowOptions};
-use tms9918a_emu::{TMS9918A, VideoMode};
+use tms9918a_emu::{Color, TMS9918A, VideoMode};
+use tms9918a_emu::fonts::Font;
 
-fn main() {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     // create a new TMS9918A VDP instance
     let mut vdp = TMS9918A::new();
 
@@ -11,20 +12,17 @@ fn main() {
     let mut window = Window::new(
         "TMS9918A Text Mode Example (high-level)",
         256,
-        196,
+        192,
         WindowOptions {
             resize: true,
             scale_mode: ScaleMode::AspectRatioStretch,
             scale: Scale::X4,
             ..WindowOptions::default()
         },
-    )
-    .unwrap_or_else(|e| {
-        panic!("{}", e);
-    });
+    )?;
 
-    // limit to max ~60 fps update rate
-    window.limit_update_rate(Some(std::time::Duration::from_micros(16600)));
+    // limit the update rate to the VDP frame rate, ~60 fps for NTSC or ~50 fps for PAL
+    window.limit_update_rate(Some(vdp.frame_duration()));
 
     // set the name table base address to 0x0000 (base address = multiplier * 0x0400)
     vdp.set_name_table_multiplier(0);
@@ -35,22 +33,18 @@ fn main() {
     // use Text Mode, 40x24 tiles at 6x8 pixels each
     vdp.set_video_mode(VideoMode::Text);
 
-    // set foreground color to light red (0x9) and background color to black (0x1)
-    vdp.write_register(7, 0x91);
+    // set foreground color to light red and background color to black
+    vdp.set_text_color(Color::LightRed, Color::Black);
 
-    // fill pattern table with font data
-    let font = include_bytes!("font.bin");
-    vdp.fill_pattern_table(font, 0, font.len());
+    // fill pattern table with the built-in font
+    vdp.load_builtin_font(Font::Text6x8)?;
 
     // clear the screen
     // the video memory contains random data on startup, similar to how real memory works
     vdp.clear_name_table();
 
-    // write text by iterating over a string
-    let text_string = "Hello, world!";
-    for (i, c) in text_string.chars().enumerate() {
-        vdp.write_name_table(i+40, c as u8);
-    }
+    // write text on the second row of tiles
+    vdp.print_text(0, 1, "Hello, world!");
 
     // enable video output (sets the blanking bit in register 1)
     vdp.enable_video(true);
@@ -61,9 +55,10 @@ fn main() {
 
         window.update_with_buffer(
             &vdp.frame,
-            vdp.frame_width,
-            vdp.frame_height,
-        )
-        .unwrap();
+            vdp.frame_width(),
+            vdp.frame_height(),
+        )?;
     }
+
+    Ok(())
 }
@@ -34,9 +34,9 @@
 //    additional VRAM data bytes can be read from the data port without needing to send the address again
 
 use minifb::{Scale, ScaleMode, Window, WindowOptions};
-use tms9918a_emu::TMS9918A;
+use tms9918a_emu::{fonts, TMS9918A};
 
-fn main() {
+fn main() -> Result<(), minifb::Error> {
     // create a new TMS9918A VDP instance
     let mut vdp = TMS9918A::new();
 
@@ -44,25 +44,23 @@ fn main() {
     let mut window = Window::new(
         "TMS9918A Text Mode Example (low-level)",
         256,
-        196,
+        192,
         WindowOptions {
             resize: true,
             scale_mode: ScaleMode::AspectRatioStretch,
             scale: Scale::X4,
             ..WindowOptions::default()
         },
-    )
-    .unwrap_or_else(|e| {
-        panic!("{}", e);
-    });
+    )?;
 
-    // limit to max ~60 fps update rate
-    window.limit_update_rate(Some(std::time::Duration::from_micros(16600)));
+    // limit the update rate to the VDP frame rate, ~60 fps for NTSC or ~50 fps for PAL
+    window.limit_update_rate(Some(vdp.frame_duration()));
 
     // register 0: disable bitmap mode, disable external video input
     vdp.write_control_port(0b00000000);
     vdp.write_control_port(0x80);
-    // register 1: enable video output, use Text mode
+    // register 1: select 16K memory, enable video output, use Text mode
+    // without the 16K bit, the data port only addresses the first 4K of video memory
     vdp.write_control_port(0b11010000);
     vdp.write_control_port(0x81);
     // register 2: set the name table base address to 0x0000 (base address = multiplier * 0x0400)
@@ -79,9 +77,8 @@ fn main() {
     vdp.write_control_port(0x00);
     vdp.write_control_port(0x48); // 0x08 | 0x40
 
-    // fill pattern table with font data
-    let font = include_bytes!("font.bin");
-    for i in font.iter() {
+    // fill pattern table with the built-in font data
+    for i in fonts::TEXT_6X8.iter() {
         vdp.write_data_port(*i);
     }
 
@@ -111,9 +108,10 @@ fn main() {
 
         window.update_with_buffer(
             &vdp.frame,
-            vdp.frame_width,
-            vdp.frame_height,
-        )
-        .unwrap();
+            vdp.frame_width(),
+            vdp.frame_height(),
+        )?;
     }
+
+    Ok(())
 }
@@ -0,0 +1,27 @@
+// viewer for frames streamed by TMS9918A::serve_frames
+// usage: net_viewer [address], the address defaults to 127.0.0.1:7911
+
+use std::net::TcpStream;
+use tms9918a_emu::net::receive_frame;
+use tms9918a_emu::window::Window;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let address = std::env::args().nth(1).unwrap_or_else(|| "127.0.0.1:7911".to_string());
+    let mut stream = TcpStream::connect(&address)?;
+
+    // create a new window, the server sets the frame rate so don't limit updates
+    let mut window = Window::new(&format!("TMS9918A Viewer ({})", address))?;
+    window.set_update_rate(None);
+
+    // reuse the same buffer for every frame
+    let mut frame = Vec::new();
+    while window.is_open() {
+        // wait for the next frame from the server
+        let (width, height) = receive_frame(&mut stream, &mut frame)?;
+
+        // display the frame
+        window.inner_mut().update_with_buffer(&frame, width, height)?;
+    }
+
+    Ok(())
+}
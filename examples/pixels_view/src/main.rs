@@ -0,0 +1,75 @@
+// TMS9918A Text mode example drawn with the pixels crate in a winit window owned by the application
+
+use std::time::Instant;
+use tms9918a_emu::fonts::Font;
+use tms9918a_emu::pixels::PixelsView;
+use tms9918a_emu::{Color, TMS9918A, VideoMode};
+use winit::dpi::LogicalSize;
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::WindowBuilder;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // create a new TMS9918A VDP instance
+    let mut vdp = TMS9918A::new();
+
+    // the application owns the event loop and the window
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title("TMS9918A pixels Example")
+        .with_inner_size(LogicalSize::new(256.0 * 3.0, 192.0 * 3.0))
+        .with_min_inner_size(LogicalSize::new(256.0, 192.0))
+        .build(&event_loop)?;
+
+    // create the view with the window's size in physical pixels, so it is sharp on high DPI screens
+    let size = window.inner_size();
+    let mut view = PixelsView::new(&window, size.width, size.height, &vdp)?;
+
+    // set up Text mode with the built-in font, the same as the high_level_text example
+    vdp.set_name_table_multiplier(0);
+    vdp.set_pattern_table_multiplier(1);
+    vdp.set_video_mode(VideoMode::Text);
+    vdp.set_text_color(Color::LightRed, Color::Black);
+    vdp.load_builtin_font(Font::Text6x8)?;
+    vdp.clear_name_table();
+    vdp.print_text(0, 1, "Hello, world!");
+    vdp.enable_video(true);
+
+    let frame_duration = vdp.frame_duration();
+    let mut next_frame = Instant::now();
+
+    event_loop.run(move |event, _, control_flow| {
+        let result = match event {
+            Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
+                *control_flow = ControlFlow::Exit;
+                Ok(())
+            }
+
+            // keep the surface the same size as the window
+            Event::WindowEvent { event: WindowEvent::Resized(size), .. } => {
+                view.resize_surface(size.width, size.height)
+            }
+            Event::WindowEvent { event: WindowEvent::ScaleFactorChanged { new_inner_size, .. }, .. } => {
+                view.resize_surface(new_inner_size.width, new_inner_size.height)
+            }
+
+            // render a frame at the VDP frame rate, ~60 fps for NTSC or ~50 fps for PAL
+            Event::MainEventsCleared => {
+                if Instant::now() >= next_frame {
+                    next_frame += frame_duration;
+                    window.request_redraw();
+                }
+                *control_flow = ControlFlow::WaitUntil(next_frame);
+                Ok(())
+            }
+            Event::RedrawRequested(_) => view.update(&mut vdp),
+
+            _ => Ok(())
+        };
+
+        if let Err(error) = result {
+            eprintln!("{}", error);
+            *control_flow = ControlFlow::Exit;
+        }
+    });
+}
@@ -0,0 +1,65 @@
+// Rendering benchmark, measures the time to render a frame in Graphics I and Text mode
+//
+// Run it with optimizations: cargo run --release
+
+use std::time::Instant;
+use tms9918a_emu::fonts::Font;
+use tms9918a_emu::{Color, SpriteDef, TMS9918A, VideoMode, VramInit};
+
+const FRAMES: u32 = 2000;
+
+// render a number of frames and print the average time per frame
+fn measure(name: &str, vdp: &mut TMS9918A, redraw_tiles: bool) {
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        // drawing every tile again shows the cost of drawing tiles, not just sprites and the finished frame
+        if redraw_tiles {
+            vdp.invalidate_tiles();
+        }
+        vdp.render();
+    }
+    let per_frame = start.elapsed() / FRAMES;
+    println!("{:<32} {:>8.1} us per frame, {} tile lines drawn", name, per_frame.as_secs_f64() * 1_000_000.0, vdp.tile_lines_drawn());
+}
+
+fn main() {
+    // Graphics I mode, every tile on the screen is a different character with sprites on top
+    let mut gfx1 = TMS9918A::with_vram_init(VramInit::Zero);
+    gfx1.write_register(1, 0x80);
+    gfx1.set_name_table_multiplier(0);
+    gfx1.set_color_table_multiplier(0x20);
+    gfx1.set_pattern_table_multiplier(2);
+    gfx1.set_sprite_attribute_table_multiplier(0x0E);
+    gfx1.write_register(6, 0x03);
+    gfx1.load_builtin_font(Font::Text6x8).unwrap();
+    for group in 0..32 {
+        gfx1.write_ram(0x0800 + group, (group as u8) << 4 | 0x04);
+    }
+    for tile in 0..768 {
+        gfx1.write_ram(tile, tile as u8);
+    }
+    gfx1.set_sprite_pattern(0, &[0x3C, 0x7E, 0xFF, 0xFF, 0xFF, 0xFF, 0x7E, 0x3C]).unwrap();
+    for sprite in 0..8 {
+        gfx1.define_sprite(sprite, SpriteDef { x: sprite * 30, y: sprite * 20, pattern: 0, color: 0x9, early_clock: false }).unwrap();
+    }
+    gfx1.define_sprite(8, SpriteDef { x: 0, y: 0xD0, pattern: 0, color: 0, early_clock: false }).unwrap();
+    gfx1.set_backdrop_color(Color::DarkBlue);
+    gfx1.enable_video(true);
+
+    // Text mode, a full screen of text
+    let mut text = TMS9918A::with_vram_init(VramInit::Zero);
+    text.set_video_mode(VideoMode::Text);
+    text.set_name_table_multiplier(0);
+    text.set_pattern_table_multiplier(1);
+    text.set_text_color(Color::White, Color::DarkBlue);
+    text.load_builtin_font(Font::Text6x8).unwrap();
+    for row in 0..24 {
+        text.print_text(0, row, "The quick brown fox jumps over the lazy");
+    }
+    text.enable_video(true);
+
+    measure("Graphics I, all tiles drawn", &mut gfx1, true);
+    measure("Graphics I, static screen", &mut gfx1, false);
+    measure("Text, all tiles drawn", &mut text, true);
+    measure("Text, static screen", &mut text, false);
+}
@@ -0,0 +1,73 @@
+// TMS9918A Text mode example running in the browser, drawing each frame to an HTML canvas
+//
+// build with wasm-pack: wasm-pack build --target web
+// then serve this directory with any web server and open index.html
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use tms9918a_emu::fonts::Font;
+use tms9918a_emu::{Color, TMS9918A, VideoMode};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::{Clamped, JsCast};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
+
+// an animation frame callback which can be shared with itself
+type AnimationCallback = Rc<RefCell<Option<Closure<dyn FnMut()>>>>;
+
+#[wasm_bindgen(start)]
+pub fn start() -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or("no window")?;
+    let document = window.document().ok_or("no document")?;
+    let canvas: HtmlCanvasElement = document.get_element_by_id("screen").ok_or("no canvas with id \"screen\"")?.dyn_into()?;
+    let context: CanvasRenderingContext2d = canvas.get_context("2d")?.ok_or("no 2d context")?.dyn_into()?;
+
+    // create a new TMS9918A VDP instance, the core doesn't need a window
+    let mut vdp = TMS9918A::new();
+
+    // set up Text mode with the built-in font, the same as the high_level_text example
+    vdp.set_name_table_multiplier(0);
+    vdp.set_pattern_table_multiplier(1);
+    vdp.set_video_mode(VideoMode::Text);
+    vdp.set_text_color(Color::LightRed, Color::Black);
+    vdp.load_builtin_font(Font::Text6x8).map_err(|error| error.to_string())?;
+    vdp.clear_name_table();
+    vdp.print_text(0, 1, "Hello, browser!");
+    vdp.enable_video(true);
+
+    // the RGBA buffer is reused for every frame
+    let mut rgba = Vec::new();
+    let mut frames: u32 = 0;
+
+    // the callback schedules itself for the next animation frame, so it needs a shared reference to itself
+    let callback: AnimationCallback = Rc::new(RefCell::new(None));
+    let next_callback = callback.clone();
+    *callback.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+        // show the frame count to show that the screen is updating
+        frames = frames.wrapping_add(1);
+        vdp.print_text(0, 3, &format!("Frame {}", frames));
+
+        // render the frame and copy it into the canvas
+        vdp.render();
+        vdp.frame_as_rgba8(&mut rgba);
+        let (width, height) = (vdp.frame_width() as u32, vdp.frame_height() as u32);
+        if let Ok(image) = ImageData::new_with_u8_clamped_array_and_sh(Clamped(&rgba), width, height) {
+            let _ = context.put_image_data(&image, 0.0, 0.0);
+        }
+
+        if let Some(callback) = next_callback.borrow().as_ref() {
+            request_animation_frame(callback);
+        }
+    }) as Box<dyn FnMut()>));
+
+    if let Some(callback) = callback.borrow().as_ref() {
+        request_animation_frame(callback);
+    }
+    Ok(())
+}
+
+// run a callback before the browser draws the next frame, usually 60 times per second
+fn request_animation_frame(callback: &Closure<dyn FnMut()>) {
+    if let Some(window) = web_sys::window() {
+        let _ = window.request_animation_frame(callback.as_ref().unchecked_ref());
+    }
+}
@@ -0,0 +1,35 @@
+// Feed random bytes to the port interface of the VDP, which must never panic
+//
+// Run with `cargo fuzz run ports` from the crate directory, this needs a nightly compiler.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tms9918a_emu::{TMS9918A, VdpVariant, VramInit};
+
+fuzz_target!(|data: &[u8]| {
+    // the first byte selects the variant, the rest are pairs of an operation and its data
+    let variant = match data.first().copied().unwrap_or(0) & 0x03 {
+        0 => VdpVariant::Tms9918,
+        1 => VdpVariant::Tms9918a,
+        2 => VdpVariant::Tms9928a,
+        _ => VdpVariant::Tms9929a
+    };
+    let mut vdp = TMS9918A::with_variant_and_vram_init(variant, VramInit::Zero);
+    for pair in data.get(1..).unwrap_or(&[]).chunks_exact(2) {
+        let (operation, byte) = (pair[0], pair[1]);
+        match operation & 0x0F {
+            0..=3 => vdp.write_control_port(byte),
+            4..=6 => vdp.write_data_port(byte),
+            7 => { vdp.read_data_port(); },
+            8 => { vdp.read_status_port(); },
+            9 => vdp.write_register(operation >> 4, byte),
+            10 => { vdp.tick(byte as u32 * 64); },
+            11 => vdp.set_border_visible(byte & 1 != 0),
+            12 => vdp.write_data_block(&[byte; 300]),
+            13 => vdp.read_data_block(&mut [0; 300]),
+            _ => vdp.render()
+        }
+    }
+    vdp.render();
+});
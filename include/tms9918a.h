@@ -0,0 +1,63 @@
+/*
+ * C bindings for the TMS9918A VDP emulator's port level interface
+ *
+ * Build the library with the ffi feature, for example as a static library:
+ *     cargo rustc --release --no-default-features --features ffi --crate-type staticlib
+ *
+ * Every function accepts a null VDP pointer, which does nothing and reads as 0,
+ * and no function unwinds into C if the emulator panics.
+ */
+
+#ifndef TMS9918A_H
+#define TMS9918A_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* largest frame rendered by tms9918a_render, 284x243 pixels with the border visible */
+#define TMS9918A_MAX_FRAME_PIXELS (284 * 243)
+
+/* an emulated VDP, only used through pointers */
+typedef struct tms9918a tms9918a;
+
+/* create a new VDP, returns null if it can't be created, free it with tms9918a_free */
+tms9918a *tms9918a_new(void);
+
+/* free a VDP created by tms9918a_new */
+void tms9918a_free(tms9918a *vdp);
+
+/* write a byte to the control port */
+void tms9918a_write_control(tms9918a *vdp, uint8_t data);
+
+/* write a byte to the data port */
+void tms9918a_write_data(tms9918a *vdp, uint8_t data);
+
+/* read a byte from the data port */
+uint8_t tms9918a_read_data(tms9918a *vdp);
+
+/* read the status register, which clears the frame flag and interrupt */
+uint8_t tms9918a_read_status(tms9918a *vdp);
+
+/*
+ * get the size of the frames rendered by tms9918a_render, in pixels
+ * either size pointer can be null, returns 0 on success or -1 if the VDP pointer is null
+ */
+int tms9918a_frame_size(tms9918a *vdp, int *width, int *height);
+
+/*
+ * render a frame and copy it to out as 0x00RRGGBB pixels, row by row from the top left
+ * the frame size is written to width and height, either can be null
+ * out can be null to only render the frame, otherwise it must have room for the whole frame,
+ * which is at most TMS9918A_MAX_FRAME_PIXELS pixels
+ * returns 0 on success or -1 if the VDP pointer is null or rendering failed
+ */
+int tms9918a_render(tms9918a *vdp, uint32_t *out, int *width, int *height);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TMS9918A_H */
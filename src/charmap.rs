@@ -0,0 +1,93 @@
+//! Character to tile translation for text output
+
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::hash::BuildHasher;
+
+/// Translation from characters to name table entries, used by [`TMS9918A::print_text`](crate::TMS9918A::print_text)
+/// and [`TextConsole`](crate::TextConsole)
+///
+/// Characters without a tile are written as the fallback tile set by
+/// [`TMS9918A::set_fallback_tile`](crate::TMS9918A::set_fallback_tile).
+///
+/// # Examples
+///
+/// ```
+/// # use tms9918a_emu::{CharMap, TMS9918A};
+/// // a font with only the digits, starting at tile 0x80
+/// struct Digits;
+///
+/// impl CharMap for Digits {
+///     fn map(&self, c: char) -> Option<u8> {
+///         c.to_digit(10).map(|digit| 0x80 + digit as u8)
+///     }
+/// }
+///
+/// let mut vdp = TMS9918A::new();
+/// vdp.set_charmap(Digits);
+/// vdp.set_fallback_tile(0);
+/// vdp.print_text(0, 0, "1a");
+/// assert_eq!(vdp.read_name_table_xy(0, 0), Ok(0x81));
+/// assert_eq!(vdp.read_name_table_xy(1, 0), Ok(0));
+/// ```
+pub trait CharMap {
+    /// Get the tile for a character, or `None` if the font has no tile for it
+    fn map(&self, c: char) -> Option<u8>;
+}
+
+/// Characters up to 0xFF use the tile with the same number, this is the default
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Identity;
+
+impl CharMap for Identity {
+    fn map(&self, c: char) -> Option<u8> {
+        if (c as u32) <= 0xFF { Some(c as u8) } else { None }
+    }
+}
+
+/// ASCII characters use the tile with the same number plus an offset
+///
+/// For example a font starting with the space character at tile 0 uses an offset of -32.
+/// Non-ASCII characters, and characters with a tile outside of 0-255, are not mapped.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct AsciiOffset(pub i16);
+
+impl CharMap for AsciiOffset {
+    fn map(&self, c: char) -> Option<u8> {
+        if !c.is_ascii() {
+            return None;
+        }
+        let tile = c as i16 + self.0;
+        if (0..=0xFF).contains(&tile) { Some(tile as u8) } else { None }
+    }
+}
+
+/// Custom translation of each character, characters missing from the map are not mapped
+///
+/// # Examples
+///
+/// ```
+/// # use tms9918a_emu::TMS9918A;
+/// use std::collections::HashMap;
+///
+/// let mut vdp = TMS9918A::new();
+/// let charmap: HashMap<char, u8> = [('♥', 0x03), ('→', 0x1A)].iter().cloned().collect();
+/// vdp.set_charmap(charmap);
+/// vdp.print_text(0, 0, "♥→");
+/// assert_eq!(vdp.read_name_table_xy(1, 0), Ok(0x1A));
+/// ```
+#[cfg(feature = "std")]
+impl<S: BuildHasher> CharMap for HashMap<char, u8, S> {
+    fn map(&self, c: char) -> Option<u8> {
+        self.get(&c).copied()
+    }
+}
+
+/// Custom translation of each character, the same as the `HashMap` translation but also available without `std`
+impl CharMap for BTreeMap<char, u8> {
+    fn map(&self, c: char) -> Option<u8> {
+        self.get(&c).copied()
+    }
+}
@@ -0,0 +1,193 @@
+//! Terminal-like text output to the name table
+
+use crate::TMS9918A;
+use core::fmt;
+
+// console state kept in the VDP, so the cursor survives between calls to TMS9918A::console
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub(crate) struct ConsoleState {
+    x: usize,
+    y: usize,
+    cursor_visible: bool,
+    cursor_tile: u8,
+    // the tile under the cursor while the cursor is displayed
+    hidden_tile: Option<u8>
+}
+
+/// Terminal-like text output to the name table, returned by [`TMS9918A::console`]
+///
+/// The console tracks a cursor position, wraps at the right edge of the screen, and scrolls the
+/// name table up one row when the cursor moves past the last row. `'\n'` moves the cursor to the
+/// start of the next row, and `'\r'` moves it to the start of the current row.
+/// Characters are written the same way as [`TMS9918A::print_text`], including the charmap.
+///
+/// # Examples
+///
+/// ```
+/// # use tms9918a_emu::{TMS9918A, VideoMode};
+/// use std::fmt::Write;
+///
+/// let mut vdp = TMS9918A::new();
+/// vdp.set_video_mode(VideoMode::Text);
+/// vdp.clear_name_table();
+///
+/// write!(vdp.console(), "score: {}\nlives: {}", 100, 3).unwrap();
+/// assert_eq!(vdp.read_name_table_xy(7, 0), Ok(b'1'));
+/// assert_eq!(vdp.read_name_table_xy(7, 1), Ok(b'3'));
+/// assert_eq!(vdp.console().cursor(), (8, 1));
+///
+/// // scroll the first row off the screen
+/// vdp.console().set_cursor(0, 23);
+/// writeln!(vdp.console()).unwrap();
+/// assert_eq!(vdp.read_name_table_xy(7, 0), Ok(b'3'));
+/// ```
+pub struct TextConsole<'a> {
+    vdp: &'a mut TMS9918A
+}
+
+impl<'a> TextConsole<'a> {
+    pub(crate) fn new(vdp: &'a mut TMS9918A) -> Self {
+        TextConsole { vdp }
+    }
+
+    /// Get the cursor position as (column, row)
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.vdp.console.x, self.vdp.console.y)
+    }
+
+    /// Move the cursor to a tile position
+    ///
+    /// Positions outside of the screen are clamped to the last column and row.
+    pub fn set_cursor(&mut self, x: usize, y: usize) {
+        self.hide_cursor();
+        self.vdp.console.x = x.min(self.vdp.name_table_columns() - 1);
+        self.vdp.console.y = y.min(23);
+        self.show_cursor();
+    }
+
+    /// Show or hide the cursor
+    ///
+    /// While the cursor is shown, the tile under it is replaced by the cursor tile, and restored when the cursor moves.
+    /// The cursor is hidden by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// use std::fmt::Write;
+    ///
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.clear_name_table();
+    /// let mut console = vdp.console();
+    /// console.set_cursor_tile(0xDB);
+    /// console.set_cursor_visible(true);
+    /// console.write_str("ok").unwrap();
+    /// console.set_cursor_visible(false);
+    /// assert_eq!(vdp.read_name_table_xy(1, 0), Ok(b'k'));
+    /// assert_eq!(vdp.read_name_table_xy(2, 0), Ok(0));
+    /// vdp.console().set_cursor_visible(true);
+    /// assert_eq!(vdp.read_name_table_xy(2, 0), Ok(0xDB));
+    /// ```
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.hide_cursor();
+        self.vdp.console.cursor_visible = visible;
+        self.show_cursor();
+    }
+
+    /// Set the tile displayed at the cursor position while the cursor is shown
+    ///
+    /// This is a name table entry, the charmap is not applied. The cursor tile is 0 by default.
+    pub fn set_cursor_tile(&mut self, tile: u8) {
+        self.hide_cursor();
+        self.vdp.console.cursor_tile = tile;
+        self.show_cursor();
+    }
+
+    /// Scroll the name table up one row and clear the last row
+    pub fn scroll(&mut self) {
+        self.hide_cursor();
+        self.scroll_up();
+        self.show_cursor();
+    }
+
+    // write one character at the cursor and advance the cursor, the cursor must be hidden
+    fn put_char(&mut self, c: char) {
+        let columns = self.vdp.name_table_columns();
+        match c {
+            '\n' => self.new_line(),
+            '\r' => self.vdp.console.x = 0,
+            _ => {
+                // the video mode may have changed since the cursor was moved
+                if self.vdp.console.x >= columns {
+                    self.new_line();
+                }
+                let tile = self.vdp.map_char(c);
+                let offset = self.vdp.console.y * columns + self.vdp.console.x;
+                self.vdp.write_name_table_raw(offset, tile);
+                self.vdp.console.x += 1;
+                if self.vdp.console.x >= columns {
+                    self.new_line();
+                }
+            }
+        }
+    }
+
+    // move the cursor to the start of the next row, scrolling past the last row
+    fn new_line(&mut self) {
+        self.vdp.console.x = 0;
+        if self.vdp.console.y >= 23 {
+            self.vdp.console.y = 23;
+            self.scroll_up();
+        } else {
+            self.vdp.console.y += 1;
+        }
+    }
+
+    // move the name table up one row as a block and clear the last row with spaces
+    fn scroll_up(&mut self) {
+        let columns = self.vdp.name_table_columns();
+        let size = self.vdp.name_table_size();
+        for offset in columns..size {
+            let tile = self.vdp.read_name_table(offset);
+            self.vdp.write_name_table_raw(offset - columns, tile);
+        }
+        let space = self.vdp.map_char(' ');
+        for offset in size - columns..size {
+            self.vdp.write_name_table_raw(offset, space);
+        }
+    }
+
+    // restore the tile under the cursor
+    fn hide_cursor(&mut self) {
+        if let Some(tile) = self.vdp.console.hidden_tile.take() {
+            let offset = self.cursor_offset();
+            self.vdp.write_name_table_raw(offset, tile);
+        }
+    }
+
+    // save the tile under the cursor and display the cursor tile
+    fn show_cursor(&mut self) {
+        if self.vdp.console.cursor_visible && self.vdp.console.hidden_tile.is_none() {
+            let offset = self.cursor_offset();
+            self.vdp.console.hidden_tile = Some(self.vdp.read_name_table(offset));
+            self.vdp.write_name_table_raw(offset, self.vdp.console.cursor_tile);
+        }
+    }
+
+    // name table offset of the cursor position, kept inside of the screen
+    fn cursor_offset(&self) -> usize {
+        let columns = self.vdp.name_table_columns();
+        self.vdp.console.y.min(23) * columns + self.vdp.console.x.min(columns - 1)
+    }
+}
+
+impl fmt::Write for TextConsole<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.hide_cursor();
+        for c in s.chars() {
+            self.put_char(c);
+        }
+        self.show_cursor();
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+//! Comparing the emulation state of two VDPs
+
+use crate::{TMS9918A, VideoMode};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Differences between the emulation state of two VDPs, returned by [`TMS9918A::diff`]
+///
+/// Values are given as (value in `self`, value in `other`). The `Display` implementation lists one difference on
+/// each line, or "no differences".
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct StateDiff {
+    /// Registers with different values, as (register, value in `self`, value in `other`)
+    pub registers: Vec<(u8, u8, u8)>,
+    /// Runs of different video memory bytes, as (start address, length)
+    pub vram: Vec<(usize, usize)>,
+    /// Different address pointers
+    pub address_pointer: Option<(u16, u16)>,
+    /// Different control port latches, `Some` with the saved first byte of a command or `None` when empty
+    pub latch: Option<(Option<u8>, Option<u8>)>,
+    /// Different read-ahead buffers
+    pub read_ahead: Option<(u8, u8)>,
+    /// Different status registers
+    pub status: Option<(u8, u8)>,
+    /// Different video modes
+    pub mode: Option<(VideoMode, VideoMode)>
+}
+
+impl StateDiff {
+    /// Check if there are no differences
+    pub fn is_empty(&self) -> bool {
+        self.registers.is_empty()
+            && self.vram.is_empty()
+            && self.address_pointer.is_none()
+            && self.latch.is_none()
+            && self.read_ahead.is_none()
+            && self.status.is_none()
+            && self.mode.is_none()
+    }
+
+    // remove all differences, keeping the allocations
+    fn clear(&mut self) {
+        self.registers.clear();
+        self.vram.clear();
+        self.address_pointer = None;
+        self.latch = None;
+        self.read_ahead = None;
+        self.status = None;
+        self.mode = None;
+    }
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no differences");
+        }
+        // a line break before every difference except the first
+        let mut separator = "";
+        for (register, a, b) in &self.registers {
+            write!(f, "{}R{}: {:02X} -> {:02X}", separator, register, a, b)?;
+            separator = "\n";
+        }
+        for (start, length) in &self.vram {
+            write!(f, "{}VRAM {:04X}-{:04X}: {} bytes", separator, start, start + length - 1, length)?;
+            separator = "\n";
+        }
+        if let Some((a, b)) = self.address_pointer {
+            write!(f, "{}address pointer: {:04X} -> {:04X}", separator, a, b)?;
+            separator = "\n";
+        }
+        if let Some((a, b)) = self.latch {
+            write!(f, "{}latch: ", separator)?;
+            write_latch(f, a)?;
+            write!(f, " -> ")?;
+            write_latch(f, b)?;
+            separator = "\n";
+        }
+        if let Some((a, b)) = self.read_ahead {
+            write!(f, "{}read-ahead: {:02X} -> {:02X}", separator, a, b)?;
+            separator = "\n";
+        }
+        if let Some((a, b)) = self.status {
+            write!(f, "{}status: {:02X} -> {:02X}", separator, a, b)?;
+            separator = "\n";
+        }
+        if let Some((a, b)) = self.mode {
+            write!(f, "{}mode: {:?} -> {:?}", separator, a, b)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_latch(f: &mut fmt::Formatter, latch: Option<u8>) -> fmt::Result {
+    match latch {
+        Some(byte) => write!(f, "{:02X}", byte),
+        None => write!(f, "empty")
+    }
+}
+
+// pairs of different values
+fn changed<T: PartialEq>(a: T, b: T) -> Option<(T, T)> {
+    if a != b { Some((a, b)) } else { None }
+}
+
+impl TMS9918A {
+    /// Compare the emulation state with another VDP
+    ///
+    /// This lists the registers, video memory, address pointer, control port latch, read-ahead buffer, status
+    /// register and video mode which are different. Different video memory bytes next to each other are combined
+    /// into runs. The framebuffer and settings which aren't part of the VDP, such as the palette, aren't compared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// let mut a = TMS9918A::new();
+    /// let mut b = a.clone();
+    /// assert!(a.diff(&b).is_empty());
+    ///
+    /// b.write_register(7, 0xF4);
+    /// b.set_video_mode(VideoMode::Text);
+    /// b.write_control_port(0x00);
+    /// b.write_control_port(0x41);
+    /// b.write_data_block(&[!a.vram()[0x100], !a.vram()[0x101], a.vram()[0x102], !a.vram()[0x103]]);
+    ///
+    /// let diff = a.diff(&b);
+    /// assert_eq!(diff.vram, [(0x100, 2), (0x103, 1)]);
+    /// assert_eq!(diff.address_pointer, Some((0x0000, 0x0104)));
+    /// assert_eq!(diff.to_string().lines().next(), Some("R1: 00 -> 10"));
+    /// assert_eq!(diff.to_string().lines().last(), Some("mode: Gfx1 -> Text"));
+    /// ```
+    pub fn diff(&self, other: &TMS9918A) -> StateDiff {
+        let mut diff = StateDiff::default();
+        self.diff_into(other, &mut diff);
+        diff
+    }
+
+    /// Compare the emulation state with another VDP, reusing the allocations of a previous [`StateDiff`]
+    ///
+    /// This is the same as [`diff`](TMS9918A::diff), for comparing every frame without allocating.
+    pub fn diff_into(&self, other: &TMS9918A, diff: &mut StateDiff) {
+        diff.clear();
+        for (register, (&a, &b)) in self.vdp_register.iter().zip(other.vdp_register.iter()).enumerate() {
+            if a != b {
+                diff.registers.push((register as u8, a, b));
+            }
+        }
+
+        let mut run_start = None;
+        for (address, (a, b)) in self.vdp_ram.iter().zip(other.vdp_ram.iter()).enumerate() {
+            match (a != b, run_start) {
+                (true, None) => run_start = Some(address),
+                (false, Some(start)) => {
+                    diff.vram.push((start, address - start));
+                    run_start = None;
+                },
+                _ => {}
+            }
+        }
+        if let Some(start) = run_start {
+            diff.vram.push((start, self.vdp_ram.len() - start));
+        }
+
+        let latch = |vdp: &TMS9918A| if vdp.vdp_first_byte_saved_flag { Some(vdp.vdp_temp_data) } else { None };
+        diff.address_pointer = changed(self.vdp_addr_pointer, other.vdp_addr_pointer);
+        diff.latch = changed(latch(self), latch(other));
+        diff.read_ahead = changed(self.vdp_read_ahead, other.vdp_read_ahead);
+        diff.status = changed(self.vdp_status, other.vdp_status);
+        diff.mode = changed(self.vdp_mode, other.vdp_mode);
+    }
+}
@@ -0,0 +1,158 @@
+//! Pluggable display backends for presenting the TMS9918A framebuffer
+//!
+//! The emulation core in [`TMS9918A`] never touches the display. A [`DisplayBackend`] shows rendered frames
+//! somewhere, and [`Display`] pairs a boxed backend with the VDP it presents, so an application can pick a
+//! backend at runtime. Running headless is just not creating a display.
+//!
+//! The backends in this crate are [`window::Window`](crate::window) with the `window` feature,
+//! [`fbdev::Framebuffer`](crate::fbdev) with the `fbdev` feature and [`pixels::PixelsView`](crate::pixels)
+//! with the `pixels` feature. Other backends can be added outside of this crate by implementing the trait.
+
+use crate::{TMS9918A, TMS9918AError};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// Input events reported by a display backend
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+    /// The display was closed, for example the window's close button was clicked
+    Closed,
+    /// The display area was resized, in pixels
+    Resized {
+        /// New width
+        width: usize,
+        /// New height
+        height: usize
+    },
+    /// A key was pressed
+    KeyDown(Key),
+    /// A key was released
+    KeyUp(Key)
+}
+
+/// Keys reported by [`Event::KeyDown`] and [`Event::KeyUp`]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Key {
+    /// A key which types a character, letters are lowercase
+    Char(char),
+    /// Enter or return
+    Enter,
+    /// Escape
+    Escape,
+    /// Backspace
+    Backspace,
+    /// Tab
+    Tab,
+    /// Up arrow
+    Up,
+    /// Down arrow
+    Down,
+    /// Left arrow
+    Left,
+    /// Right arrow
+    Right,
+    /// Either shift key
+    Shift,
+    /// Either control key
+    Control,
+    /// Either alt key
+    Alt,
+    /// A function key, 1 for F1
+    Function(u8),
+    /// Any other key
+    Other
+}
+
+/// A place to show rendered frames, such as a window or a screen
+///
+/// # Examples
+///
+/// ```
+/// # use tms9918a_emu::display::{Display, DisplayBackend};
+/// # use tms9918a_emu::{TMS9918A, TMS9918AError};
+/// // a backend which counts the frames it is given
+/// #[derive(Default)]
+/// struct FrameCounter {
+///     frames: usize
+/// }
+///
+/// impl DisplayBackend for FrameCounter {
+///     fn present(&mut self, frame: &[u32], width: usize, height: usize) -> Result<(), TMS9918AError> {
+///         assert_eq!(frame.len(), width * height);
+///         self.frames += 1;
+///         Ok(())
+///     }
+/// }
+///
+/// let mut vdp = TMS9918A::new();
+/// let mut display = Display::new(FrameCounter::default());
+/// display.update(&mut vdp).unwrap();
+/// assert!(display.is_open());
+/// assert_eq!(display.poll(), []);
+/// ```
+pub trait DisplayBackend {
+    /// Show a frame of `width` x `height` pixels in the `0x00RRGGBB` format, row by row from the top left
+    fn present(&mut self, frame: &[u32], width: usize, height: usize) -> Result<(), TMS9918AError>;
+
+    /// Return true if the display is still open, this is always true for displays which can't be closed
+    fn is_open(&self) -> bool {
+        true
+    }
+
+    /// Take the input events which happened since the last call, none for displays without input
+    fn poll(&mut self) -> Vec<Event> {
+        Vec::new()
+    }
+}
+
+/// A display backend chosen at runtime, presenting the framebuffer of a TMS9918A
+///
+/// The VDP is passed to each call rather than owned, so the emulation core can stay on another thread.
+pub struct Display {
+    backend: Box<dyn DisplayBackend>
+}
+
+impl Display {
+    /// Create a display with a backend
+    pub fn new<B: DisplayBackend + 'static>(backend: B) -> Self {
+        Display { backend: Box::new(backend) }
+    }
+
+    /// Create a display with an already boxed backend
+    pub fn from_boxed(backend: Box<dyn DisplayBackend>) -> Self {
+        Display { backend }
+    }
+
+    /// Return true if the display is still open
+    pub fn is_open(&self) -> bool {
+        self.backend.is_open()
+    }
+
+    /// Take the input events which happened since the last call
+    pub fn poll(&mut self) -> Vec<Event> {
+        self.backend.poll()
+    }
+
+    /// Render the VDP framebuffer and show it
+    ///
+    /// This is the same as calling [`TMS9918A::render`] followed by [`present`](Display::present).
+    pub fn update(&mut self, vdp: &mut TMS9918A) -> Result<(), TMS9918AError> {
+        vdp.render();
+        self.present(vdp)
+    }
+
+    /// Show the current VDP framebuffer without rendering a new frame
+    pub fn present(&mut self, vdp: &TMS9918A) -> Result<(), TMS9918AError> {
+        self.backend.present(&vdp.frame, vdp.frame_width(), vdp.frame_height())
+    }
+
+    /// Return a reference to the backend
+    pub fn backend(&self) -> &dyn DisplayBackend {
+        self.backend.as_ref()
+    }
+
+    /// Return a mutable reference to the backend
+    pub fn backend_mut(&mut self) -> &mut dyn DisplayBackend {
+        self.backend.as_mut()
+    }
+}
@@ -0,0 +1,110 @@
+//! Image export of the framebuffer and video memory contents, only built with the `image` feature
+
+use crate::{Color, TMS9918A, TMS9918AError};
+use std::path::Path;
+
+impl TMS9918A {
+    /// Save the framebuffer as a PNG image
+    ///
+    /// The image is [`frame_width`](TMS9918A::frame_width) x [`frame_height`](TMS9918A::frame_height) pixels,
+    /// and contains the last frame rendered by [`update`](TMS9918A::update) or [`render`](TMS9918A::render),
+    /// so no window is needed. This is only available with the `image` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tms9918a_emu::TMS9918A;
+    /// # fn main() -> Result<(), tms9918a_emu::TMS9918AError> {
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.render();
+    /// vdp.screenshot("screenshot.png")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn screenshot<P: AsRef<Path>>(&self, path: P) -> Result<(), TMS9918AError> {
+        self.frame_image()
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(|error| TMS9918AError::Image(error.to_string()))
+    }
+
+    /// Copy the framebuffer into an RGB image
+    ///
+    /// This is only available with the `image` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::{Color, TMS9918A};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_backdrop_color(Color::LightBlue);
+    /// vdp.render();
+    /// let image = vdp.frame_image();
+    /// assert_eq!(image.dimensions(), (vdp.frame_width() as u32, vdp.frame_height() as u32));
+    /// assert_eq!(image.get_pixel(0, 0).0, [0x7D, 0x75, 0xFC]);
+    /// ```
+    pub fn frame_image(&self) -> image::RgbImage {
+        let (width, height) = (self.frame_width, self.frame_height);
+        image::RgbImage::from_fn(width as u32, height as u32, |x, y| rgb(self.frame[y as usize * width + x as usize]))
+    }
+
+    /// Save the tiles in the pattern table as a PNG image, in a grid with `columns` tiles on each row
+    ///
+    /// Pattern table offset register must be set first.
+    ///
+    /// All 256 tiles are drawn in tile order, or all 768 tiles in Graphics II mode, so each third of the screen
+    /// starts on a new row of a 16 or 32 column grid. Set pixels use the foreground color and clear pixels use
+    /// the background color, in the current palette. This is only available with the `image` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tms9918a_emu::{Color, TMS9918A};
+    /// # fn main() -> Result<(), tms9918a_emu::TMS9918AError> {
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_pattern_table_multiplier(1);
+    /// // 16x16 tiles, 128x128 pixels
+    /// vdp.export_patterns_png("patterns.png", 16, Color::White, Color::Black)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_patterns_png<P: AsRef<Path>>(&self, path: P, columns: usize, foreground: Color, background: Color) -> Result<(), TMS9918AError> {
+        let base = self.vdp_pattern_table_offset as usize;
+        let tiles = self.pattern_table_size() / 8;
+        self.export_tiles_png(path.as_ref(), base, tiles, columns, foreground, background)
+    }
+
+    /// Save the 256 8x8 patterns in the sprite pattern table as a PNG image, in a grid with `columns` patterns on each row
+    ///
+    /// Sprite pattern table offset register must be set first.
+    ///
+    /// A 16x16 sprite uses 4 consecutive patterns, so an 8 column grid shows each 16x16 sprite as two pairs of
+    /// patterns, in the order top left, bottom left, top right, bottom right. This is only available with the `image` feature.
+    pub fn export_sprite_patterns_png<P: AsRef<Path>>(&self, path: P, columns: usize, foreground: Color, background: Color) -> Result<(), TMS9918AError> {
+        let base = self.vdp_sprite_pattern_offset as usize;
+        self.export_tiles_png(path.as_ref(), base, 256, columns, foreground, background)
+    }
+
+    // draw 8x8 patterns starting at a video memory address in a grid and save it as a PNG image
+    fn export_tiles_png(&self, path: &Path, base: usize, tiles: usize, columns: usize, foreground: Color, background: Color) -> Result<(), TMS9918AError> {
+        let columns = columns.max(1);
+        let rows = tiles.div_ceil(columns);
+        let colors = [rgb(background.to_rgb(&self.palette)), rgb(foreground.to_rgb(&self.palette))];
+        let mut image = image::RgbImage::new(columns as u32 * 8, rows as u32 * 8);
+        for tile in 0..tiles {
+            let (tile_x, tile_y) = (tile % columns * 8, tile / columns * 8);
+            for row in 0..8 {
+                let pattern = self.vdp_ram[(base + tile * 8 + row) & 0x3FFF];
+                for x in 0..8 {
+                    let color = colors[(pattern >> (7 - x) & 1) as usize];
+                    image.put_pixel((tile_x + x) as u32, (tile_y + row) as u32, color);
+                }
+            }
+        }
+        image.save_with_format(path, image::ImageFormat::Png).map_err(|error| TMS9918AError::Image(error.to_string()))
+    }
+}
+
+// convert a 0x00RRGGBB framebuffer color to an image pixel
+fn rgb(color: u32) -> image::Rgb<u8> {
+    image::Rgb([(color >> 16) as u8, (color >> 8) as u8, color as u8])
+}
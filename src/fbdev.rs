@@ -0,0 +1,234 @@
+//! Linux framebuffer device for displaying the TMS9918A framebuffer without a window system
+//!
+//! This module is only available on Linux with the `fbdev` feature. It draws directly to a framebuffer
+//! device such as `/dev/fb0`, for example on a Raspberry Pi console without X or Wayland, where
+//! [`window::Window`](crate::window) can't open a window. [`Framebuffer`] has the same
+//! [`update`](Framebuffer::update) and [`present`](Framebuffer::present) functions as the window.
+
+use crate::display::DisplayBackend;
+use crate::{TMS9918A, TMS9918AError};
+use std::fs::{File, OpenOptions};
+use std::os::raw::{c_int, c_ulong};
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+// ioctl requests from linux/fb.h
+const FBIOGET_VSCREENINFO: c_ulong = 0x4600;
+const FBIOGET_FSCREENINFO: c_ulong = 0x4602;
+
+extern "C" {
+    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+}
+
+// struct fb_bitfield from linux/fb.h
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct FbBitfield {
+    offset: u32,
+    length: u32,
+    msb_right: u32
+}
+
+// struct fb_var_screeninfo from linux/fb.h, only some fields are used
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Default)]
+struct FbVarScreeninfo {
+    xres: u32,
+    yres: u32,
+    xres_virtual: u32,
+    yres_virtual: u32,
+    xoffset: u32,
+    yoffset: u32,
+    bits_per_pixel: u32,
+    grayscale: u32,
+    red: FbBitfield,
+    green: FbBitfield,
+    blue: FbBitfield,
+    transp: FbBitfield,
+    nonstd: u32,
+    activate: u32,
+    height: u32,
+    width: u32,
+    accel_flags: u32,
+    pixclock: u32,
+    left_margin: u32,
+    right_margin: u32,
+    upper_margin: u32,
+    lower_margin: u32,
+    hsync_len: u32,
+    vsync_len: u32,
+    sync: u32,
+    vmode: u32,
+    rotate: u32,
+    colorspace: u32,
+    reserved: [u32; 4]
+}
+
+// struct fb_fix_screeninfo from linux/fb.h, only some fields are used
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Default)]
+struct FbFixScreeninfo {
+    id: [u8; 16],
+    smem_start: c_ulong,
+    smem_len: u32,
+    type_: u32,
+    type_aux: u32,
+    visual: u32,
+    xpanstep: u16,
+    ypanstep: u16,
+    ywrapstep: u16,
+    line_length: u32,
+    mmio_start: c_ulong,
+    mmio_len: u32,
+    accel: u32,
+    capabilities: u16,
+    reserved: [u16; 2]
+}
+
+/// A Linux framebuffer device displaying the framebuffer of a TMS9918A
+///
+/// The frame is scaled by the largest whole number that fits on the screen and centered, with the rest of the
+/// screen black. Frames are converted to the pixel format of the device, which must have 16, 24 or 32 bits per pixel.
+pub struct Framebuffer {
+    device: File,
+    // visible resolution in pixels
+    width: usize,
+    height: usize,
+    bytes_per_pixel: usize,
+    line_length: usize,
+    // byte offset of the visible area, for devices panned inside a larger virtual screen
+    origin: usize,
+    // position and size of the red, green and blue bits in a pixel
+    channels: [FbBitfield; 3],
+    // frame size when the screen was last cleared
+    frame_size: Option<(usize, usize)>,
+    // rows of the screen covered by the frame, reused for each frame
+    buffer: Vec<u8>
+}
+
+impl Framebuffer {
+    /// Open the first framebuffer device, `/dev/fb0`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tms9918a_emu::TMS9918A;
+    /// # use tms9918a_emu::fbdev::Framebuffer;
+    /// # fn main() -> Result<(), tms9918a_emu::TMS9918AError> {
+    /// let mut vdp = TMS9918A::new();
+    /// let mut framebuffer = Framebuffer::new()?;
+    ///
+    /// loop {
+    ///     framebuffer.update(&mut vdp)?;
+    ///     std::thread::sleep(vdp.frame_duration());
+    /// }
+    /// # }
+    /// ```
+    pub fn new() -> Result<Self, TMS9918AError> {
+        Self::open("/dev/fb0")
+    }
+
+    /// Open a framebuffer device
+    ///
+    /// Returns an error if the device can't be opened, isn't a framebuffer device, or has an unsupported pixel format.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, TMS9918AError> {
+        let to_error = |error: std::io::Error| TMS9918AError::WindowCreation(error.to_string());
+        let device = OpenOptions::new().read(true).write(true).open(path).map_err(to_error)?;
+
+        let mut var_info = FbVarScreeninfo::default();
+        let mut fix_info = FbFixScreeninfo::default();
+        // the structs match the kernel's layout, and the device stays open during the calls
+        let result = unsafe {
+            let fd = device.as_raw_fd();
+            if ioctl(fd, FBIOGET_VSCREENINFO, &mut var_info as *mut FbVarScreeninfo) < 0 {
+                -1
+            } else {
+                ioctl(fd, FBIOGET_FSCREENINFO, &mut fix_info as *mut FbFixScreeninfo)
+            }
+        };
+        if result < 0 {
+            return Err(to_error(std::io::Error::last_os_error()));
+        }
+
+        let bytes_per_pixel = match var_info.bits_per_pixel {
+            16 | 24 | 32 => var_info.bits_per_pixel as usize / 8,
+            bits => return Err(TMS9918AError::WindowCreation(format!("unsupported pixel format: {} bits per pixel", bits)))
+        };
+        let line_length = fix_info.line_length as usize;
+        Ok(Framebuffer {
+            device,
+            width: var_info.xres as usize,
+            height: var_info.yres as usize,
+            bytes_per_pixel,
+            line_length,
+            origin: var_info.yoffset as usize * line_length + var_info.xoffset as usize * bytes_per_pixel,
+            channels: [var_info.red, var_info.green, var_info.blue],
+            frame_size: None,
+            buffer: Vec::new()
+        })
+    }
+
+    /// Get the resolution of the screen in pixels, as (width, height)
+    pub fn resolution(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Render the VDP framebuffer and display it on the screen
+    ///
+    /// This is the same as calling [`TMS9918A::render`] followed by [`present`](Framebuffer::present).
+    pub fn update(&mut self, vdp: &mut TMS9918A) -> Result<(), TMS9918AError> {
+        vdp.render();
+        self.present(vdp)
+    }
+
+    /// Display the current VDP framebuffer on the screen without rendering a new frame
+    pub fn present(&mut self, vdp: &TMS9918A) -> Result<(), TMS9918AError> {
+        DisplayBackend::present(self, &vdp.frame, vdp.frame_width(), vdp.frame_height())
+    }
+}
+
+impl DisplayBackend for Framebuffer {
+    fn present(&mut self, frame: &[u32], frame_width: usize, frame_height: usize) -> Result<(), TMS9918AError> {
+        let to_error = |error: std::io::Error| TMS9918AError::WindowUpdate(error.to_string());
+        if self.frame_size != Some((frame_width, frame_height)) {
+            // clear the whole screen when the frame size changes, for example when the border is shown
+            let screen = vec![0; self.height * self.line_length];
+            self.device.write_all_at(&screen, self.origin as u64).map_err(to_error)?;
+            self.frame_size = Some((frame_width, frame_height));
+        }
+
+        // largest whole scale that fits, frames larger than the screen are cropped
+        let scale = (self.width / frame_width).min(self.height / frame_height).max(1);
+        let (scaled_width, scaled_height) = ((frame_width * scale).min(self.width), (frame_height * scale).min(self.height));
+        let (left, top) = ((self.width - scaled_width) / 2, (self.height - scaled_height) / 2);
+        // center cropped frames too
+        let (crop_x, crop_y) = ((frame_width * scale - scaled_width) / 2, (frame_height * scale - scaled_height) / 2);
+
+        self.buffer.clear();
+        self.buffer.resize(scaled_height * self.line_length, 0);
+        for y in 0..scaled_height {
+            let frame_row = &frame[(y + crop_y) / scale * frame_width..][..frame_width];
+            let row = &mut self.buffer[y * self.line_length..][..self.line_length];
+            for x in 0..scaled_width {
+                let color = frame_row[(x + crop_x) / scale];
+                let offset = (left + x) * self.bytes_per_pixel;
+                let pixel = convert(color, &self.channels).to_le_bytes();
+                row[offset..offset + self.bytes_per_pixel].copy_from_slice(&pixel[..self.bytes_per_pixel]);
+            }
+        }
+        self.device.write_all_at(&self.buffer, (self.origin + top * self.line_length) as u64).map_err(to_error)
+    }
+}
+
+// convert a 0x00RRGGBB color to the device's pixel format
+fn convert(color: u32, channels: &[FbBitfield; 3]) -> u32 {
+    let channel = |value: u32, field: &FbBitfield| {
+        let length = field.length.min(8);
+        (value >> (8 - length)) << field.offset
+    };
+    channel(color >> 16 & 0xFF, &channels[0]) | channel(color >> 8 & 0xFF, &channels[1]) | channel(color & 0xFF, &channels[2])
+}
@@ -0,0 +1,128 @@
+//! C bindings for the port level interface, only built with the `ffi` feature
+//!
+//! The functions are declared in `include/tms9918a.h`. Build a static or dynamic library for linking
+//! into a C program with `cargo rustc --release --no-default-features --features ffi --crate-type staticlib`,
+//! or `--crate-type cdylib`.
+//!
+//! Every function catches panics so they never unwind into C, and accepts a null VDP pointer,
+//! which does nothing and reads as 0.
+
+use crate::TMS9918A;
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+// run a function with the VDP behind a pointer, returning the default value if the pointer is null or the function panics
+unsafe fn with_vdp<T: Default>(vdp: *mut TMS9918A, f: impl FnOnce(&mut TMS9918A) -> T) -> T {
+    match vdp.as_mut() {
+        Some(vdp) => panic::catch_unwind(AssertUnwindSafe(|| f(vdp))).unwrap_or_default(),
+        None => T::default()
+    }
+}
+
+/// Create a new VDP, returns null if it can't be created
+///
+/// The VDP must be freed with [`tms9918a_free`].
+#[no_mangle]
+pub extern "C" fn tms9918a_new() -> *mut TMS9918A {
+    panic::catch_unwind(|| Box::into_raw(Box::new(TMS9918A::new()))).unwrap_or(ptr::null_mut())
+}
+
+/// Free a VDP created by [`tms9918a_new`]
+///
+/// # Safety
+///
+/// The pointer must be null or returned by `tms9918a_new`, and not used again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tms9918a_free(vdp: *mut TMS9918A) {
+    if !vdp.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(vdp))));
+    }
+}
+
+/// Write a byte to the control port
+///
+/// # Safety
+///
+/// The pointer must be null or a VDP returned by [`tms9918a_new`].
+#[no_mangle]
+pub unsafe extern "C" fn tms9918a_write_control(vdp: *mut TMS9918A, data: u8) {
+    with_vdp(vdp, |vdp| vdp.write_control_port(data))
+}
+
+/// Write a byte to the data port
+///
+/// # Safety
+///
+/// The pointer must be null or a VDP returned by [`tms9918a_new`].
+#[no_mangle]
+pub unsafe extern "C" fn tms9918a_write_data(vdp: *mut TMS9918A, data: u8) {
+    with_vdp(vdp, |vdp| vdp.write_data_port(data))
+}
+
+/// Read a byte from the data port
+///
+/// # Safety
+///
+/// The pointer must be null or a VDP returned by [`tms9918a_new`].
+#[no_mangle]
+pub unsafe extern "C" fn tms9918a_read_data(vdp: *mut TMS9918A) -> u8 {
+    with_vdp(vdp, |vdp| vdp.read_data_port())
+}
+
+/// Read the status register, which clears the frame flag and interrupt
+///
+/// # Safety
+///
+/// The pointer must be null or a VDP returned by [`tms9918a_new`].
+#[no_mangle]
+pub unsafe extern "C" fn tms9918a_read_status(vdp: *mut TMS9918A) -> u8 {
+    with_vdp(vdp, |vdp| vdp.read_status_port())
+}
+
+/// Get the size of the frames rendered by [`tms9918a_render`], in pixels
+///
+/// Either size pointer can be null. Returns 0 on success or -1 if the VDP pointer is null.
+///
+/// # Safety
+///
+/// The VDP pointer must be null or a VDP returned by [`tms9918a_new`], and the size pointers must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn tms9918a_frame_size(vdp: *mut TMS9918A, width: *mut c_int, height: *mut c_int) -> c_int {
+    with_vdp(vdp, |vdp| {
+        write_size(vdp, width, height);
+        Some(0)
+    }).unwrap_or(-1)
+}
+
+/// Render a frame and copy it to a buffer as `0x00RRGGBB` pixels, row by row from the top left
+///
+/// The frame size is written to `width` and `height`, either can be null. `out` can be null to only render the frame,
+/// otherwise it must have room for the whole frame, which is at most `TMS9918A_MAX_FRAME_PIXELS` pixels.
+/// Returns 0 on success or -1 if the VDP pointer is null or rendering failed.
+///
+/// # Safety
+///
+/// The VDP pointer must be null or a VDP returned by [`tms9918a_new`], the size pointers must be null or valid,
+/// and `out` must be null or valid for writing the whole frame.
+#[no_mangle]
+pub unsafe extern "C" fn tms9918a_render(vdp: *mut TMS9918A, out: *mut u32, width: *mut c_int, height: *mut c_int) -> c_int {
+    with_vdp(vdp, |vdp| {
+        vdp.render();
+        if !out.is_null() {
+            ptr::copy_nonoverlapping(vdp.frame.as_ptr(), out, vdp.frame.len());
+        }
+        write_size(vdp, width, height);
+        Some(0)
+    }).unwrap_or(-1)
+}
+
+// write the frame size to the pointers which aren't null
+unsafe fn write_size(vdp: &TMS9918A, width: *mut c_int, height: *mut c_int) {
+    if let Some(width) = width.as_mut() {
+        *width = vdp.frame_width() as c_int;
+    }
+    if let Some(height) = height.as_mut() {
+        *height = vdp.frame_height() as c_int;
+    }
+}
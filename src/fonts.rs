@@ -0,0 +1,117 @@
+//! Built-in fonts, only built with the `fonts` feature
+
+#[cfg(feature = "image")]
+use crate::TMS9918AError;
+#[cfg(feature = "image")]
+use std::path::Path;
+
+/// 6x8 Text mode font with the 128 ASCII characters
+///
+/// Each character is 8 bytes and starts at the tile with the same character code, so the font is
+/// loaded at the start of the pattern table. The glyphs are 5 pixels wide and left aligned, so they
+/// can also be used in the 8x8 Graphics modes.
+pub const TEXT_6X8: &[u8; 1024] = include_bytes!("fonts/text_6x8.bin");
+
+/// 8x8 Graphics mode font with the 256 characters of code page 437
+///
+/// Each character is 8 bytes and starts at the tile with the same character code, like [`TEXT_6X8`]. The ASCII
+/// characters are the same glyphs centered in the 8 pixel wide tile, and the other characters follow the IBM PC
+/// layout: symbols, accented letters, box drawing characters which join up across tile edges, block elements,
+/// Greek letters and math symbols. The box drawing characters at 0xB3-0xDA make it usable for text user interfaces
+/// drawn with [`TMS9918A::draw_box`](crate::TMS9918A::draw_box).
+pub const GRAPHICS_8X8: &[u8; 2048] = include_bytes!("fonts/graphics_8x8.bin");
+
+/// Built-in fonts which can be loaded with [`TMS9918A::load_builtin_font`](crate::TMS9918A::load_builtin_font)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Font {
+    /// [`TEXT_6X8`]
+    Text6x8,
+    /// [`GRAPHICS_8X8`]
+    Graphics8x8
+}
+
+impl Font {
+    /// Get the font data, 8 bytes for each character
+    pub fn data(self) -> &'static [u8] {
+        match self {
+            Font::Text6x8 => TEXT_6X8,
+            Font::Graphics8x8 => GRAPHICS_8X8
+        }
+    }
+
+    /// Load a font from a glyph sheet image file, only available with the `image` feature
+    ///
+    /// See [`from_image_bytes`](Font::from_image_bytes) for details.
+    #[cfg(feature = "image")]
+    pub fn from_image<P: AsRef<Path>>(path: P, glyph_width: u32, glyph_height: u32) -> Result<Vec<u8>, TMS9918AError> {
+        let image = image::open(path).map_err(|error| TMS9918AError::Image(error.to_string()))?;
+        glyphs_from_image(&image.into_luma_alpha8(), glyph_width, glyph_height)
+    }
+
+    /// Load a font from a glyph sheet image in memory, only available with the `image` feature
+    ///
+    /// The image is cut into glyphs of `glyph_width` x `glyph_height` pixels, from left to right and then
+    /// top to bottom, so both a single row strip of glyphs and a grid of glyphs (usually 16 columns) can be used.
+    /// Pixels brighter than 50% gray are set, and transparent pixels are clear. Glyphs can be up to 8x8 pixels,
+    /// smaller glyphs are placed in the top left of the 8x8 pattern, so a 6x8 Text mode font uses 6x8 glyphs.
+    ///
+    /// Returns the pattern bytes, 8 for each glyph, which can be loaded with [`TMS9918A::load_font`](crate::TMS9918A::load_font).
+    /// Returns an error if the image can't be decoded, the glyph size is above 8x8, or the image size isn't a multiple of the glyph size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use image::{GrayImage, ImageOutputFormat, Luma};
+    /// use std::io::Cursor;
+    /// use tms9918a_emu::fonts::Font;
+    ///
+    /// // a strip of two 6x8 glyphs, with the top left pixel of the first glyph and the
+    /// // bottom right pixel of the second glyph set
+    /// let mut sheet = GrayImage::new(12, 8);
+    /// sheet.put_pixel(0, 0, Luma([255]));
+    /// sheet.put_pixel(11, 7, Luma([255]));
+    /// let mut png = Cursor::new(Vec::new());
+    /// sheet.write_to(&mut png, ImageOutputFormat::Png).unwrap();
+    ///
+    /// let font = Font::from_image_bytes(png.get_ref(), 6, 8).unwrap();
+    /// assert_eq!(font.len(), 16);
+    /// assert_eq!(font[0], 0b10000000);
+    /// assert_eq!(font[15], 0b00000100);
+    ///
+    /// assert!(Font::from_image_bytes(png.get_ref(), 8, 8).is_err());
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn from_image_bytes(bytes: &[u8], glyph_width: u32, glyph_height: u32) -> Result<Vec<u8>, TMS9918AError> {
+        let image = image::load_from_memory(bytes).map_err(|error| TMS9918AError::Image(error.to_string()))?;
+        glyphs_from_image(&image.into_luma_alpha8(), glyph_width, glyph_height)
+    }
+}
+
+// cut a glyph sheet into 8 byte patterns, left to right and then top to bottom
+#[cfg(feature = "image")]
+fn glyphs_from_image(image: &image::GrayAlphaImage, glyph_width: u32, glyph_height: u32) -> Result<Vec<u8>, TMS9918AError> {
+    if glyph_width == 0 || glyph_height == 0 || glyph_width > 8 || glyph_height > 8 {
+        return Err(TMS9918AError::InvalidGlyphSize { width: glyph_width, height: glyph_height });
+    }
+    let (width, height) = image.dimensions();
+    if width % glyph_width != 0 || height % glyph_height != 0 {
+        return Err(TMS9918AError::ImageSize { width, height });
+    }
+
+    let mut patterns = Vec::new();
+    for glyph_y in (0..height).step_by(glyph_height as usize) {
+        for glyph_x in (0..width).step_by(glyph_width as usize) {
+            let mut pattern = [0; 8];
+            for (y, row) in pattern.iter_mut().enumerate().take(glyph_height as usize) {
+                for x in 0..glyph_width {
+                    let pixel = image.get_pixel(glyph_x + x, glyph_y + y as u32);
+                    if pixel[0] >= 0x80 && pixel[1] >= 0x80 {
+                        *row |= 0x80 >> x;
+                    }
+                }
+            }
+            patterns.extend_from_slice(&pattern);
+        }
+    }
+    Ok(patterns)
+}
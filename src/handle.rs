@@ -0,0 +1,132 @@
+//! Port access from other threads through a channel to the thread owning the VDP
+
+use crate::{TMS9918A, TMS9918AError};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::Mutex;
+
+// a port access sent from a handle, reads include the channel for the result
+pub(crate) enum PortCommand {
+    WriteControl(u8),
+    WriteData(u8),
+    ReadData(SyncSender<u8>),
+    ReadStatus(SyncSender<u8>)
+}
+
+// the channel shared by all handles of a VDP
+pub(crate) struct CommandQueue {
+    sender: Sender<PortCommand>,
+    // a Receiver can't be shared between threads, the mutex keeps the VDP Sync
+    receiver: Mutex<Receiver<PortCommand>>
+}
+
+/// A handle to the ports of a TMS9918A owned by another thread
+///
+/// Handles are created by [`TMS9918A::handle`] and are cheap to clone and send to other threads. Port accesses are
+/// queued and run in order when the thread owning the VDP calls [`TMS9918A::pump_commands`], usually before
+/// rendering each frame. Writes return immediately. Reads wait until the owning thread pumps the queue and return
+/// the value the port read at that point, so the read-ahead buffer and address pointer behave the same as on a VDP
+/// accessed directly.
+///
+/// All functions return [`TMS9918AError::Disconnected`] once the VDP is dropped.
+///
+/// # Examples
+///
+/// ```
+/// # use tms9918a_emu::TMS9918A;
+/// use std::thread;
+///
+/// let mut vdp = TMS9918A::new();
+/// let handle = vdp.handle();
+///
+/// // CPU thread, writes a byte to address 0 and reads it back
+/// let cpu = thread::spawn(move || -> Result<u8, tms9918a_emu::TMS9918AError> {
+///     handle.write_control(0x00)?;
+///     handle.write_control(0x40)?;
+///     handle.write_data(0x55)?;
+///     handle.write_control(0x00)?;
+///     handle.write_control(0x00)?;
+///     handle.read_data()
+/// });
+///
+/// // display thread, runs the queued accesses until the CPU thread is done
+/// while !cpu.is_finished() {
+///     vdp.pump_commands();
+/// }
+/// assert_eq!(cpu.join().unwrap(), Ok(0x55));
+/// ```
+#[derive(Clone)]
+pub struct VdpHandle {
+    sender: Sender<PortCommand>
+}
+
+impl VdpHandle {
+    /// Write a byte to the control port
+    pub fn write_control(&self, data: u8) -> Result<(), TMS9918AError> {
+        self.send(PortCommand::WriteControl(data))
+    }
+
+    /// Write a byte to the data port
+    pub fn write_data(&self, data: u8) -> Result<(), TMS9918AError> {
+        self.send(PortCommand::WriteData(data))
+    }
+
+    /// Read a byte from the data port, waiting until the owning thread pumps the queue
+    pub fn read_data(&self) -> Result<u8, TMS9918AError> {
+        self.request(PortCommand::ReadData)
+    }
+
+    /// Read the status register, waiting until the owning thread pumps the queue
+    pub fn read_status(&self) -> Result<u8, TMS9918AError> {
+        self.request(PortCommand::ReadStatus)
+    }
+
+    fn send(&self, command: PortCommand) -> Result<(), TMS9918AError> {
+        self.sender.send(command).map_err(|_| TMS9918AError::Disconnected)
+    }
+
+    // send a read and wait for its result
+    fn request(&self, command: fn(SyncSender<u8>) -> PortCommand) -> Result<u8, TMS9918AError> {
+        let (reply_sender, reply) = mpsc::sync_channel(1);
+        self.send(command(reply_sender))?;
+        // the reply sender is dropped without a reply if the VDP is dropped with the read still queued
+        reply.recv().map_err(|_| TMS9918AError::Disconnected)
+    }
+}
+
+impl TMS9918A {
+    /// Create a handle for accessing the ports from another thread
+    ///
+    /// All handles of a VDP share one queue, which is run by [`pump_commands`](TMS9918A::pump_commands).
+    /// This is only available with the `std` feature. See [`VdpHandle`].
+    pub fn handle(&mut self) -> VdpHandle {
+        let queue = self.commands.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel();
+            CommandQueue { sender, receiver: Mutex::new(receiver) }
+        });
+        VdpHandle { sender: queue.sender.clone() }
+    }
+
+    /// Run the port accesses queued by the handles of this VDP, in the order they were sent
+    ///
+    /// Returns the number of accesses run. Call this before rendering each frame so the frame includes
+    /// all writes sent so far, and often enough that threads reading through a handle aren't kept waiting.
+    pub fn pump_commands(&mut self) -> usize {
+        // the lock can't be poisoned, nothing panics while it is held
+        let commands: Vec<PortCommand> = match self.commands.as_mut().map(|queue| queue.receiver.get_mut()) {
+            Some(Ok(receiver)) => receiver.try_iter().collect(),
+            _ => return 0
+        };
+
+        let count = commands.len();
+        for command in commands {
+            match command {
+                PortCommand::WriteControl(data) => self.write_control_port(data),
+                PortCommand::WriteData(data) => self.write_data_port(data),
+                // the handle may have given up waiting, then there is nobody to reply to
+                PortCommand::ReadData(reply) => { let _ = reply.send(self.read_data_port()); },
+                PortCommand::ReadStatus(reply) => { let _ = reply.send(self.read_status_port()); }
+            }
+        }
+        count
+    }
+}
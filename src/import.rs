@@ -0,0 +1,349 @@
+//! Image conversion to Graphics I and Graphics II tables, only built with the `image` feature
+
+use crate::{Palette, TMS9918A, TMS9918AError};
+use std::collections::{BTreeMap, HashMap};
+
+/// Name, pattern and color tables of an image converted by [`import_image_gfx1`]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Gfx1Image {
+    /// Name table, 32x24 entries
+    pub name_table: Vec<u8>,
+    /// Pattern table, 256 tiles of 8 bytes
+    pub pattern_table: Vec<u8>,
+    /// Color table, 32 entries, one for each group of 8 tiles
+    pub color_table: Vec<u8>,
+    /// Number of unique tiles used by the image
+    pub tiles: usize
+}
+
+impl Gfx1Image {
+    /// Upload the tables to the VDP
+    ///
+    /// The name, color and pattern table offset registers must be set first.
+    pub fn load(&self, vdp: &mut TMS9918A) -> Result<(), TMS9918AError> {
+        vdp.fill_name_table(&self.name_table, 0, self.name_table.len())?;
+        vdp.fill_pattern_table(&self.pattern_table, 0, self.pattern_table.len())?;
+        vdp.fill_color_table(&self.color_table, 0, self.color_table.len())
+    }
+}
+
+/// Name, pattern and color tables of an image converted by [`import_image_gfx2`]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Gfx2Image {
+    /// Name table, 32x24 entries numbered 0-255 in each third of the screen
+    pub name_table: Vec<u8>,
+    /// Pattern table, 3 character sets of 256 tiles of 8 bytes
+    pub pattern_table: Vec<u8>,
+    /// Color table, one entry for each pattern table byte
+    pub color_table: Vec<u8>,
+    /// Tile rows which had more than two colors and lost some pixels
+    pub clashes: Vec<ColorClash>
+}
+
+impl Gfx2Image {
+    /// Upload the tables to the VDP
+    ///
+    /// The VDP must be in Graphics II mode, and the name, color and pattern table offset registers must be set first.
+    pub fn load(&self, vdp: &mut TMS9918A) -> Result<(), TMS9918AError> {
+        vdp.fill_name_table(&self.name_table, 0, self.name_table.len())?;
+        vdp.fill_pattern_table(&self.pattern_table, 0, self.pattern_table.len())?;
+        vdp.fill_color_table(&self.color_table, 0, self.color_table.len())
+    }
+}
+
+/// A tile row with more than two colors in an image converted by [`import_image_gfx2`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ColorClash {
+    /// Tile column
+    pub x: usize,
+    /// Tile row
+    pub y: usize,
+    /// Pixel row in the tile, 0-7
+    pub row: usize,
+    /// Number of pixels which were changed to one of the two colors kept
+    pub lost_pixels: usize
+}
+
+// all colors except transparent
+const OPAQUE_COLORS: [usize; 15] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+// a unique tile, before it is assigned a tile number
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+enum Tile {
+    // a tile with two colors, the pattern bits are set for the foreground color
+    TwoColor { pattern: [u8; 8], foreground: u8, background: u8 },
+    // a tile with only one color, which fits in any group using that color
+    Solid(u8)
+}
+
+// a unique tile index and its pattern
+type TileSlot = (usize, [u8; 8]);
+
+// a group of 8 tiles sharing one color table entry
+struct Group {
+    foreground: Option<u8>,
+    background: u8,
+    tiles: Vec<TileSlot>
+}
+
+/// Convert a 256x192 image to Graphics I name, pattern and color tables
+///
+/// Each pixel is converted to the nearest of colors 1-15 in `palette`, and the screen is cut into 32x24 tiles of 8x8 pixels.
+/// Each tile keeps its two most common colors, other pixels use the nearer of the two. Identical tiles are only stored once,
+/// and tiles with the same colors are placed in the same group of 8 tiles, which shares one color table entry in Graphics I mode.
+///
+/// Returns an error if the image isn't 256x192 pixels, if more than 256 unique tiles are needed, or if more than
+/// 32 color groups are needed. The number of tiles or groups needed is included in the error.
+///
+/// # Examples
+///
+/// ```
+/// use image::{DynamicImage, Rgb, RgbImage};
+/// use tms9918a_emu::{import_image_gfx1, BuiltinPalette, TMS9918A};
+///
+/// let palette = BuiltinPalette::Original.colors();
+/// // a white box on a black screen
+/// let mut image = RgbImage::from_pixel(256, 192, Rgb([0, 0, 0]));
+/// for y in 60..132 {
+///     for x in 100..156 {
+///         image.put_pixel(x, y, Rgb([255, 255, 255]));
+///     }
+/// }
+/// let converted = import_image_gfx1(&DynamicImage::ImageRgb8(image), &palette).unwrap();
+/// // solid black, solid white, and the 4 corners and 4 edges of the box which don't line up with the tiles
+/// assert_eq!(converted.tiles, 10);
+/// assert_eq!(converted.name_table[0], converted.name_table[767]);
+///
+/// let mut vdp = TMS9918A::new();
+/// vdp.set_color_table_multiplier(0x80);
+/// vdp.set_pattern_table_multiplier(1);
+/// converted.load(&mut vdp).unwrap();
+/// ```
+pub fn import_image_gfx1(image: &image::DynamicImage, palette: &Palette) -> Result<Gfx1Image, TMS9918AError> {
+    let image = image.to_rgb8();
+    let (width, height) = image.dimensions();
+    if width != 256 || height != 192 {
+        return Err(TMS9918AError::ImageSize { width, height });
+    }
+
+    // cut the screen into tiles and deduplicate them
+    let mut unique_tiles = Vec::new();
+    let mut tile_indices = HashMap::new();
+    let mut cells = Vec::with_capacity(32 * 24);
+    for tile_y in 0..24 {
+        for tile_x in 0..32 {
+            let mut pixels = [[0; 8]; 8];
+            for (y, row) in pixels.iter_mut().enumerate() {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let rgb = image.get_pixel(tile_x * 8 + x as u32, tile_y * 8 + y as u32).0;
+                    *pixel = rgb_to_u32(rgb);
+                }
+            }
+            let tile = convert_tile(&pixels, palette);
+            let index = *tile_indices.entry(tile).or_insert_with(|| {
+                unique_tiles.push(tile);
+                unique_tiles.len() - 1
+            });
+            cells.push(index);
+        }
+    }
+    if unique_tiles.len() > 256 {
+        return Err(TMS9918AError::TooManyTiles(unique_tiles.len()));
+    }
+
+    // place two color tiles in groups of 8 tiles with the same colors
+    let mut by_colors: BTreeMap<(u8, u8), Vec<TileSlot>> = BTreeMap::new();
+    for (index, tile) in unique_tiles.iter().enumerate() {
+        if let Tile::TwoColor { pattern, foreground, background } = *tile {
+            by_colors.entry((foreground, background)).or_default().push((index, pattern));
+        }
+    }
+    let mut groups = Vec::new();
+    for ((foreground, background), tiles) in by_colors {
+        for chunk in tiles.chunks(8) {
+            groups.push(Group { foreground: Some(foreground), background, tiles: chunk.to_vec() });
+        }
+    }
+
+    // solid tiles fit in any group with a free slot using the same foreground or background color
+    for (index, tile) in unique_tiles.iter().enumerate() {
+        if let Tile::Solid(color) = *tile {
+            let slot = groups.iter_mut()
+                .filter(|group| group.tiles.len() < 8)
+                .find_map(|group| {
+                    if group.background == color {
+                        Some((group, 0x00))
+                    } else if group.foreground.unwrap_or(color) == color {
+                        group.foreground = Some(color);
+                        Some((group, 0xFF))
+                    } else {
+                        None
+                    }
+                });
+            match slot {
+                Some((group, pattern)) => group.tiles.push((index, [pattern; 8])),
+                None => groups.push(Group { foreground: None, background: color, tiles: vec![(index, [0x00; 8])] })
+            }
+        }
+    }
+    if groups.len() > 32 {
+        return Err(TMS9918AError::TooManyColorGroups(groups.len()));
+    }
+
+    // assign tile numbers and build the tables
+    let mut converted = Gfx1Image {
+        name_table: vec![0; 32 * 24],
+        pattern_table: vec![0; 256 * 8],
+        color_table: vec![0; 32],
+        tiles: unique_tiles.len()
+    };
+    let mut tile_numbers = vec![0; unique_tiles.len()];
+    for (group_number, group) in groups.iter().enumerate() {
+        let foreground = group.foreground.unwrap_or(group.background);
+        converted.color_table[group_number] = foreground << 4 | group.background;
+        for (slot, (index, pattern)) in group.tiles.iter().enumerate() {
+            let tile_number = group_number * 8 + slot;
+            tile_numbers[*index] = tile_number as u8;
+            converted.pattern_table[tile_number * 8..tile_number * 8 + 8].copy_from_slice(pattern);
+        }
+    }
+    for (entry, index) in converted.name_table.iter_mut().zip(cells) {
+        *entry = tile_numbers[index];
+    }
+    Ok(converted)
+}
+
+/// Convert a 256x192 image to Graphics II name, pattern and color tables
+///
+/// Each pixel is converted to the nearest of colors 1-15 in `palette`. Every tile on the screen gets its own pattern,
+/// so the name table simply counts from 0 to 255 in each third of the screen. Each 8 pixel row of a tile keeps its
+/// two most common colors, other pixels use the nearer of the two and the row is listed in [`Gfx2Image::clashes`].
+///
+/// Returns an error if the image isn't 256x192 pixels.
+///
+/// # Examples
+///
+/// ```
+/// use image::{DynamicImage, Rgb, RgbImage};
+/// use tms9918a_emu::{import_image_gfx2, BuiltinPalette, TMS9918A, VideoMode};
+///
+/// let palette = BuiltinPalette::Original.colors();
+/// // black, white and dark red pixels on the first row of the first tile
+/// let mut image = RgbImage::from_pixel(256, 192, Rgb([0, 0, 0]));
+/// image.put_pixel(0, 0, Rgb([255, 255, 255]));
+/// image.put_pixel(1, 0, Rgb([255, 255, 255]));
+/// image.put_pixel(2, 0, Rgb([212, 82, 77]));
+/// let converted = import_image_gfx2(&DynamicImage::ImageRgb8(image), &palette).unwrap();
+/// assert_eq!(converted.pattern_table[0], 0b11000000);
+/// assert_eq!(converted.color_table[0], 0xF1);
+/// assert_eq!(converted.clashes.len(), 1);
+/// assert_eq!(converted.clashes[0].lost_pixels, 1);
+///
+/// let mut vdp = TMS9918A::new();
+/// vdp.set_video_mode(VideoMode::Gfx2);
+/// vdp.set_name_table_multiplier(6);
+/// vdp.set_color_table_multiplier(0x80);
+/// vdp.set_pattern_table_multiplier(0);
+/// converted.load(&mut vdp).unwrap();
+/// ```
+pub fn import_image_gfx2(image: &image::DynamicImage, palette: &Palette) -> Result<Gfx2Image, TMS9918AError> {
+    let image = image.to_rgb8();
+    let (width, height) = image.dimensions();
+    if width != 256 || height != 192 {
+        return Err(TMS9918AError::ImageSize { width, height });
+    }
+
+    let mut converted = Gfx2Image {
+        name_table: (0..32 * 24).map(|cell| cell as u8).collect(),
+        pattern_table: vec![0; 32 * 24 * 8],
+        color_table: vec![0; 32 * 24 * 8],
+        clashes: Vec::new()
+    };
+    for y in 0..24 {
+        for x in 0..32 {
+            for row in 0..8 {
+                let mut pixels = [0; 8];
+                for (column, pixel) in pixels.iter_mut().enumerate() {
+                    *pixel = rgb_to_u32(image.get_pixel((x * 8 + column) as u32, (y * 8 + row) as u32).0);
+                }
+                let (foreground, background) = two_colors(pixels.iter(), palette);
+                let (pattern, lost_pixels) = convert_row(&pixels, palette, foreground, background);
+                let offset = (y * 32 + x) * 8 + row;
+                converted.pattern_table[offset] = pattern;
+                converted.color_table[offset] = (foreground as u8) << 4 | background as u8;
+                if lost_pixels > 0 {
+                    converted.clashes.push(ColorClash { x, y, row, lost_pixels });
+                }
+            }
+        }
+    }
+    Ok(converted)
+}
+
+// convert 8x8 RGB pixels to a tile using the two most common palette colors
+fn convert_tile(pixels: &[[u32; 8]; 8], palette: &Palette) -> Tile {
+    let (foreground, background) = two_colors(pixels.iter().flatten(), palette);
+    if foreground == background {
+        return Tile::Solid(foreground as u8);
+    }
+    let mut pattern = [0; 8];
+    for (y, row) in pixels.iter().enumerate() {
+        for (x, pixel) in row.iter().enumerate() {
+            if nearest_color(*pixel, palette, &[background, foreground]) == foreground {
+                pattern[y] |= 0x80 >> x;
+            }
+        }
+    }
+    Tile::TwoColor { pattern, foreground: foreground as u8, background: background as u8 }
+}
+
+// convert a row of 8 RGB pixels to a pattern byte using two colors, also returns the number of pixels of other colors
+fn convert_row(pixels: &[u32; 8], palette: &Palette, foreground: usize, background: usize) -> (u8, usize) {
+    let mut pattern = 0;
+    let mut lost_pixels = 0;
+    for (x, pixel) in pixels.iter().enumerate() {
+        if nearest_color(*pixel, palette, &[background, foreground]) == foreground {
+            pattern |= 0x80 >> x;
+        }
+        let color = nearest_color(*pixel, palette, &OPAQUE_COLORS);
+        if color != foreground && color != background {
+            lost_pixels += 1;
+        }
+    }
+    (pattern, lost_pixels)
+}
+
+// the two most common palette colors of some RGB pixels as (foreground, background), both the same if there is only one
+//
+// the higher color number is used as the foreground, so tiles with the same two colors share a group in Graphics I mode
+fn two_colors<'a, I: Iterator<Item = &'a u32>>(pixels: I, palette: &Palette) -> (usize, usize) {
+    let mut counts = [0; 16];
+    for pixel in pixels {
+        counts[nearest_color(*pixel, palette, &OPAQUE_COLORS)] += 1;
+    }
+    let mut colors: Vec<usize> = (1..16).filter(|&color| counts[color] > 0).collect();
+    colors.sort_by(|a, b| counts[*b].cmp(&counts[*a]));
+    let second = colors.get(1).copied().unwrap_or(colors[0]);
+    (colors[0].max(second), colors[0].min(second))
+}
+
+// the color nearest to an RGB pixel out of a list of colors, the first color wins ties
+fn nearest_color(pixel: u32, palette: &Palette, colors: &[usize]) -> usize {
+    *colors.iter()
+        .min_by_key(|color| color_distance(pixel, palette[**color]))
+        .expect("no colors to choose from")
+}
+
+// squared distance between two RGB colors
+fn color_distance(a: u32, b: u32) -> u32 {
+    let channel = |shift: u32| {
+        let difference = ((a >> shift) & 0xFF) as i32 - ((b >> shift) & 0xFF) as i32;
+        (difference * difference) as u32
+    };
+    channel(16) + channel(8) + channel(0)
+}
+
+// pack an RGB pixel in the same format as the framebuffer
+fn rgb_to_u32(rgb: [u8; 3]) -> u32 {
+    (rgb[0] as u32) << 16 | (rgb[1] as u32) << 8 | rgb[2] as u32
+}
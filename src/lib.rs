@@ -1,9 +1,73 @@
 //! Texas Instruments TMS9918A VDP emulator library
+//!
+//! The emulation core only needs `core` and `alloc`. Without the default `std` feature the crate is `no_std`,
+//! for example for driving a display from a microcontroller, and loading files is not available.
 
-use rand::Rng;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use bitflags::bitflags;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+mod charmap;
+mod console;
+mod diff;
+pub mod display;
+#[cfg(feature = "image")]
+mod export;
+#[cfg(all(feature = "fbdev", target_os = "linux"))]
+pub mod fbdev;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+mod handle;
+#[cfg(feature = "image")]
+mod import;
+#[cfg(feature = "net")]
+pub mod net;
+#[cfg(feature = "fonts")]
+pub mod fonts;
+mod pattern;
+#[cfg(feature = "pixels")]
+pub mod pixels;
+#[cfg(feature = "gif")]
+mod recorder;
+mod terminal;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "tilemap")]
+mod tilemap;
+#[cfg(feature = "std")]
+mod trace;
+#[cfg(feature = "window")]
+pub mod window;
+
+pub use charmap::{AsciiOffset, CharMap, Identity};
+pub use console::TextConsole;
+pub use diff::StateDiff;
+#[cfg(feature = "std")]
+pub use handle::VdpHandle;
+#[cfg(feature = "image")]
+pub use import::{import_image_gfx1, import_image_gfx2, ColorClash, Gfx1Image, Gfx2Image};
+pub use pattern::Pattern;
+#[cfg(feature = "tilemap")]
+pub use tilemap::Tilemap;
 
 // TMS9918A video modes
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum VideoMode {
     /// Graphics I: 256x192 pixels, 32x24 tiles of 8x8 pixels each, 1 character set.
     /// 
@@ -27,26 +91,829 @@ pub enum VideoMode {
     Multicolor
 }
 
+/// Errors returned by TMS9918A functions
+/// 
+/// All fallible functions in this crate return this error type.
+/// 
+/// Creating a [`TMS9918A`] never fails: it only contains the emulated VDP state and framebuffer,
+/// and never opens a window, so it can be used in headless and library contexts.
+/// Displaying the framebuffer is handled by `window::Window`, which is only built with the `window` feature.
+#[derive(Clone, PartialEq, Debug)]
+pub enum TMS9918AError {
+    /// Video memory address is out of range (above 0x3FFF)
+    AddressOutOfRange(usize),
+    /// The window could not be created
+    WindowCreation(String),
+    /// The window contents could not be updated
+    WindowUpdate(String),
+    /// A framebuffer row is too short to fit the frame, the minimum stride is included
+    StrideTooSmall(usize),
+    /// A buffer is too small to fit the frame or tiles, the minimum length is included
+    BufferTooSmall(usize),
+    /// A table access ending at `end` is past the end of a table of `size` bytes
+    OutOfRange {
+        /// Offset just past the last byte accessed
+        end: usize,
+        /// Size of the table in the current video mode
+        size: usize
+    },
+    /// A tile position is outside of the 32x24 screen, or 40x24 in Text mode
+    TileOutOfRange {
+        /// Column
+        x: usize,
+        /// Row
+        y: usize
+    },
+    /// A file or stream could not be read or written
+    Io(String),
+    /// Font data length is not a multiple of 8 bytes, the length is included
+    InvalidFontLength(usize),
+    /// An image could not be read, decoded or encoded
+    Image(String),
+    /// Glyph size is 0 or larger than 8x8 pixels
+    InvalidGlyphSize {
+        /// Glyph width in pixels
+        width: u32,
+        /// Glyph height in pixels
+        height: u32
+    },
+    /// Image size is not supported, for example it is not a multiple of the glyph size
+    ImageSize {
+        /// Image width in pixels
+        width: u32,
+        /// Image height in pixels
+        height: u32
+    },
+    /// An image needs more than 256 unique tiles, the number of tiles needed is included
+    TooManyTiles(usize),
+    /// An image needs more than 32 color groups of 8 tiles, the number of groups needed is included
+    TooManyColorGroups(usize),
+    /// An MSX screen file has an invalid header or is truncated, the reason is included
+    InvalidSc2(String),
+    /// A tile map has a tile index outside of 0-255, or a row with the wrong length, at this position
+    InvalidTilemap {
+        /// Row
+        row: usize,
+        /// Column
+        column: usize
+    },
+    /// A tile map is larger than the screen
+    TilemapTooLarge {
+        /// Map width in tiles
+        width: usize,
+        /// Map height in tiles
+        height: usize
+    },
+    /// Pattern string art has the wrong size or an invalid character at this position
+    InvalidPatternArt {
+        /// Row
+        row: usize,
+        /// Column, in characters
+        column: usize
+    },
+    /// The VDP behind a [`VdpHandle`] was dropped
+    Disconnected,
+    /// A video memory dump is not 16384 bytes, the length is included
+    InvalidVramSize(usize),
+    /// A port trace file has an invalid header or event, the reason is included
+    InvalidTrace(String)
+}
+
+impl fmt::Display for TMS9918AError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TMS9918AError::AddressOutOfRange(address) => write!(f, "video memory address out of range: {:#06X}", address),
+            TMS9918AError::WindowCreation(error) => write!(f, "failed to create window: {}", error),
+            TMS9918AError::WindowUpdate(error) => write!(f, "failed to update window: {}", error),
+            TMS9918AError::StrideTooSmall(stride) => write!(f, "framebuffer stride too small, at least {} pixels required", stride),
+            TMS9918AError::BufferTooSmall(length) => write!(f, "buffer too small, at least {} elements required", length),
+            TMS9918AError::OutOfRange { end, size } => write!(f, "table access ending at offset {} is out of range for a table of {} bytes", end, size),
+            TMS9918AError::TileOutOfRange { x, y } => write!(f, "tile position ({}, {}) is outside of the screen", x, y),
+            TMS9918AError::Io(error) => write!(f, "I/O error: {}", error),
+            TMS9918AError::InvalidFontLength(length) => write!(f, "font data length of {} bytes is not a multiple of 8", length),
+            TMS9918AError::Image(error) => write!(f, "image error: {}", error),
+            TMS9918AError::InvalidGlyphSize { width, height } => write!(f, "invalid glyph size {}x{}, glyphs must be 1x1 to 8x8 pixels", width, height),
+            TMS9918AError::ImageSize { width, height } => write!(f, "unsupported image size {}x{}", width, height),
+            TMS9918AError::TooManyTiles(tiles) => write!(f, "image needs {} unique tiles, at most 256 are available", tiles),
+            TMS9918AError::TooManyColorGroups(groups) => write!(f, "image needs {} color groups, at most 32 are available", groups),
+            TMS9918AError::InvalidSc2(reason) => write!(f, "invalid SC2 screen file: {}", reason),
+            TMS9918AError::InvalidTilemap { row, column } => write!(f, "invalid tile map at row {}, column {}", row, column),
+            TMS9918AError::TilemapTooLarge { width, height } => write!(f, "tile map of {}x{} tiles is larger than the screen", width, height),
+            TMS9918AError::InvalidPatternArt { row, column } => write!(f, "invalid pattern art at row {}, column {}", row, column),
+            TMS9918AError::Disconnected => write!(f, "the VDP was dropped"),
+            TMS9918AError::InvalidVramSize(length) => write!(f, "video memory dump of {} bytes is not 16384 bytes", length),
+            TMS9918AError::InvalidTrace(reason) => write!(f, "invalid port trace file: {}", reason)
+        }
+    }
+}
+
+impl core::error::Error for TMS9918AError {}
+
+/// Direction of video memory accesses through the data port
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AccessDirection {
+    /// Read from video memory
+    Read,
+    /// Write to video memory
+    Write
+}
+
+/// A peripheral connected to an emulated CPU's I/O ports
+/// 
+/// This allows a CPU emulator to own the VDP alongside other peripherals, for example as a `Box<dyn IoDevice>`.
+pub trait IoDevice {
+    /// Read from the port at an address
+    fn read(&mut self, port: u16) -> u8;
+    /// Write to the port at an address
+    fn write(&mut self, port: u16, data: u8);
+}
+
+/// TMS9918A video standards
+///
+/// The rendered resolution is the same for both standards, only the timing is different.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VideoStandard {
+    /// NTSC (TMS9918A, TMS9928A): 10.738635 MHz master clock, 262 scanlines per frame, ~60 Hz
+    Ntsc,
+    /// PAL (TMS9929A): 10.6875 MHz master clock, 313 scanlines per frame, ~50 Hz
+    Pal
+}
+
+impl VideoStandard {
+    /// Master clock frequency in Hz
+    pub fn master_clock(self) -> u64 {
+        match self {
+            VideoStandard::Ntsc => 10_738_635,
+            VideoStandard::Pal => 10_687_500
+        }
+    }
+
+    /// Number of scanlines per frame, including borders and vertical blanking
+    pub fn scanlines_per_frame(self) -> u16 {
+        match self {
+            VideoStandard::Ntsc => 262,
+            VideoStandard::Pal => 313
+        }
+    }
+
+    /// Duration of one frame, about 16.7 ms for NTSC and 20 ms for PAL
+    pub fn frame_duration(self) -> Duration {
+        let master_clocks = self.scanlines_per_frame() as u64 * MASTER_CLOCKS_PER_LINE;
+        Duration::from_nanos(master_clocks * 1_000_000_000 / self.master_clock())
+    }
+}
+
+/// TMS99xx VDP variants
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VdpVariant {
+    /// TMS9918: the original NTSC VDP, without Graphics II mode
+    ///
+    /// Selecting Graphics II mode on a TMS9918 results in Graphics I mode, as the bitmap mode bit is ignored.
+    Tms9918,
+    /// TMS9918A: NTSC VDP with composite video output
+    Tms9918a,
+    /// TMS9928A: NTSC VDP with component (Y, R-Y, B-Y) video output
+    Tms9928a,
+    /// TMS9929A: PAL VDP with component (Y, R-Y, B-Y) video output
+    Tms9929a
+}
+
+impl VdpVariant {
+    /// Check if this variant supports Graphics II mode
+    pub fn has_gfx2(self) -> bool {
+        self != VdpVariant::Tms9918
+    }
+
+    /// Default video standard of this variant
+    pub fn video_standard(self) -> VideoStandard {
+        match self {
+            VdpVariant::Tms9929a => VideoStandard::Pal,
+            _ => VideoStandard::Ntsc
+        }
+    }
+
+    /// Default palette of this variant
+    ///
+    /// The composite TMS9918 and TMS9918A use [`BuiltinPalette::Original`], the component TMS9928A and TMS9929A use
+    /// [`BuiltinPalette::Tms9928Component`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::{BuiltinPalette, VdpVariant};
+    /// assert_eq!(VdpVariant::Tms9918a.default_palette(), BuiltinPalette::Original);
+    /// assert_eq!(VdpVariant::Tms9929a.default_palette(), BuiltinPalette::Tms9928Component);
+    /// ```
+    pub fn default_palette(self) -> BuiltinPalette {
+        match self {
+            VdpVariant::Tms9928a | VdpVariant::Tms9929a => BuiltinPalette::Tms9928Component,
+            _ => BuiltinPalette::Original
+        }
+    }
+}
+
+/// Initial contents of video memory, used by [`TMS9918A::with_vram_init`] and [`TMS9918A::cold_reset_with`]
+///
+/// Real DRAM contains garbage at power on, which [`TMS9918A::new`] imitates with random contents. The other options
+/// give the same contents every time, for tests and reproducible demos.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VramInit {
+    /// All bytes are 0
+    Zero,
+    /// All bytes are this value
+    Fill(u8),
+    /// Pseudo-random bytes, the same seed gives the same contents on every platform
+    Random {
+        /// Seed of the pseudo-random generator
+        seed: u64
+    }
+}
+
+impl VramInit {
+    // video memory contents of this size
+    fn memory(self, size: usize) -> Vec<u8> {
+        match self {
+            VramInit::Zero => vec![0; size],
+            VramInit::Fill(value) => vec![value; size],
+            VramInit::Random { seed } => random_memory(size, seed)
+        }
+    }
+}
+
+/// Color palette with 16 colors in the `0x00RRGGBB` format, in the order of the VDP color numbers
+pub type Palette = [u32; 16];
+
+/// The 16 VDP colors
+///
+/// Colors are stored in video memory and registers as 4-bit color numbers.
+/// Converting a `u8` into a `Color` uses the low 4 bits.
+///
+/// # Examples
+///
+/// ```
+/// # use tms9918a_emu::{BuiltinPalette, Color};
+/// assert_eq!(Color::from(0x9), Color::LightRed);
+/// assert_eq!(u8::from(Color::White), 0xF);
+/// assert_eq!(Color::White.to_rgb(&BuiltinPalette::Original.colors()), 0xFFFFFF);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Color {
+    /// 0: transparent, the backdrop color shows through
+    Transparent,
+    /// 1: black
+    Black,
+    /// 2: medium green
+    MediumGreen,
+    /// 3: light green
+    LightGreen,
+    /// 4: dark blue
+    DarkBlue,
+    /// 5: light blue
+    LightBlue,
+    /// 6: dark red
+    DarkRed,
+    /// 7: cyan
+    Cyan,
+    /// 8: medium red
+    MediumRed,
+    /// 9: light red
+    LightRed,
+    /// 10: dark yellow
+    DarkYellow,
+    /// 11: light yellow
+    LightYellow,
+    /// 12: dark green
+    DarkGreen,
+    /// 13: magenta
+    Magenta,
+    /// 14: gray
+    Gray,
+    /// 15: white
+    White
+}
+
+impl Color {
+    /// RGB value of this color in a palette
+    ///
+    /// Transparent is looked up like any other color, which is black in the built-in palettes.
+    pub fn to_rgb(self, palette: &Palette) -> u32 {
+        palette[u8::from(self) as usize]
+    }
+}
+
+impl From<u8> for Color {
+    fn from(color: u8) -> Self {
+        match color & 0x0F {
+            0x0 => Color::Transparent,
+            0x1 => Color::Black,
+            0x2 => Color::MediumGreen,
+            0x3 => Color::LightGreen,
+            0x4 => Color::DarkBlue,
+            0x5 => Color::LightBlue,
+            0x6 => Color::DarkRed,
+            0x7 => Color::Cyan,
+            0x8 => Color::MediumRed,
+            0x9 => Color::LightRed,
+            0xA => Color::DarkYellow,
+            0xB => Color::LightYellow,
+            0xC => Color::DarkGreen,
+            0xD => Color::Magenta,
+            0xE => Color::Gray,
+            _ => Color::White
+        }
+    }
+}
+
+impl From<Color> for u8 {
+    fn from(color: Color) -> Self {
+        color as u8
+    }
+}
+
+/// Built-in color palettes
+///
+/// Each palette has 16 colors in the `0x00RRGGBB` format, in the order of the VDP color numbers.
+/// Color 0 is transparent and is never displayed directly, the backdrop color shows through instead.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BuiltinPalette {
+    /// The palette used by previous versions of this crate, the default for the TMS9918 and TMS9918A
+    ///
+    /// `000000 000000 21C942 5EDC78 5455ED 7D75FC D3524D 43EBF6 FD5554 FF7978 D3C153 E5CE80 21B03C C95BBA CCCCCC FFFFFF`
+    Original,
+    /// TMS9918A palette derived from its composite video output
+    ///
+    /// `000000 000000 21C842 5EDC78 5455ED 7D76FC D4524D 42EBF5 FC5554 FF7978 D4C154 E6CE80 21B03B C95BBA CCCCCC FFFFFF`
+    Tms9918Composite,
+    /// TMS9928A palette derived from the Y, R-Y, and B-Y levels of its component video output
+    ///
+    /// `000000 000000 0AAD1E 34C84C 2B2DE3 514BFB BD2925 1EE2EF FB2C2B FF5F4C BDA22B D7B454 0A8C18 AF329A B2B2B2 FFFFFF`
+    Tms9928Component,
+    /// The classic TI-99/4A 12-bit palette, expanded to 24 bits
+    ///
+    /// `000000 000000 22CC33 55DD66 5544FF 7766FF DD5544 44EEFF FF5544 FF7766 DDCC33 EEDD66 22BB22 CC55CC CCCCCC FFFFFF`
+    Ti
+}
+
+impl BuiltinPalette {
+    /// Colors of this palette
+    pub fn colors(self) -> Palette {
+        match self {
+            BuiltinPalette::Original => [
+                0x000000, 0x000000, 0x21C942, 0x5EDC78,
+                0x5455ED, 0x7D75FC, 0xD3524D, 0x43EBF6,
+                0xFD5554, 0xFF7978, 0xD3C153, 0xE5CE80,
+                0x21B03C, 0xC95BBA, 0xCCCCCC, 0xFFFFFF
+            ],
+            BuiltinPalette::Tms9918Composite => [
+                0x000000, 0x000000, 0x21C842, 0x5EDC78,
+                0x5455ED, 0x7D76FC, 0xD4524D, 0x42EBF5,
+                0xFC5554, 0xFF7978, 0xD4C154, 0xE6CE80,
+                0x21B03B, 0xC95BBA, 0xCCCCCC, 0xFFFFFF
+            ],
+            BuiltinPalette::Tms9928Component => [
+                0x000000, 0x000000, 0x0AAD1E, 0x34C84C,
+                0x2B2DE3, 0x514BFB, 0xBD2925, 0x1EE2EF,
+                0xFB2C2B, 0xFF5F4C, 0xBDA22B, 0xD7B454,
+                0x0A8C18, 0xAF329A, 0xB2B2B2, 0xFFFFFF
+            ],
+            BuiltinPalette::Ti => [
+                0x000000, 0x000000, 0x22CC33, 0x55DD66,
+                0x5544FF, 0x7766FF, 0xDD5544, 0x44EEFF,
+                0xFF5544, 0xFF7766, 0xDDCC33, 0xEEDD66,
+                0x22BB22, 0xCC55CC, 0xCCCCCC, 0xFFFFFF
+            ]
+        }
+    }
+}
+
+/// Sprite attributes used by [`TMS9918A::define_sprite`]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct SpriteDef {
+    /// Horizontal position, 0 is the left edge of the screen
+    pub x: u8,
+    /// Vertical position
+    pub y: u8,
+    /// Sprite pattern number in the sprite pattern table
+    ///
+    /// The low two bits are ignored when using 16x16 sprites.
+    pub pattern: u8,
+    /// Sprite color, 0-15
+    pub color: u8,
+    /// Early clock: shift the sprite 32 pixels to the left, allowing it to slide in from the left edge of the screen
+    pub early_clock: bool
+}
+
+/// The eight VDP write-only registers
+///
+/// Converting a `Register` into a `u8` gives its register number, for use with the raw register functions.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Register {
+    /// Register 0: M3 mode bit and external video input
+    Control0,
+    /// Register 1: 4/16K memory, blanking, interrupt enable, M1 and M2 mode bits, sprite size and magnification
+    Control1,
+    /// Register 2: name table base address, in units of 0x400 bytes
+    NameTableBase,
+    /// Register 3: color table base address, in units of 0x40 bytes
+    /// 
+    /// In Graphics II mode only bit 7 is used, selecting 0x0000 or 0x2000.
+    ColorTableBase,
+    /// Register 4: pattern table base address, in units of 0x800 bytes
+    /// 
+    /// In Graphics II mode only bit 2 is used, selecting 0x0000 or 0x2000.
+    PatternTableBase,
+    /// Register 5: sprite attribute table base address, in units of 0x80 bytes
+    SpriteAttributeBase,
+    /// Register 6: sprite pattern table base address, in units of 0x800 bytes
+    SpritePatternBase,
+    /// Register 7: Text mode foreground color in the high 4 bits, backdrop color in the low 4 bits
+    TextColor
+}
+
+impl Register {
+    /// Size of one unit of the base address set by a table base address register
+    ///
+    /// The table base address is the register value multiplied by this, `None` for registers which are not table base addresses.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::{Register, TMS9918A};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.write_register_typed(Register::ColorTableBase, 0x80);
+    /// let granularity = Register::ColorTableBase.base_address_granularity().unwrap();
+    /// assert_eq!(vdp.read_register_typed(Register::ColorTableBase) as u16 * granularity, 0x2000);
+    /// ```
+    pub fn base_address_granularity(self) -> Option<u16> {
+        match self {
+            Register::NameTableBase => Some(0x0400),
+            Register::ColorTableBase => Some(0x0040),
+            Register::PatternTableBase => Some(0x0800),
+            Register::SpriteAttributeBase => Some(0x0080),
+            Register::SpritePatternBase => Some(0x0800),
+            _ => None
+        }
+    }
+}
+
+impl From<Register> for u8 {
+    fn from(register: Register) -> Self {
+        register as u8
+    }
+}
+
+bitflags! {
+    /// Bits of register 0
+    ///
+    /// Bits are numbered from 0 for the least significant bit, like everywhere else in this crate. The TMS9918A
+    /// Data Manual numbers them the other way around, from 0 for the most significant bit.
+    ///
+    /// Converting from a `u8` ignores the unused bits.
+    #[derive(Default)]
+    pub struct Reg0Flags: u8 {
+        /// Bit 1 (0x02): M3 mode bit, selects Graphics II mode
+        const M3 = 0x02;
+        /// Bit 0 (0x01): external video input enable
+        const EXTVID = 0x01;
+    }
+}
+
+bitflags! {
+    /// Bits of register 1
+    ///
+    /// Bits are numbered from 0 for the least significant bit, see [`Reg0Flags`].
+    ///
+    /// Converting from a `u8` ignores the unused bit 2 (0x04).
+    #[derive(Default)]
+    pub struct Reg1Flags: u8 {
+        /// Bit 7 (0x80): 4K/16K video memory selection, set for 16K
+        const MEM_16K = 0x80;
+        /// Bit 6 (0x40): blanking, set to enable the display
+        const BLANK = 0x40;
+        /// Bit 5 (0x20): interrupt enable
+        const IE = 0x20;
+        /// Bit 4 (0x10): M1 mode bit, selects Text mode
+        const M1 = 0x10;
+        /// Bit 3 (0x08): M2 mode bit, selects Multicolor mode
+        const M2 = 0x08;
+        /// Bit 1 (0x02): sprite size, set for 16x16 sprites
+        const SIZE = 0x02;
+        /// Bit 0 (0x01): sprite magnification, set to double the size of sprites
+        const MAG = 0x01;
+    }
+}
+
+impl From<u8> for Reg0Flags {
+    fn from(data: u8) -> Self {
+        Reg0Flags::from_bits_truncate(data)
+    }
+}
+
+impl From<Reg0Flags> for u8 {
+    fn from(flags: Reg0Flags) -> Self {
+        flags.bits()
+    }
+}
+
+impl From<u8> for Reg1Flags {
+    fn from(data: u8) -> Self {
+        Reg1Flags::from_bits_truncate(data)
+    }
+}
+
+impl From<Reg1Flags> for u8 {
+    fn from(flags: Reg1Flags) -> Self {
+        flags.bits()
+    }
+}
+
+/// Snapshot of the eight VDP registers, returned by [`TMS9918A::register_dump`]
+///
+/// Printing it with `{}` decodes the mode bits, the display and interrupt enable bits,
+/// the sprite size and magnification, the table base addresses, and the colors.
+///
+/// # Examples
+///
+/// ```
+/// # use tms9918a_emu::TMS9918A;
+/// let mut vdp = TMS9918A::new();
+/// let registers = [0x00, 0xE0, 0x00, 0x80, 0x00, 0x36, 0x07, 0xF1];
+/// for (register, &data) in registers.iter().enumerate() {
+///     vdp.write_register(register as u8, data);
+/// }
+/// assert_eq!(
+///     vdp.register_dump().to_string(),
+///     "R0=00 R1=E0 (16K, display on, IE, 8x8 sprites) \
+///      NT=0000 CT=2000 PT=0000 SA=1B00 SP=3800 FG=F BD=1"
+/// );
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct RegisterDump {
+    /// Register values, indexed by register number
+    pub registers: [u8; 8]
+}
+
+impl fmt::Display for RegisterDump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let r = &self.registers;
+
+        let r0 = Reg0Flags::from(r[0]);
+        let mut r0_flags = Vec::new();
+        if r0.contains(Reg0Flags::M3) { r0_flags.push("M3"); }
+        if r0.contains(Reg0Flags::EXTVID) { r0_flags.push("external video"); }
+
+        let r1 = Reg1Flags::from(r[1]);
+        let mut r1_flags = vec![
+            if r1.contains(Reg1Flags::MEM_16K) { "16K" } else { "4K" },
+            if r1.contains(Reg1Flags::BLANK) { "display on" } else { "display off" }
+        ];
+        if r1.contains(Reg1Flags::IE) { r1_flags.push("IE"); }
+        if r1.contains(Reg1Flags::M1) { r1_flags.push("M1"); }
+        if r1.contains(Reg1Flags::M2) { r1_flags.push("M2"); }
+        r1_flags.push(if r1.contains(Reg1Flags::SIZE) { "16x16 sprites" } else { "8x8 sprites" });
+        if r1.contains(Reg1Flags::MAG) { r1_flags.push("magnified"); }
+
+        write!(f, "R0={:02X} ", r[0])?;
+        if !r0_flags.is_empty() {
+            write!(f, "({}) ", r0_flags.join(", "))?;
+        }
+        write!(f, "R1={:02X} ({}) ", r[1], r1_flags.join(", "))?;
+        write!(
+            f,
+            "NT={:04X} CT={:04X} PT={:04X} SA={:04X} SP={:04X} FG={:X} BD={:X}",
+            (r[2] as usize & 0x0F) * 0x0400,
+            r[3] as usize * 0x0040,
+            (r[4] as usize & 0x07) * 0x0800,
+            (r[5] as usize & 0x7F) * 0x0080,
+            (r[6] as usize & 0x07) * 0x0800,
+            r[7] >> 4,
+            r[7] & 0x0F
+        )
+    }
+}
+
+/// Tiles used by [`TMS9918A::draw_box`] to draw the frame of a box
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct BoxTiles {
+    /// Corner tiles: top left, top right, bottom left, bottom right
+    pub corners: [u8; 4],
+    /// Edge tiles: top, bottom, left, right
+    pub edges: [u8; 4]
+}
+
+/// How [`TMS9918A::scroll_name_table`] fills the tiles scrolled onto the screen
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScrollFill {
+    /// Tiles scrolled off one edge of the screen come back in on the opposite edge
+    Wrap,
+    /// Vacated tiles are filled with this tile
+    Tile(u8)
+}
+
+/// Result of [`TMS9918A::tick`]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct TickResult {
+    /// The last scanline of the frame was completed, the framebuffer contains a complete frame
+    pub frame_ready: bool,
+    /// The last line of the active display area was completed and the frame flag was set
+    pub entered_vblank: bool
+}
+
+// master clock cycles per scanline, 342 pixels at half the master clock frequency
+const MASTER_CLOCKS_PER_LINE: u64 = 684;
+
+// visible border around the active display area: left, right, top, bottom
+// the active display area is 256x192 pixels, for a bordered frame of 284x243 pixels
+const BORDER: (usize, usize, usize, usize) = (13, 15, 27, 24);
+// Text mode has a wider border on both sides of its 240 pixels
+const TEXT_BORDER: (usize, usize, usize, usize) = (19, 25, 27, 24);
+// without the border, Text mode is centered in the same 256 pixel wide frame as the other modes
+const TEXT_PADDING: (usize, usize, usize, usize) = (8, 8, 0, 0);
+
+// 32 sprites with 4 bytes each
+const SPRITE_ATTRIBUTE_TABLE_SIZE: usize = 128;
+// 256 patterns with 8 bytes each
+const SPRITE_PATTERN_TABLE_SIZE: usize = 2048;
+
+// pixel masks of each pattern byte, from the leftmost pixel: 0xFF for a set pixel and 0x00 for a clear pixel
+// drawing a pattern byte selects between the foreground and background color with the masks, without branches
+static PATTERN_MASKS: [[u8; 8]; 256] = pattern_masks();
+
+const fn pattern_masks() -> [[u8; 8]; 256] {
+    let mut masks = [[0; 8]; 256];
+    let mut pattern = 0;
+    while pattern < 256 {
+        let mut pixel = 0;
+        while pixel < 8 {
+            if pattern & (0x80 >> pixel) != 0 {
+                masks[pattern][pixel] = 0xFF;
+            }
+            pixel += 1;
+        }
+        pattern += 1;
+    }
+    masks
+}
+
+// callback set by TMS9918A::on_vblank
+type VblankCallback = Box<dyn FnMut(&mut TMS9918A) + Send + Sync>;
+
+// callback set by TMS9918A::set_frame_sink
+type FrameSink = Box<dyn FnMut(&[u32], usize, usize) + Send + Sync>;
+
+// pseudo-random memory contents, like DRAM at power on
+// uses SplitMix64, so the same seed always gives the same contents on every platform
+fn random_memory(size: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed;
+    let mut memory = Vec::with_capacity(size + 8);
+    while memory.len() < size {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        memory.extend_from_slice(&(z ^ (z >> 31)).to_le_bytes());
+    }
+    memory.truncate(size);
+    memory
+}
+
+// seed for random memory contents, different for each VDP where the OS provides randomness
+// this uses the standard library's hash keys, which also work on targets without OS randomness like wasm32
+#[cfg(feature = "std")]
+fn random_seed() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    std::collections::hash_map::RandomState::new().build_hasher().finish()
+}
+
+// without the standard library there is no source of randomness, so every VDP starts with the same contents
+#[cfg(not(feature = "std"))]
+fn random_seed() -> u64 {
+    0x7E59_918A
+}
+
+// 64-bit FNV-1a hash of a sequence of bytes
+fn fnv1a<I: IntoIterator<Item = u8>>(bytes: I) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    bytes.into_iter().fold(FNV_OFFSET_BASIS, |hash, byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+// hash of the frame size followed by the pixels, see TMS9918A::frame_hash
+pub(crate) fn hash_frame(pixels: &[u32], width: usize, height: usize) -> u64 {
+    fnv1a([width as u32, height as u32].iter().chain(pixels.iter()).flat_map(|value| value.to_le_bytes()))
+}
+
+/// A TMS9918A video display processor
+///
+/// The emulation core doesn't own a window, so it is `Send` and `Sync` and can be shared between threads.
+/// Callbacks and charmaps given to it must be `Send` and `Sync` too. The intended pattern for a machine with the
+/// CPU on its own thread is a `Mutex<TMS9918A>`: the CPU thread locks it to access the ports, and the UI thread
+/// locks it to render, copies or presents the frame, then releases the lock before waiting for the next frame.
+/// A [`VdpHandle`] does the same without locking, by queueing port accesses for the owning thread.
+///
+/// # Video memory size
+///
+/// Like the real chip, the 4K/16K selection bit (bit 7 of register 1) is clear after reset, so the data port and the
+/// display only address the first 4K of video memory and every higher address aliases it. A table set above 0x0FFF
+/// is read from its alias in the first 4K. Programs must set this bit, for example by writing 0x80 or more to
+/// register 1, before using tables above 0x0FFF. [`enable_video`](TMS9918A::enable_video) doesn't change it. The
+/// table functions such as [`write_ram`](TMS9918A::write_ram) write video memory directly and always address all
+/// 16K, but the display still only shows the first 4K while the bit is clear.
+///
+/// # Examples
+///
+/// ```
+/// # use tms9918a_emu::TMS9918A;
+/// use std::sync::{Arc, Mutex};
+/// use std::thread;
+///
+/// let vdp = Arc::new(Mutex::new(TMS9918A::new()));
+///
+/// // CPU thread, writes the backdrop color to register 7
+/// let cpu_vdp = vdp.clone();
+/// let cpu = thread::spawn(move || {
+///     let mut vdp = cpu_vdp.lock().unwrap();
+///     vdp.write_control_port(0x04);
+///     vdp.write_control_port(0x87);
+/// });
+/// cpu.join().unwrap();
+///
+/// // UI thread, renders and presents the frame
+/// let mut vdp = vdp.lock().unwrap();
+/// vdp.render();
+/// assert_eq!(vdp.read_register(7), 0x04);
+/// ```
 pub struct TMS9918A {
     /// VDP framebuffer
     pub frame: Vec<u32>,
-    /// VDP framebuffer width
-    pub frame_width: usize,
-    /// VDP framebuffer height
-    pub frame_height: usize,
+    // palette color index of each pixel in the framebuffer
+    frame_indices: Vec<u8>,
+    // color indices of the line being drawn
+    line_indices: Vec<u8>,
+    // width and height of the framebuffer contents in the current video mode
+    frame_width: usize,
+    frame_height: usize,
     // if true, clear framebuffer on next update
     frame_clear: bool,
+    // if true, the framebuffer includes the border around the active display area
+    border_visible: bool,
+    // if true, print_text wraps to the next row at the right edge of the screen instead of clipping
+    text_wrap: bool,
+    // translation from characters to tiles used by print_text and the text console
+    charmap: Arc<dyn CharMap + Send + Sync>,
+    // tile written for characters which the charmap doesn't map
+    fallback_tile: u8,
+    // cursor and cursor display state of the text console
+    console: console::ConsoleState,
+    // color palette used for rendering, color 0 is transparent
+    palette: Palette,
+    // if true, Text mode is padded to the 256 pixel width of the other modes
+    text_padding: bool,
+    // if true, only display the four highest priority sprites on each line
+    sprite_limit: bool,
+    // if true, rotate which sprites are displayed on lines with more than four sprites
+    sprite_flicker: bool,
+    // incremented every frame while sprite flicker is enabled
+    sprite_flicker_rotation: usize,
+    // tiles of the active display area without sprites, as color indices with 256 per line, kept between frames
+    tile_layer: Vec<u8>,
+    // for each name table entry, bit n is set if pixel row n of the tile must be drawn into tile_layer again
+    dirty_tiles: Vec<u8>,
+    // for each pattern, bit n is set if row n was written since the dirty tiles were last updated
+    dirty_patterns: Vec<u8>,
+    // bit n is set if color table entry n was written since the dirty tiles were last updated
+    dirty_colors: u32,
+    // true if any bit of dirty_patterns or dirty_colors is set
+    tables_dirty: bool,
+    // tile lines drawn into tile_layer during the current frame and during the last completed frame
+    frame_tile_lines: usize,
+    last_frame_tile_lines: usize,
 
-    /// TMS9918A video memory, 16KB: contains name table, color table, and pattern table
-    /// 
-    /// Initialized with random values to simulate real memory behavior.
-    pub vdp_ram: Vec<u8>,
+    // video memory, 16KB, initialized with random values to simulate real memory behavior unless created with
+    // with_vram_init
+    // every write must mark the tiles using it as dirty, see mark_vram_written and VramMut
+    vdp_ram: Vec<u8>,
     // offsets into VDP_RAM for the various tables
     vdp_name_table_offset: u16,
     vdp_color_table_offset: u16,
     vdp_pattern_table_offset: u16,
+    vdp_sprite_attribute_offset: u16,
+    vdp_sprite_pattern_offset: u16,
     // TMS9918A registers
     vdp_register: Vec<u8>,
+    // TMS9918A status register
+    vdp_status: u8,
+    // scanline currently being displayed, 0-191 are the active display lines
+    vdp_scanline: u16,
+    // time elapsed on the current scanline, in CPU cycles * master clock frequency
+    vdp_line_clock: u64,
+    // CPU clock frequency in Hz, used by tick()
+    cpu_clock: u32,
+    // NTSC or PAL timing
+    video_standard: VideoStandard,
+    // emulated VDP variant
+    vdp_variant: VdpVariant,
     // TMS9918A video mode
     vdp_mode: VideoMode,
     // temporary data register
@@ -56,7 +923,291 @@ pub struct TMS9918A {
     // true after the first command byte was sent
     vdp_first_byte_saved_flag: bool,
     // byte at current memory address pointer
-    vdp_read_ahead: u8
+    vdp_read_ahead: u8,
+
+    // called at the end of each frame by update()
+    vblank_callback: Option<VblankCallback>,
+    // receives each completed frame
+    frame_sink: Option<FrameSink>,
+    // animated GIF recording, if running
+    #[cfg(feature = "gif")]
+    recorder: Option<recorder::Recorder>,
+    // TCP frame streaming, if running
+    #[cfg(feature = "net")]
+    frame_server: Option<net::FrameServer>,
+    // port accesses queued by handles, created by the first handle
+    #[cfg(feature = "std")]
+    commands: Option<handle::CommandQueue>,
+    // port access trace, if recording
+    #[cfg(feature = "std")]
+    tracer: Option<trace::TraceRecorder>
+}
+
+// the core must stay shareable between threads, see the TMS9918A docs
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<TMS9918A>();
+};
+
+impl Default for TMS9918A {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Copy the whole emulation state: video memory, registers, ports, timing, framebuffer and rendering settings
+///
+/// The copy shares the charmap of the original. Callbacks, recording, frame streaming and handles belong
+/// to the original only, so the copy starts without them.
+///
+/// # Examples
+///
+/// ```
+/// # use tms9918a_emu::TMS9918A;
+/// let mut vdp = TMS9918A::new();
+/// vdp.write_register(7, 0xF4);
+/// vdp.print_text(0, 0, "Hello");
+/// vdp.render();
+/// vdp.write_control_port(0x00);
+/// vdp.write_control_port(0x40);
+/// let copy = vdp.clone();
+/// assert!(copy == vdp);
+/// ```
+impl Clone for TMS9918A {
+    fn clone(&self) -> Self {
+        TMS9918A {
+            frame: self.frame.clone(),
+            frame_indices: self.frame_indices.clone(),
+            line_indices: self.line_indices.clone(),
+            frame_width: self.frame_width,
+            frame_height: self.frame_height,
+            frame_clear: self.frame_clear,
+            border_visible: self.border_visible,
+            text_wrap: self.text_wrap,
+            charmap: self.charmap.clone(),
+            fallback_tile: self.fallback_tile,
+            console: self.console,
+            palette: self.palette,
+            text_padding: self.text_padding,
+            sprite_limit: self.sprite_limit,
+            sprite_flicker: self.sprite_flicker,
+            sprite_flicker_rotation: self.sprite_flicker_rotation,
+            tile_layer: self.tile_layer.clone(),
+            dirty_tiles: self.dirty_tiles.clone(),
+            dirty_patterns: self.dirty_patterns.clone(),
+            dirty_colors: self.dirty_colors,
+            tables_dirty: self.tables_dirty,
+            frame_tile_lines: self.frame_tile_lines,
+            last_frame_tile_lines: self.last_frame_tile_lines,
+            vdp_ram: self.vdp_ram.clone(),
+            vdp_name_table_offset: self.vdp_name_table_offset,
+            vdp_color_table_offset: self.vdp_color_table_offset,
+            vdp_pattern_table_offset: self.vdp_pattern_table_offset,
+            vdp_sprite_attribute_offset: self.vdp_sprite_attribute_offset,
+            vdp_sprite_pattern_offset: self.vdp_sprite_pattern_offset,
+            vdp_register: self.vdp_register.clone(),
+            vdp_status: self.vdp_status,
+            vdp_scanline: self.vdp_scanline,
+            vdp_line_clock: self.vdp_line_clock,
+            cpu_clock: self.cpu_clock,
+            video_standard: self.video_standard,
+            vdp_variant: self.vdp_variant,
+            vdp_mode: self.vdp_mode,
+            vdp_temp_data: self.vdp_temp_data,
+            vdp_addr_pointer: self.vdp_addr_pointer,
+            vdp_first_byte_saved_flag: self.vdp_first_byte_saved_flag,
+            vdp_read_ahead: self.vdp_read_ahead,
+            vblank_callback: None,
+            frame_sink: None,
+            #[cfg(feature = "gif")]
+            recorder: None,
+            #[cfg(feature = "net")]
+            frame_server: None,
+            #[cfg(feature = "std")]
+            commands: None,
+            #[cfg(feature = "std")]
+            tracer: None
+        }
+    }
+}
+
+/// Compare the emulation state: video memory, registers, ports, timing, framebuffer and rendering settings
+///
+/// The charmap, callbacks, recording, frame streaming and handles aren't compared.
+impl PartialEq for TMS9918A {
+    fn eq(&self, other: &Self) -> bool {
+        // line_indices is only used while drawing a line, and the tile layer and dirty tiles only cache what is
+        // drawn from video memory, so they aren't part of the state
+        self.frame == other.frame
+            && self.frame_indices == other.frame_indices
+            && self.frame_width == other.frame_width
+            && self.frame_height == other.frame_height
+            && self.frame_clear == other.frame_clear
+            && self.border_visible == other.border_visible
+            && self.text_wrap == other.text_wrap
+            && self.fallback_tile == other.fallback_tile
+            && self.console == other.console
+            && self.palette == other.palette
+            && self.text_padding == other.text_padding
+            && self.sprite_limit == other.sprite_limit
+            && self.sprite_flicker == other.sprite_flicker
+            && self.sprite_flicker_rotation == other.sprite_flicker_rotation
+            && self.vdp_ram == other.vdp_ram
+            && self.vdp_name_table_offset == other.vdp_name_table_offset
+            && self.vdp_color_table_offset == other.vdp_color_table_offset
+            && self.vdp_pattern_table_offset == other.vdp_pattern_table_offset
+            && self.vdp_sprite_attribute_offset == other.vdp_sprite_attribute_offset
+            && self.vdp_sprite_pattern_offset == other.vdp_sprite_pattern_offset
+            && self.vdp_register == other.vdp_register
+            && self.vdp_status == other.vdp_status
+            && self.vdp_scanline == other.vdp_scanline
+            && self.vdp_line_clock == other.vdp_line_clock
+            && self.cpu_clock == other.cpu_clock
+            && self.video_standard == other.video_standard
+            && self.vdp_variant == other.vdp_variant
+            && self.vdp_mode == other.vdp_mode
+            && self.vdp_temp_data == other.vdp_temp_data
+            && self.vdp_addr_pointer == other.vdp_addr_pointer
+            && self.vdp_first_byte_saved_flag == other.vdp_first_byte_saved_flag
+            && self.vdp_read_ahead == other.vdp_read_ahead
+    }
+}
+
+/// Print a short summary of the VDP state for debugging, without video memory or the framebuffer
+///
+/// This shows the variant, the video mode, the registers, the table base addresses, the port state, the status
+/// register, the current scanline and a hash of video memory. Printing with `{}` shows the same state decoded
+/// over a few lines, for pasting into bug reports.
+///
+/// # Examples
+///
+/// ```
+/// # use tms9918a_emu::{TMS9918A, VramInit};
+/// let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+/// vdp.write_register(7, 0xF4);
+/// vdp.write_control_port(0x34);
+/// let debug = format!("{:?}", vdp);
+/// assert!(debug.starts_with("TMS9918A { variant: Tms9918a, mode: Gfx1, registers: [00, 00, 00, 00, 00, 00, 00, F4]"));
+/// assert!(debug.contains("latch: Some(34)"));
+/// assert!(debug.contains(&format!("vram_hash: {:#018X}", vdp.vram_hash())));
+/// ```
+impl fmt::Debug for TMS9918A {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let latch = if self.vdp_first_byte_saved_flag { Some(self.vdp_temp_data) } else { None };
+        f.debug_struct("TMS9918A")
+            .field("variant", &self.vdp_variant)
+            .field("mode", &self.vdp_mode)
+            .field("registers", &format_args!("{:02X?}", self.vdp_register))
+            .field("tables", &format_args!(
+                "NT={:04X} CT={:04X} PT={:04X} SA={:04X} SP={:04X}",
+                self.vdp_name_table_offset,
+                self.vdp_color_table_offset,
+                self.vdp_pattern_table_offset,
+                self.vdp_sprite_attribute_offset,
+                self.vdp_sprite_pattern_offset
+            ))
+            .field("address_pointer", &format_args!("{:04X}", self.vdp_addr_pointer))
+            .field("latch", &format_args!("{:02X?}", latch))
+            .field("read_ahead", &format_args!("{:02X}", self.vdp_read_ahead))
+            .field("status", &format_args!("{:02X}", self.vdp_status))
+            .field("scanline", &self.vdp_scanline)
+            .field("vram_hash", &format_args!("{:#018X}", self.vram_hash()))
+            .finish()
+    }
+}
+
+/// Print the VDP state decoded over a few lines, for bug reports
+///
+/// # Examples
+///
+/// ```
+/// # use tms9918a_emu::{TMS9918A, VideoMode, VramInit};
+/// let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+/// vdp.set_video_mode(VideoMode::Text);
+/// vdp.write_register(7, 0xF4);
+/// let display = format!("{}", vdp);
+/// assert!(display.contains("TMS9918A in Text mode, NTSC\n"));
+/// assert!(display.contains("\nregisters: 00 10 00 00 00 00 00 F4\n"));
+/// assert!(display.contains("\nR0=00 R1=10 (4K, display off, M1, 8x8 sprites) NT=0000 CT=0000 PT=0000 SA=0000 SP=0000 FG=F BD=4\n"));
+/// assert!(display.contains("\naddress pointer: 0000, latch: empty, read-ahead: 00\n"));
+/// assert!(display.contains("\nstatus: 00, scanline: 0\n"));
+/// assert!(display.contains("\nVRAM hash: 0x9C1BDA7F8C872325"));
+/// ```
+impl fmt::Display for TMS9918A {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let variant = match self.vdp_variant {
+            VdpVariant::Tms9918 => "TMS9918",
+            VdpVariant::Tms9918a => "TMS9918A",
+            VdpVariant::Tms9928a => "TMS9928A",
+            VdpVariant::Tms9929a => "TMS9929A"
+        };
+        let mode = match self.vdp_mode {
+            VideoMode::Gfx1 => "Graphics I",
+            VideoMode::Gfx2 => "Graphics II",
+            VideoMode::Text => "Text",
+            VideoMode::Multicolor => "Multicolor"
+        };
+        let standard = match self.video_standard {
+            VideoStandard::Ntsc => "NTSC",
+            VideoStandard::Pal => "PAL"
+        };
+        writeln!(f, "{} in {} mode, {}", variant, mode, standard)?;
+
+        write!(f, "registers:")?;
+        for register in &self.vdp_register {
+            write!(f, " {:02X}", register)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "{}", self.register_dump())?;
+
+        write!(f, "address pointer: {:04X}, latch: ", self.vdp_addr_pointer)?;
+        if self.vdp_first_byte_saved_flag {
+            write!(f, "{:02X}", self.vdp_temp_data)?;
+        } else {
+            write!(f, "empty")?;
+        }
+        writeln!(f, ", read-ahead: {:02X}", self.vdp_read_ahead)?;
+
+        let mut status_flags = Vec::new();
+        if self.vdp_status & 0x80 != 0 { status_flags.push("frame".to_string()); }
+        if self.vdp_status & 0x40 != 0 { status_flags.push(format!("fifth sprite {}", self.vdp_status & 0x1F)); }
+        if self.vdp_status & 0x20 != 0 { status_flags.push("coincidence".to_string()); }
+        write!(f, "status: {:02X}", self.vdp_status)?;
+        if !status_flags.is_empty() {
+            write!(f, " ({})", status_flags.join(", "))?;
+        }
+        writeln!(f, ", scanline: {}", self.vdp_scanline)?;
+
+        write!(f, "VRAM hash: {:#018X}", self.vram_hash())
+    }
+}
+
+/// Mutable access to the whole 16KB video memory, returned by [`TMS9918A::vram_mut`]
+///
+/// This dereferences to the video memory as a byte slice. When it is dropped, every tile is drawn again on the
+/// next frame, because the VDP can't tell which parts of video memory were changed.
+pub struct VramMut<'a> {
+    vdp: &'a mut TMS9918A
+}
+
+impl Deref for VramMut<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.vdp.vdp_ram
+    }
+}
+
+impl DerefMut for VramMut<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.vdp.vdp_ram
+    }
+}
+
+impl Drop for VramMut<'_> {
+    fn drop(&mut self) {
+        self.vdp.invalidate_tiles();
+    }
 }
 
 impl TMS9918A {
@@ -71,26 +1222,107 @@ impl TMS9918A {
     /// # }
     /// ```
     pub fn new() -> Self {
+        Self::with_variant(VdpVariant::Tms9918a)
+    }
+
+    /// Create a new VDP state emulating a specific VDP variant
+    /// 
+    /// The variant selects which video modes are available, the default video standard, and the default palette.
+    /// 
+    /// # Examples
+    /// 
+    /// ```no_run
+    /// # use tms9918a_emu::{TMS9918A, VdpVariant};
+    /// # fn main() {
+    /// // emulate the PAL TMS9929A
+    /// let mut vdp = TMS9918A::with_variant(VdpVariant::Tms9929a);
+    /// # }
+    /// ```
+    pub fn with_variant(variant: VdpVariant) -> Self {
+        Self::with_variant_and_vram_init(variant, VramInit::Random { seed: random_seed() })
+    }
+
+    /// Create a new VDP state with the given video memory contents
+    /// 
+    /// Use [`VramInit::Zero`] or a fixed [`VramInit::Random`] seed for the same contents every time.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VramInit};
+    /// let vdp = TMS9918A::with_vram_init(VramInit::Fill(0xAA));
+    /// assert!(vdp.vram().iter().all(|&byte| byte == 0xAA));
+    /// 
+    /// // the same seed gives the same garbage screen
+    /// let a = TMS9918A::with_vram_init(VramInit::Random { seed: 1234 });
+    /// let b = TMS9918A::with_vram_init(VramInit::Random { seed: 1234 });
+    /// assert_eq!(a.vram(), b.vram());
+    /// ```
+    pub fn with_vram_init(init: VramInit) -> Self {
+        Self::with_variant_and_vram_init(VdpVariant::Tms9918a, init)
+    }
+
+    /// Create a new VDP state emulating a specific VDP variant, with the given video memory contents
+    pub fn with_variant_and_vram_init(variant: VdpVariant, init: VramInit) -> Self {
         TMS9918A {
-            frame: vec![0; 256 * 196],
+            frame: vec![0; 256 * 192],
+            frame_indices: vec![0; 256 * 192],
+            line_indices: Vec::new(),
             frame_width: 256,
-            frame_height: 196,
+            frame_height: 192,
             frame_clear: false,
-            vdp_ram: (0..16*1024).map(|_| rand::thread_rng().gen()).collect(),
+            border_visible: false,
+            text_padding: true,
+            text_wrap: false,
+            charmap: Arc::new(Identity),
+            fallback_tile: b'?',
+            console: console::ConsoleState::default(),
+            palette: variant.default_palette().colors(),
+            sprite_limit: true,
+            sprite_flicker: false,
+            sprite_flicker_rotation: 0,
+            tile_layer: vec![0; 256 * 192],
+            dirty_tiles: vec![0xFF; 960],
+            dirty_patterns: vec![0; 256],
+            dirty_colors: 0,
+            tables_dirty: false,
+            frame_tile_lines: 0,
+            last_frame_tile_lines: 0,
+            vdp_ram: init.memory(16 * 1024),
             vdp_name_table_offset: 0,
             vdp_color_table_offset: 0,
             vdp_pattern_table_offset: 0,
+            vdp_sprite_attribute_offset: 0,
+            vdp_sprite_pattern_offset: 0,
             vdp_register: vec![0; 8],
+            vdp_status: 0,
+            vdp_scanline: 0,
+            vdp_line_clock: 0,
+            cpu_clock: 3_579_545,
+            video_standard: variant.video_standard(),
+            vdp_variant: variant,
             vdp_mode: VideoMode::Gfx1,
             vdp_temp_data: 0,
             vdp_addr_pointer: 0,
             vdp_first_byte_saved_flag: false,
-            vdp_read_ahead: 0
+            vdp_read_ahead: 0,
+            vblank_callback: None,
+            frame_sink: None,
+            #[cfg(feature = "gif")]
+            recorder: None,
+            #[cfg(feature = "net")]
+            frame_server: None,
+            #[cfg(feature = "std")]
+            commands: None,
+            #[cfg(feature = "std")]
+            tracer: None
         }
     }
 
     /// Update the framebuffer from the TMS9918A video memory contents
     ///
+    /// This is the same as [`render`](TMS9918A::render). To display the frame, use `window::Window::update`.
+    ///
     /// # Examples
     /// 
     /// ```no_run
@@ -104,403 +1336,2238 @@ impl TMS9918A {
     /// # }
     /// ```
     pub fn update(&mut self) {
-        let colors: [u32; 16] = [
-            0x000000, 0x000000, 0x21C942, 0x5EDC78,
-            0x5455ED, 0x7D75FC, 0xD3524D, 0x43EBF6,
-            0xFD5554, 0xFF7978, 0xD3C153, 0xE5CE80,
-            0x21B03C, 0xC95BBA, 0xCCCCCC, 0xFFFFFF
-        ];
-
-        if self.frame_clear {
-            for i in self.frame.iter_mut() {
-                *i = 0;
-            }
-            self.frame_clear = false;
-        }
-
-        // check blanking bit
-        if self.vdp_register[1] & (1 << 6) != 0 {
-            // blanking bit is set, screen is enabled
-            match self.vdp_mode {
-                VideoMode::Gfx1 => {
-                    self.frame_width = 256;
-                    self.frame_height = 196;
-                    for tile_y in 0..24 {
-                        for tile_x in 0..32 {
-                            let name_entry = self.vdp_ram[self.vdp_name_table_offset as usize + (tile_y * 32) + tile_x];
-                            let color_entry = name_entry / 8;
-                            let color_byte = self.vdp_ram[self.vdp_color_table_offset as usize + color_entry as usize];
-                            let foreground_color = colors[color_byte as usize >> 4 & 0x0F];
-                            let background_color = colors[color_byte as usize & 0x0F];
-                            for pattern_byte in 0..8 {
-                                let offset = self.vdp_pattern_table_offset as usize + (name_entry as usize * 8) + (pattern_byte);
-                                let pattern = self.vdp_ram[offset];
-                                let pattern_bit_indexes = 0..8;
-                                let frame_bit_indexes = (0..8).rev();
-                                for (pattern_bit, frame_bit) in pattern_bit_indexes.zip(frame_bit_indexes) {
-                                    let pixel = if pattern & (1 << pattern_bit) != 0 { foreground_color } else { background_color };
-                                    let frame_offset = (tile_x * 8) + (tile_y * 8 * self.frame_width) + (pattern_byte * self.frame_width) + frame_bit;
-                                    self.frame[frame_offset] = pixel;
-                                }
-                            }
-                        }
-                    }
-                }
-                VideoMode::Text => {
-                    self.frame_width = 240;
-                    self.frame_height = 196;
-                    for tile_y in 0..24 {
-                        for tile_x in 0..40 {
-                            let name_entry = self.vdp_ram[self.vdp_name_table_offset as usize + (tile_y * 40) + tile_x];
-                            let color_byte = self.vdp_register[7];
-                            let foreground_color = colors[color_byte as usize >> 4 & 0x0F];
-                            let background_color = colors[color_byte as usize & 0x0F];
-                            for pattern_byte in 0..8 {
-                                let offset = self.vdp_pattern_table_offset as usize + (name_entry as usize * 8) + (pattern_byte);
-                                let pattern = self.vdp_ram[offset];
-                                let pattern_bit_indexes = 2..8;
-                                let frame_bit_indexes = (0..6).rev();
-                                for (pattern_bit, frame_bit) in pattern_bit_indexes.zip(frame_bit_indexes) {
-                                    let pixel = if pattern & (1 << pattern_bit) != 0 { foreground_color } else { background_color };
-                                    let frame_offset = (tile_x * 6) + (tile_y * 8 * self.frame_width) + (pattern_byte * self.frame_width) + frame_bit;
-                                    self.frame[frame_offset] = pixel;
-                                }
-                            }
-                        }
-                    }
-                }
-                _ => panic!("unimplemented video mode: {:?}", self.vdp_mode),
-            };
-        } else {
-            // blanking bit is clear, screen is disabled
-            for i in self.frame.iter_mut() {
-                *i = 0;
-            }
-        }
+        self.render();
     }
 
-    /// Enable or disable the video display by setting or clearing the blanking bit in register 1
-    /// 
-    /// The video display is disabled by default due to registers 0 and 1 being cleared on reset,
-    /// resulting in a black screen similar to the behavior of a real TMS9918A.
-    #[inline]
-    pub fn enable_video(&mut self, enable: bool) {
-        if enable {
-            self.vdp_register[1] |= 1 << 6;
-        } else {
-            self.vdp_register[1] &= !(1 << 6);
+    /// Render a complete frame from the TMS9918A video memory contents into the framebuffer
+    ///
+    /// This never touches a window, the framebuffer can be inspected or post-processed
+    /// before it is displayed, or not displayed at all.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// // white foreground and background
+    /// vdp.write_register(7, 0xFF);
+    /// vdp.enable_video(true);
+    /// vdp.render();
+    /// assert!(vdp.frame.iter().all(|&p| p == 0xFFFFFF));
+    /// ```
+    pub fn render(&mut self) {
+        for line in 0..192 {
+            self.render_scanline(line);
         }
+        self.vdp_line_clock = 0;
+        self.end_frame();
     }
 
-    /// Reset VDP to initial state without modifying video memory
-    pub fn warm_reset(&mut self) {
-        self.write_register(0, 0);
-        self.write_register(1, 0);
-        self.vdp_temp_data = 0;
-        self.vdp_addr_pointer = 0;
-        self.vdp_read_ahead = 0;
-        self.vdp_first_byte_saved_flag = false;
-    }
-
-    /// Reset VDP to initial state and randomize video memory contents
-    pub fn cold_reset(&mut self) {
-        self.warm_reset();
-        for i in self.vdp_ram.iter_mut() {
-            *i = rand::thread_rng().gen();
-        }
-    }
-
-    /// Set TMS9918A video mode
-    /// 
-    /// Valid video modes are Text, Graphics I, Graphics II, and Multicolor.
-    /// 
-    /// Graphics II and Multicolor modes are not currently implemented, and sprites are not currently implemented in any mode.
-    /// 
-    /// Undocumented modes (combining video modes by setting the bitmap enable bit in register 0) are not supported.
-    /// 
+    /// Advance the VDP by a number of CPU clock cycles
+    ///
+    /// This allows running the VDP in lockstep with an emulated CPU. Each scanline is rendered
+    /// when it is completed, and the frame flag is set when the last line of the active display area is completed.
+    ///
+    /// The CPU clock frequency is set by [`set_cpu_clock`](TMS9918A::set_cpu_clock),
+    /// and defaults to 3.579545 MHz (a Z80 running at the VDP master clock frequency divided by 3).
+    ///
     /// # Examples
-    /// 
-    /// ```no_run
-    /// # use tms9918a_emu::{TMS9918A, VideoMode};
-    /// # fn main() {
-    /// # let mut vdp = TMS9918A::new();
-    /// // use text mode
-    /// vdp.set_video_mode(VideoMode::Text);
-    /// # }
+    ///
     /// ```
-    pub fn set_video_mode(&mut self, mode: VideoMode) {
-        match mode {
-            VideoMode::Gfx1 => {
-                let r0 = self.vdp_register[0] & !(0b01000000);
-                let r1 = self.vdp_register[1] & !(0b00011000);
-                self.write_register(0, r0);
-                self.write_register(1, r1);
-            }
-            VideoMode::Gfx2 => {
-                let r0 = self.vdp_register[0] | (0b01000000);
-                let r1 = self.vdp_register[1] & !(0b00011000);
-                self.write_register(0, r0);
-                self.write_register(1, r1);
-            }
-            VideoMode::Multicolor => {
-                let r0 = self.vdp_register[0] & !(0b01000000);
-                let r1 = (self.vdp_register[1] & !(0b00010000)) | 0b00001000;
-                self.write_register(0, r0);
-                self.write_register(1, r1);
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// // at 3.579545 MHz, each scanline takes 228 CPU cycles
+    /// let result = vdp.tick((192 * 228) - 1);
+    /// assert!(!result.entered_vblank);
+    /// assert_eq!(vdp.current_scanline(), 191);
+    /// let result = vdp.tick(1);
+    /// assert!(result.entered_vblank);
+    /// assert!(vdp.is_vblank());
+    /// // the frame ends after 262 scanlines
+    /// let result = vdp.tick(70 * 228);
+    /// assert!(result.frame_ready);
+    /// assert_eq!(vdp.current_scanline(), 0);
+    /// ```
+    pub fn tick(&mut self, cpu_cycles: u32) -> TickResult {
+        let mut result = TickResult::default();
+        let line_length = MASTER_CLOCKS_PER_LINE * self.cpu_clock as u64;
+        self.vdp_line_clock += cpu_cycles as u64 * self.video_standard.master_clock();
+        while self.vdp_line_clock >= line_length {
+            // the current scanline is complete
+            self.vdp_line_clock -= line_length;
+            let line = self.vdp_scanline;
+            if line < 192 {
+                self.render_scanline(line as usize);
             }
-            VideoMode::Text => {
-                let r0 = self.vdp_register[0] & !(0b01000000);
-                let r1 = (self.vdp_register[1] & !(0b00001000)) | 0b00010000;
-                self.write_register(0, r0);
-                self.write_register(1, r1);
+            if line + 1 == 192 {
+                self.end_frame();
+                result.entered_vblank = true;
+            } else if line + 1 >= self.video_standard.scanlines_per_frame() {
+                self.vdp_scanline = 0;
+                result.frame_ready = true;
+            } else {
+                self.vdp_scanline = line + 1;
             }
         }
+        result
     }
 
-    /// Write register value
-    pub fn write_register(&mut self, register: u8, data: u8) {
-        // write register value
-        self.vdp_register[register as usize] = data;
-
-        // write offset values
-        self.vdp_name_table_offset = self.vdp_register[2] as u16 * 0x0400;
-        self.vdp_color_table_offset = self.vdp_register[3] as u16 * 0x0040;
-        self.vdp_pattern_table_offset = self.vdp_register[4] as u16 * 0x0800;
-
-        // write video mode
-        if register == 0 || register == 1 {
-            // register 0 bit 6: enable a bitmap graphics mode
-            let m3 = if self.vdp_register[0] & (1 << 6) != 0 { true } else { false };
-            // register 1 bit 3: enable text mode
-            let m1 = if self.vdp_register[1] & (1 << 4) != 0 { true } else { false };
-            // register 0 bit 6: enable multicolor mode
-            let m2 = if self.vdp_register[1] & (1 << 3) != 0 { true } else { false };
-
-            match (m1, m2, m3) {
-                (false, false, false) => {
-                    self.vdp_mode = VideoMode::Gfx1;
-                    // clear framebuffer on next update
-                    self.frame_clear = true;
-                }
-                (false, false, true) => {
-                    self.vdp_mode = VideoMode::Gfx2;
-                    // clear framebuffer on next update
-                    self.frame_clear = true;
-                }
-                (false, true, false) => {
-                    self.vdp_mode = VideoMode::Multicolor;
-                    // clear framebuffer on next update
-                    self.frame_clear = true;
-                }
-                (true, false, false) => {
-                    self.vdp_mode = VideoMode::Text;
-                    // clear framebuffer on next update
-                    self.frame_clear = true;
-                }
-                _ => panic!("unimplemented video mode combination: M1: {}, M2: {}, M3: {}", m1, m2, m3)
-            }
+    /// Get the emulated VDP variant
+    #[inline]
+    pub fn variant(&self) -> VdpVariant {
+        self.vdp_variant
+    }
 
-            //println!("set graphics mode: {:?}", self.vdp_mode);
-        }
+    /// Set the video standard, which selects between NTSC and PAL timing
+    ///
+    /// The default depends on the VDP variant, NTSC is used for all variants except the TMS9929A. This affects [`tick`](TMS9918A::tick), the number of scanlines per frame,
+    /// and [`frame_duration`](TMS9918A::frame_duration).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VideoStandard};
+    /// let mut vdp = TMS9918A::new();
+    /// // emulate a TMS9929A
+    /// vdp.set_video_standard(VideoStandard::Pal);
+    /// assert_eq!(vdp.frame_duration().as_millis(), 20);
+    /// ```
+    #[inline]
+    pub fn set_video_standard(&mut self, standard: VideoStandard) {
+        self.video_standard = standard;
     }
 
-    /// Read register value
-    /// 
-    /// Reading from VDP registers is not supported by the real hardware.
-    /// 
-    /// This is mainly intended for debugging purposes.
-    pub fn read_register(&mut self, register: u8) -> u8 {
-        let register = self.vdp_register[register as usize];
-        register
+    /// Get the video standard
+    #[inline]
+    pub fn video_standard(&self) -> VideoStandard {
+        self.video_standard
     }
 
-    /// Write memory contents
+    /// Get the duration of one frame for the current video standard
+    ///
+    /// This is useful for limiting the update rate of a window to the real frame rate.
     #[inline]
-    pub fn write_ram(&mut self, address: usize, data: u8) {
-        self.vdp_ram[address] = data;
+    pub fn frame_duration(&self) -> Duration {
+        self.video_standard.frame_duration()
     }
 
-    /// Read memory contents
+    /// Set the CPU clock frequency in Hz used by [`tick`](TMS9918A::tick)
     #[inline]
-    pub fn read_ram(&mut self, address: usize) -> u8 {
-        let data = self.vdp_ram[address];
-        data
+    pub fn set_cpu_clock(&mut self, frequency: u32) {
+        self.cpu_clock = frequency.max(1);
     }
 
-    /// Set the name table address multiplier in register 2
-    /// 
-    /// Name table base address is equal to multiplier * 0x0400.
-    /// 
-    /// This function is equivalent to setting register 2 directly.
-    /// 
+    /// Update one line of the framebuffer from the TMS9918A video memory contents
+    ///
+    /// Each line is rendered using the registers as they are when the line is rendered,
+    /// so registers can be changed between lines to create raster effects.
+    /// [`update`](TMS9918A::update) renders all 192 lines of the active display area in order.
+    ///
+    /// Lines outside of the active display area (0-191) are ignored.
+    ///
     /// # Examples
-    /// 
-    /// ```no_run
+    ///
+    /// ```
     /// # use tms9918a_emu::{TMS9918A, VideoMode};
-    /// # fn main() {
-    /// # let mut vdp = TMS9918A::new();
-    /// // set name table base address to 0x0400
-    /// vdp.set_name_table_multiplier(1);
-    /// # }
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// vdp.enable_video(true);
+    /// // blank tiles with a black background on the top half of the screen
+    /// for i in 0..0x4000 {
+    ///     vdp.write_ram(i, 0);
+    /// }
+    /// vdp.write_register(7, 0x01);
+    /// for line in 0..96 {
+    ///     vdp.render_scanline(line);
+    /// }
+    /// // and white on the bottom half
+    /// vdp.write_register(7, 0x0F);
+    /// for line in 96..192 {
+    ///     vdp.render_scanline(line);
+    /// }
+    /// assert_eq!(vdp.frame[95 * 256], 0x000000);
+    /// assert_eq!(vdp.frame[96 * 256], 0xFFFFFF);
     /// ```
-    #[inline]
-    pub fn set_name_table_multiplier(&mut self, mut multiplier: u8) {
-        if multiplier > 15 {
-            multiplier = 15;
+    pub fn render_scanline(&mut self, line: usize) {
+        if line >= 192 {
+            return;
+        }
+        self.vdp_scanline = line as u16;
+
+        if self.frame_clear {
+            self.frame.fill(0);
+            self.frame_indices.fill(0);
+            self.frame_clear = false;
         }
-        self.write_register(2, multiplier);
+
+        // take the framebuffer out while drawing so the line can be drawn into it
+        let mut frame = core::mem::take(&mut self.frame);
+        let mut frame_indices = core::mem::take(&mut self.frame_indices);
+        let frame_width = self.frame_width;
+        self.draw_frame_line(line, &mut frame, Some(&mut frame_indices), frame_width, 0);
+        self.frame = frame;
+        self.frame_indices = frame_indices;
     }
 
-    /// Fill name table contents from an array
-    /// 
-    /// Name table offset register must be set first.
-    /// 
+    /// Render a complete frame into a framebuffer owned by the caller
+    ///
+    /// The frame is drawn with its top left corner at `origin_x`, `origin_y` in a buffer with `stride` pixels per row.
+    /// The frame is [`frame_width`](TMS9918A::frame_width) pixels wide and [`frame_height`](TMS9918A::frame_height) pixels tall.
+    /// Like [`render`](TMS9918A::render), this sets the frame flag and runs the vblank callback,
+    /// but the VDP's own framebuffer is left untouched.
+    ///
+    /// Returns an error if the stride is too small to fit the frame next to `origin_x`,
+    /// or if the buffer is too small to fit the frame at the given origin.
+    ///
     /// # Examples
-    /// 
-    /// ```no_run
+    ///
+    /// ```
     /// # use tms9918a_emu::{TMS9918A, VideoMode};
-    /// # fn main() {
-    /// # let mut vdp = TMS9918A::new();
-    /// // fill the first 5 name table entries
-    /// let name_table: [u8; 5] = [1, 2, 3, 4, 5];
-    /// vdp.fill_name_table(&name_table, 0, name_table.len());
-    /// # }
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// vdp.write_register(7, 0xFF);
+    /// vdp.enable_video(true);
+    ///
+    /// // draw the frame in the bottom right corner of a 320x200 surface
+    /// let mut surface = vec![0; 320 * 200];
+    /// vdp.render_into(&mut surface, 320, 64, 8).unwrap();
+    /// assert_eq!(surface[8 * 320 + 63], 0);
+    /// assert_eq!(surface[8 * 320 + 64], 0xFFFFFF);
+    /// assert_eq!(surface[320 * 200 - 1], 0xFFFFFF);
     /// ```
-    #[inline]
-    pub fn fill_name_table(&mut self, array: &[u8], offset: usize, length: usize) {
-        for i in offset..offset+length {
-            self.write_name_table(i, array[i]);
+    pub fn render_into(&mut self, buffer: &mut [u32], stride: usize, origin_x: usize, origin_y: usize) -> Result<(), TMS9918AError> {
+        let minimum_stride = origin_x.saturating_add(self.frame_width);
+        if stride < minimum_stride {
+            return Err(TMS9918AError::StrideTooSmall(minimum_stride));
+        }
+        let required_length = origin_y.saturating_add(self.frame_height - 1)
+            .saturating_mul(stride)
+            .saturating_add(minimum_stride);
+        if buffer.len() < required_length {
+            return Err(TMS9918AError::BufferTooSmall(required_length));
         }
-    }
 
-    /// Clear the screen by zeroing the name table
-    /// 
-    /// Name table offset register must be set first.
-    #[inline]
-    pub fn clear_name_table(&mut self) {
-        if self.vdp_mode == VideoMode::Text {
-            // text mode's name table is 960 bytes
-            for i in 0..960 {
-                self.write_name_table(i, 0);
-            }
-        } else {
-            // all other modes' name tables are 768 bytes
-            for i in 0..768 {
-                self.write_name_table(i, 0);
-            }
+        for line in 0..192 {
+            self.vdp_scanline = line as u16;
+            self.draw_frame_line(line, buffer, None, stride, origin_y * stride + origin_x);
         }
+        self.vdp_line_clock = 0;
+        self.end_frame();
+        Ok(())
     }
 
-    /// Write name table contents
-    /// 
-    /// Name table offset register must be set first.
+    /// Get the width of the framebuffer contents in pixels
+    ///
+    /// This is 256 pixels, or 284 pixels when the border is visible.
+    /// With Text mode padding disabled, this is 240 pixels in Text mode while the border is hidden.
+    /// It changes as soon as the video mode, the border visibility, or the Text mode padding changes.
+    /// Rows in [`frame`](TMS9918A::frame) are this many pixels long.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// assert_eq!((vdp.frame_width(), vdp.frame_height()), (256, 192));
+    /// vdp.set_text_padding(false);
+    /// assert_eq!((vdp.frame_width(), vdp.frame_height()), (240, 192));
+    /// ```
     #[inline]
-    pub fn write_name_table(&mut self, offset: usize, data: u8) {
-        self.vdp_ram[self.vdp_name_table_offset as usize + offset] = data;
+    pub fn frame_width(&self) -> usize {
+        self.frame_width
     }
 
-    /// Read name table contents
-    /// 
-    /// Name table offset register must be set first.
+    /// Get the height of the framebuffer contents in pixels
+    ///
+    /// This is the 192 lines of the active display area, or 243 lines when the border is visible.
     #[inline]
-    pub fn read_name_table(&self, offset: usize) -> u8 {
-        self.vdp_ram[self.vdp_name_table_offset as usize + offset]
+    pub fn frame_height(&self) -> usize {
+        self.frame_height
     }
 
-    /// Set the color table address multiplier in register 3
-    /// 
-    /// Color table base address is equal to multiplier * 0x0040.
-    /// 
-    /// This function is equivalent to setting register 3 directly.
-    /// 
+    /// Iterate over the rows of the framebuffer, from top to bottom
+    ///
+    /// Each row is [`frame_width`](TMS9918A::frame_width) pixels, and there are [`frame_height`](TMS9918A::frame_height) rows.
+    ///
     /// # Examples
-    /// 
-    /// ```no_run
-    /// # use tms9918a_emu::{TMS9918A, VideoMode};
-    /// # fn main() {
-    /// # let mut vdp = TMS9918A::new();
-    /// // set color table base address to 0x0040
-    /// vdp.set_color_table_multiplier(1);
-    /// # }
+    ///
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_border_visible(true);
+    /// vdp.render();
+    /// assert_eq!(vdp.scanlines().len(), vdp.frame_height());
+    /// assert!(vdp.scanlines().all(|row| row.len() == vdp.frame_width()));
+    ///
+    /// // darken every other row in place
+    /// for row in vdp.scanlines_mut().skip(1).step_by(2) {
+    ///     for pixel in row {
+    ///         *pixel = (*pixel >> 1) & 0x7F7F7F;
+    ///     }
+    /// }
     /// ```
     #[inline]
-    pub fn set_color_table_multiplier(&mut self, multiplier: u8) {
-        self.write_register(3, multiplier);
+    pub fn scanlines(&self) -> core::slice::ChunksExact<'_, u32> {
+        self.frame.chunks_exact(self.frame_width)
     }
 
-    /// Fill color table contents from an array
-    /// 
-    /// Color table offset register must be set first.
-    /// 
+    /// Iterate over the rows of the framebuffer for modifying them, from top to bottom
+    ///
+    /// See [`scanlines`](TMS9918A::scanlines).
+    #[inline]
+    pub fn scanlines_mut(&mut self) -> core::slice::ChunksExactMut<'_, u32> {
+        self.frame.chunks_exact_mut(self.frame_width)
+    }
+
+    /// Convert the framebuffer to 8 bit RGBA, 4 bytes per pixel in the order red, green, blue, alpha
+    ///
+    /// Alpha is always 0xFF. The buffer is cleared first and reuses its allocation, so passing the same
+    /// buffer every frame doesn't allocate after the first frame. Pixels are row by row, from the top left.
+    ///
     /// # Examples
-    /// 
-    /// ```no_run
-    /// # use tms9918a_emu::{TMS9918A, VideoMode};
-    /// # fn main() {
-    /// # let mut vdp = TMS9918A::new();
-    /// // fill the first 5 color table entries
-    /// // black on white, white on black, light blue on dark blue, light red on cyan, black on gray
-    /// let color_table: [u8; 5] = [0x1F, 0xF1, 0x54, 0x97, 0x1E];
-    /// vdp.fill_color_table(&color_table, 0, color_table.len());
-    /// # }
+    ///
     /// ```
-    #[inline]
-    pub fn fill_color_table(&mut self, array: &[u8], offset: usize, length: usize) {
-        for i in offset..offset+length {
-            self.write_color_table(i, array[i]);
-        }
+    /// # use tms9918a_emu::{Color, TMS9918A};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_backdrop_color(Color::LightBlue);
+    /// vdp.render();
+    /// let mut rgba = Vec::new();
+    /// vdp.frame_as_rgba8(&mut rgba);
+    /// assert_eq!(rgba.len(), vdp.frame_width() * vdp.frame_height() * 4);
+    /// assert_eq!(rgba[..4], [0x7D, 0x75, 0xFC, 0xFF]);
+    /// ```
+    pub fn frame_as_rgba8(&self, buffer: &mut Vec<u8>) {
+        buffer.clear();
+        buffer.extend(self.frame.iter().flat_map(|&color| [(color >> 16) as u8, (color >> 8) as u8, color as u8, 0xFF]));
     }
 
-    /// Write color table contents
-    /// 
-    /// Color table offset register must be set first.
-    #[inline]
-    pub fn write_color_table(&mut self, offset: usize, data: u8) {
-        self.vdp_ram[self.vdp_color_table_offset as usize + offset] = data;
+    /// Convert the framebuffer to 16 bit RGB565, one `u16` per pixel
+    ///
+    /// Red is in bits 15-11, green in bits 10-5 and blue in bits 4-0, each keeping the high bits of the 8 bit value.
+    /// The buffer is cleared first and reuses its allocation. Pixels are row by row, from the top left.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::{Color, TMS9918A};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_backdrop_color(Color::White);
+    /// vdp.render();
+    /// let mut rgb565 = Vec::new();
+    /// vdp.frame_as_rgb565(&mut rgb565);
+    /// assert_eq!(rgb565[0], 0xFFFF);
+    /// ```
+    pub fn frame_as_rgb565(&self, buffer: &mut Vec<u16>) {
+        buffer.clear();
+        buffer.extend(self.frame.iter().map(|&color| {
+            ((color >> 8) & 0xF800 | (color >> 5) & 0x07E0 | (color >> 3) & 0x001F) as u16
+        }));
     }
 
-    /// Read color table contents
-    /// 
-    /// Color table offset register must be set first.
-    #[inline]
-    pub fn read_color_table(&self, offset: usize) -> u8 {
-        self.vdp_ram[self.vdp_color_table_offset as usize + offset]
+    /// Copy the palette color index, 0-15, of each pixel in the framebuffer
+    ///
+    /// Transparent pixels have the index of the backdrop color which shows through them, so index 0 only appears
+    /// where the backdrop color is 0. Changes made to the framebuffer directly, for example through
+    /// [`scanlines_mut`](TMS9918A::scanlines_mut), are not included, and frames drawn by
+    /// [`render_into`](TMS9918A::render_into) don't update the indices. The buffer is cleared first and reuses its allocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::{Color, TMS9918A};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_backdrop_color(Color::DarkRed);
+    /// vdp.render();
+    /// let mut indices = Vec::new();
+    /// vdp.frame_as_indexed(&mut indices);
+    /// assert_eq!(indices.len(), vdp.frame_width() * vdp.frame_height());
+    /// assert_eq!(indices[0], Color::DarkRed as u8);
+    /// ```
+    pub fn frame_as_indexed(&self, buffer: &mut Vec<u8>) {
+        buffer.clear();
+        buffer.extend_from_slice(&self.frame_indices);
     }
 
-    /// Set the pattern table address multiplier in register 4
-    /// 
-    /// Pattern table base address is equal to multiplier * 0x0800.
-    /// 
-    /// This function is equivalent to setting register 4 directly.
-    /// 
+    /// Compute a hash of the framebuffer, for comparing rendered frames against known values in tests
+    ///
+    /// The hash is the 64-bit FNV-1a hash of the frame width and height as little endian `u32`s, followed by each
+    /// pixel of the frame as a little endian `0x00RRGGBB` `u32`, row by row from the top left. It only depends on
+    /// the pixels and the frame size, so it stays the same across versions of this crate as long as the frame
+    /// looks the same.
+    ///
     /// # Examples
-    /// 
-    /// ```no_run
-    /// # use tms9918a_emu::{TMS9918A, VideoMode};
-    /// # fn main() {
-    /// # let mut vdp = TMS9918A::new();
-    /// // set pattern table base address to 0x0800
-    /// vdp.set_pattern_table_multiplier(1);
-    /// # }
+    ///
     /// ```
-    #[inline]
-    pub fn set_pattern_table_multiplier(&mut self, mut multiplier: u8) {
-        if multiplier > 7 {
-            multiplier = 7;
-        }
-        self.write_register(4, multiplier);
+    /// # use tms9918a_emu::{Color, TMS9918A, VramInit};
+    /// let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+    /// vdp.set_backdrop_color(Color::DarkBlue);
+    /// vdp.render();
+    /// assert_eq!(vdp.frame_hash(), 0x5D43_D79C_1106_0B2A);
+    ///
+    /// vdp.set_backdrop_color(Color::LightBlue);
+    /// vdp.render();
+    /// assert_ne!(vdp.frame_hash(), 0x5D43_D79C_1106_0B2A);
+    /// ```
+    pub fn frame_hash(&self) -> u64 {
+        hash_frame(&self.frame[..self.frame_width * self.frame_height], self.frame_width, self.frame_height)
     }
 
-    /// Fill pattern table contents from an array
-    /// 
-    /// Pattern table offset register must be set first.
-    /// 
+    /// Compute a hash of video memory, for checking that two VDPs have the same video memory contents
+    ///
+    /// The hash is the 64-bit FNV-1a hash of the 16K of video memory, and is also shown when the VDP is printed.
+    pub fn vram_hash(&self) -> u64 {
+        fnv1a(self.vdp_ram.iter().copied())
+    }
+
+    /// Show or hide the border around the active display area
+    ///
+    /// The border is filled with the backdrop color from register 7, the same as the border of a real TMS9918A.
+    /// When the border is visible, the framebuffer is 284x243 pixels with the active display area inside of it,
+    /// otherwise it only contains the active display area. The border is hidden by default.
+    ///
     /// # Examples
-    /// 
+    ///
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_border_visible(true);
+    /// assert_eq!((vdp.frame_width(), vdp.frame_height()), (284, 243));
+    ///
+    /// // dark blue border
+    /// vdp.write_register(7, 0x04);
+    /// vdp.update();
+    /// assert_eq!(vdp.frame[0], 0x5455ED);
+    /// assert_eq!(vdp.frame.len(), 284 * 243);
+    /// assert_eq!(vdp.frame[284 * 243 - 1], 0x5455ED);
+    /// ```
+    pub fn set_border_visible(&mut self, visible: bool) {
+        self.border_visible = visible;
+        self.resize_frame();
+    }
+
+    /// Check if the border around the active display area is visible
+    #[inline]
+    pub fn border_visible(&self) -> bool {
+        self.border_visible
+    }
+
+    /// Enable or disable padding Text mode to the 256 pixel width of the other video modes
+    ///
+    /// The real TMS9918A keeps the same raster in every mode, so the 240 pixels of Text mode are
+    /// centered with 8 pixels of the backdrop color on each side. Padding is enabled by default,
+    /// disabling it makes the framebuffer only 240 pixels wide in Text mode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// assert_eq!(vdp.frame_width(), 256);
+    /// vdp.set_text_padding(false);
+    /// assert_eq!(vdp.frame_width(), 240);
+    /// ```
+    pub fn set_text_padding(&mut self, enable: bool) {
+        self.text_padding = enable;
+        self.resize_frame();
+    }
+
+    /// Use one of the built-in color palettes
+    ///
+    /// The new palette is used starting with the next rendered line. The variant's [`VdpVariant::default_palette`] is
+    /// used by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::{BuiltinPalette, TMS9918A};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_builtin_palette(BuiltinPalette::Tms9928Component);
+    /// // dark blue backdrop with the display disabled
+    /// vdp.write_register(7, 0x04);
+    /// vdp.update();
+    /// assert_eq!(vdp.frame[0], 0x2B2DE3);
+    /// ```
+    pub fn set_builtin_palette(&mut self, palette: BuiltinPalette) {
+        self.palette = palette.colors();
+    }
+
+    /// Use a custom color palette
+    ///
+    /// The palette has 16 colors in the `0x00RRGGBB` format expected by minifb, in the order of the VDP color numbers.
+    /// The unused upper 8 bits of each color are cleared. Color 0 is transparent and is never displayed directly,
+    /// the backdrop color shows through instead.
+    ///
+    /// The new palette is used starting with the next rendered line, video memory is not changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// // green phosphor monochrome monitor
+    /// let mut palette = [0; 16];
+    /// for (i, color) in palette.iter_mut().enumerate() {
+    ///     *color = (i as u32 * 17) << 8;
+    /// }
+    /// vdp.set_palette(&palette);
+    /// assert_eq!(vdp.palette()[15], 0x00FF00);
+    ///
+    /// vdp.write_register(7, 0x0F);
+    /// vdp.update();
+    /// assert_eq!(vdp.frame[0], 0x00FF00);
+    /// ```
+    pub fn set_palette(&mut self, palette: &Palette) {
+        for (color, &new_color) in self.palette.iter_mut().zip(palette.iter()) {
+            *color = new_color & 0x00FFFFFF;
+        }
+    }
+
+    /// Get the color palette used for rendering
+    #[inline]
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    // size of the name table in the current video mode, there are always 24 rows
+    fn name_table_size(&self) -> usize {
+        self.name_table_columns() * 24
+    }
+
+    // number of tiles in each row of the name table in the current video mode
+    fn name_table_columns(&self) -> usize {
+        match self.vdp_mode {
+            VideoMode::Text => 40,
+            _ => 32
+        }
+    }
+
+    // size of the color table in the current video mode
+    fn color_table_size(&self) -> usize {
+        match self.vdp_mode {
+            VideoMode::Gfx2 => 0x1800,
+            _ => 32
+        }
+    }
+
+    // size of the pattern table in the current video mode
+    fn pattern_table_size(&self) -> usize {
+        match self.vdp_mode {
+            VideoMode::Gfx2 => 0x1800,
+            _ => 0x0800
+        }
+    }
+
+    // check that length bytes starting at offset are inside of a table and inside of video memory
+    fn check_table_range(base: u16, size: usize, offset: usize, length: usize) -> Result<(), TMS9918AError> {
+        let end = offset.saturating_add(length);
+        if end > size {
+            return Err(TMS9918AError::OutOfRange { end, size });
+        }
+        let last_address = base as usize + end.saturating_sub(1);
+        if last_address > 0x3FFF {
+            return Err(TMS9918AError::AddressOutOfRange(last_address));
+        }
+        Ok(())
+    }
+
+    // width of the active display area in the current video mode
+    fn mode_width(&self) -> usize {
+        match self.vdp_mode {
+            VideoMode::Text => 240,
+            _ => 256
+        }
+    }
+
+    // space around the active display area in the framebuffer: left, right, top, bottom
+    fn border(&self) -> (usize, usize, usize, usize) {
+        match (self.border_visible, &self.vdp_mode) {
+            (true, VideoMode::Text) => TEXT_BORDER,
+            (true, _) => BORDER,
+            (false, VideoMode::Text) if self.text_padding => TEXT_PADDING,
+            (false, _) => (0, 0, 0, 0)
+        }
+    }
+
+    // update the framebuffer size after the video mode or border visibility changed
+    fn resize_frame(&mut self) {
+        let (left, right, top, bottom) = self.border();
+        self.frame_width = left + self.mode_width() + right;
+        self.frame_height = top + 192 + bottom;
+        self.frame.resize(self.frame_width * self.frame_height, 0);
+        self.frame_indices.resize(self.frame_width * self.frame_height, 0);
+    }
+
+    // backdrop color from register 7
+    fn backdrop_color_index(&self) -> u8 {
+        self.vdp_register[7] & 0x0F
+    }
+
+    // draw a line of the active display area and the border next to it into a frame
+    // the frame starts at origin in the buffer and has stride pixels per row
+    // if indices is given, the color indices are also drawn into it at the same position
+    // the top and bottom border are drawn along with the first and last line
+    fn draw_frame_line(&mut self, line: usize, buffer: &mut [u32], mut indices: Option<&mut [u8]>, stride: usize, origin: usize) {
+        let (left, right, top, bottom) = self.border();
+        let width = self.frame_width;
+        let backdrop = self.backdrop_color_index();
+        let border_rows = match line {
+            0 => 0..top,
+            191 => top + 192..top + 192 + bottom,
+            _ => 0..0
+        };
+        for border_row in border_rows {
+            let offset = origin + border_row * stride;
+            buffer[offset..offset + width].fill(self.palette[backdrop as usize]);
+            if let Some(indices) = indices.as_deref_mut() {
+                indices[offset..offset + width].fill(backdrop);
+            }
+        }
+
+        // draw color indices first, then look them up in the palette
+        let mut row = core::mem::take(&mut self.line_indices);
+        row.resize(width, 0);
+        row[..left].fill(backdrop);
+        row[width - right..].fill(backdrop);
+        self.draw_scanline(line, &mut row[left..width - right]);
+
+        let offset = origin + (top + line) * stride;
+        for (pixel, index) in buffer[offset..offset + width].iter_mut().zip(row.iter()) {
+            *pixel = self.palette[*index as usize];
+        }
+        if let Some(indices) = indices {
+            indices[offset..offset + width].copy_from_slice(&row);
+        }
+        self.line_indices = row;
+    }
+
+    // draw one line of the active display area into a row of color indices
+    fn draw_scanline(&mut self, line: usize, row: &mut [u8]) {
+        let mut colors: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+        // color 0 is transparent, the backdrop color from register 7 shows through
+        colors[0] = self.backdrop_color_index();
+
+        // check blanking bit
+        if self.reg1_flags().contains(Reg1Flags::BLANK) {
+            // blanking bit is set, screen is enabled
+            match self.vdp_mode {
+                VideoMode::Gfx1 => {
+                    self.draw_tile_line(line, &colors);
+                    row.copy_from_slice(&self.tile_layer[line * 256..line * 256 + 256]);
+                    self.render_sprites(line, row, &colors);
+                }
+                VideoMode::Text => {
+                    self.draw_tile_line(line, &colors);
+                    row.copy_from_slice(&self.tile_layer[line * 256..line * 256 + 240]);
+                }
+                // modes which aren't implemented yet show the backdrop color, like a blanked screen
+                VideoMode::Gfx2 | VideoMode::Multicolor => row.fill(colors[0])
+            };
+        } else {
+            // blanking bit is clear, screen is disabled and shows the backdrop color
+            row.fill(colors[0]);
+        }
+    }
+
+    // draw the dirty tiles of a line of the active display area into the tile layer, in Graphics I or Text mode
+    fn draw_tile_line(&mut self, line: usize, colors: &[u8; 16]) {
+        self.update_dirty_tiles();
+        let (columns, tile_width) = if self.vdp_mode == VideoMode::Text { (40, 6) } else { (32, 8) };
+        let tile_y = line / 8;
+        let pattern_byte = line % 8;
+        for tile_x in 0..columns {
+            let tile = (tile_y * columns) + tile_x;
+            if self.dirty_tiles[tile] & (1 << pattern_byte) == 0 {
+                continue;
+            }
+            self.dirty_tiles[tile] &= !(1 << pattern_byte);
+            self.frame_tile_lines += 1;
+
+            let name_entry = self.vdp_ram[self.display_address(self.vdp_name_table_offset) + tile];
+            let color_byte = if self.vdp_mode == VideoMode::Text {
+                self.vdp_register[7]
+            } else {
+                self.vdp_ram[self.display_address(self.vdp_color_table_offset) + (name_entry / 8) as usize]
+            };
+            let foreground_color = colors[color_byte as usize >> 4 & 0x0F];
+            let background_color = colors[color_byte as usize & 0x0F];
+            let offset = self.display_address(self.vdp_pattern_table_offset) + (name_entry as usize * 8) + (pattern_byte);
+            let masks = &PATTERN_MASKS[self.vdp_ram[offset] as usize];
+            // Text mode tiles only use the leftmost 6 pixels of their patterns
+            let start = (line * 256) + (tile_x * tile_width);
+            for (pixel, &mask) in self.tile_layer[start..start + tile_width].iter_mut().zip(masks.iter()) {
+                *pixel = (foreground_color & mask) | (background_color & !mask);
+            }
+        }
+    }
+
+    // mark the tile rows using a written range of video memory as dirty
+    //
+    // the tables are compared at the addresses the display reads them from, so in 4K mode a write below 0x1000 marks
+    // the tiles of tables set above it
+    fn mark_vram_written(&mut self, address: usize, length: usize) {
+        let end = address + length;
+        // a name table entry changes the whole tile, the Text mode name table has 960 entries
+        let name_table = self.display_address(self.vdp_name_table_offset);
+        for entry in address.max(name_table)..end.min(name_table + 960) {
+            self.dirty_tiles[entry - name_table] = 0xFF;
+        }
+        // the tiles using a pattern or color table entry are found later, when a line is drawn
+        let pattern_table = self.display_address(self.vdp_pattern_table_offset);
+        for offset in address.max(pattern_table)..end.min(pattern_table + 256 * 8) {
+            let offset = offset - pattern_table;
+            self.dirty_patterns[offset / 8] |= 1 << (offset % 8);
+            self.tables_dirty = true;
+        }
+        let color_table = self.display_address(self.vdp_color_table_offset);
+        for entry in address.max(color_table)..end.min(color_table + 32) {
+            self.dirty_colors |= 1 << (entry - color_table);
+            self.tables_dirty = true;
+        }
+    }
+
+    // mark the tile rows using written patterns and color table entries as dirty
+    // this looks up every name table entry, so it only runs once before drawing, not for every write
+    fn update_dirty_tiles(&mut self) {
+        if !self.tables_dirty {
+            return;
+        }
+        let name_table = self.display_address(self.vdp_name_table_offset);
+        for (entry, dirty) in self.dirty_tiles.iter_mut().enumerate() {
+            let name_entry = self.vdp_ram[name_table + entry] as usize;
+            *dirty |= self.dirty_patterns[name_entry];
+            if self.dirty_colors & (1 << (name_entry / 8)) != 0 {
+                *dirty = 0xFF;
+            }
+        }
+        self.dirty_patterns.fill(0);
+        self.dirty_colors = 0;
+        self.tables_dirty = false;
+    }
+
+    /// Draw every tile again when the next frame is rendered
+    ///
+    /// Rendering only draws the tiles again whose name table entry, pattern or colors were written since the last
+    /// frame, or all tiles after a register changed. Writes through the functions of the VDP and through
+    /// [`vram_mut`](TMS9918A::vram_mut) are tracked, so this is only needed to force a full redraw, for example when
+    /// measuring the rendering time of a whole screen.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.enable_video(true);
+    /// vdp.render();
+    /// vdp.render();
+    /// assert_eq!(vdp.tile_lines_drawn(), 0);
+    ///
+    /// vdp.invalidate_tiles();
+    /// vdp.render();
+    /// assert_eq!(vdp.tile_lines_drawn(), 768 * 8);
+    /// ```
+    pub fn invalidate_tiles(&mut self) {
+        self.dirty_tiles.fill(0xFF);
+    }
+
+    /// Get the number of tile lines drawn in the last completed frame
+    ///
+    /// Each line of 8 pixels (6 in Text mode) of a tile which was drawn again counts once, so drawing the whole
+    /// screen in Graphics I mode is 768 tiles * 8 lines. A static screen doesn't draw any tiles, only sprites and the
+    /// finished frame are drawn.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VideoMode, VramInit};
+    /// let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// vdp.set_pattern_table_multiplier(1);
+    /// vdp.write_register(7, 0xF4);
+    /// vdp.enable_video(true);
+    /// vdp.print_text(0, 0, "Hello, world!");
+    /// vdp.render();
+    /// assert_eq!(vdp.tile_lines_drawn(), 960 * 8);
+    ///
+    /// // nothing changed
+    /// vdp.render();
+    /// assert_eq!(vdp.tile_lines_drawn(), 0);
+    ///
+    /// // only the changed tile is drawn again
+    /// vdp.print_text(0, 1, "!");
+    /// vdp.render();
+    /// assert_eq!(vdp.tile_lines_drawn(), 8);
+    ///
+    /// // one row of a pattern changes every tile using it
+    /// let offset = 0x0800 + b'l' as usize * 8 + 3;
+    /// vdp.write_ram(offset, 0x55);
+    /// vdp.render();
+    /// assert_eq!(vdp.tile_lines_drawn(), 3);
+    /// ```
+    #[inline]
+    pub fn tile_lines_drawn(&self) -> usize {
+        self.last_frame_tile_lines
+    }
+
+    // finish the current frame after the last line of the active display area
+    fn end_frame(&mut self) {
+        if self.sprite_flicker {
+            self.sprite_flicker_rotation = self.sprite_flicker_rotation.wrapping_add(1);
+        }
+
+        // the frame is complete, set the frame flag and enter vertical blanking
+        self.vdp_status |= 1 << 7;
+        self.vdp_scanline = 192;
+        self.last_frame_tile_lines = core::mem::take(&mut self.frame_tile_lines);
+
+        if let Some(sink) = &mut self.frame_sink {
+            sink(&self.frame, self.frame_width, self.frame_height);
+        }
+        #[cfg(feature = "gif")]
+        self.record_frame();
+        #[cfg(feature = "net")]
+        self.serve_frame();
+        #[cfg(feature = "std")]
+        self.trace_frame();
+
+        // take the callback out while it runs so it can borrow the VDP mutably
+        if let Some(mut callback) = self.vblank_callback.take() {
+            callback(self);
+            // keep the callback unless it was replaced while running
+            if self.vblank_callback.is_none() {
+                self.vblank_callback = Some(callback);
+            }
+        }
+    }
+
+    /// Get the scanline currently being displayed
+    ///
+    /// Scanlines 0-191 are the active display area, and the remaining scanlines (192-261 for NTSC, 192-312 for PAL)
+    /// are the bottom border, vertical blanking, and top border.
+    ///
+    /// [`update`](TMS9918A::update) renders the whole frame at once, and leaves the current scanline at 192,
+    /// the first line of the vertical blanking period, when the frame flag is set.
+    /// [`render_scanline`](TMS9918A::render_scanline) sets the current scanline to the line being rendered,
+    /// and [`tick`](TMS9918A::tick) advances the current scanline as CPU cycles elapse.
+    #[inline]
+    pub fn current_scanline(&self) -> u16 {
+        self.vdp_scanline
+    }
+
+    /// Check if the VDP is in the vertical blanking period
+    ///
+    /// This is true from the end of the active display area, when the frame flag is set,
+    /// until the start of the next frame.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.update();
+    /// assert_eq!(vdp.current_scanline(), 192);
+    /// assert!(vdp.is_vblank());
+    /// ```
+    #[inline]
+    pub fn is_vblank(&self) -> bool {
+        self.vdp_scanline >= 192
+    }
+
+    /// Set a callback to run at the end of each frame
+    ///
+    /// The callback runs once per [`update`](TMS9918A::update), right after the frame is rendered and the frame flag is set.
+    /// It receives the VDP itself, so it can run a CPU for a frame's worth of cycles and access the VDP from there.
+    ///
+    /// Setting a new callback replaces the previous one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.on_vblank(|vdp| {
+    ///     // acknowledge the interrupt and change the backdrop color
+    ///     vdp.read_status_port();
+    ///     vdp.write_register(7, 0x04);
+    /// });
+    /// vdp.update();
+    /// assert_eq!(vdp.read_register(7), 0x04);
+    /// assert_eq!(vdp.read_status_port() & 0x80, 0);
+    /// ```
+    pub fn on_vblank<F: FnMut(&mut TMS9918A) + Send + Sync + 'static>(&mut self, callback: F) {
+        self.vblank_callback = Some(Box::new(callback));
+    }
+
+    /// Remove the callback set by [`on_vblank`](TMS9918A::on_vblank)
+    #[inline]
+    pub fn clear_vblank_callback(&mut self) {
+        self.vblank_callback = None;
+    }
+
+    /// Set a callback which receives each completed frame, for example to feed an external video encoder
+    ///
+    /// The callback is called with the framebuffer, its width and its height whenever a frame is completed by
+    /// [`update`](TMS9918A::update), [`render`](TMS9918A::render) or [`tick`](TMS9918A::tick), before the
+    /// [`on_vblank`](TMS9918A::on_vblank) callback runs and before the frame is presented in a window.
+    /// It works the same without a window.
+    ///
+    /// The framebuffer slice is only valid during the call, copy it to keep the frame.
+    /// Setting a new callback replaces the previous one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let frames = Arc::new(Mutex::new(Vec::new()));
+    /// let sink_frames = frames.clone();
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_frame_sink(move |frame, width, height| {
+    ///     assert_eq!(frame.len(), width * height);
+    ///     sink_frames.lock().unwrap().push(frame.to_vec());
+    /// });
+    /// vdp.update();
+    /// vdp.update();
+    /// vdp.clear_frame_sink();
+    /// vdp.update();
+    /// assert_eq!(frames.lock().unwrap().len(), 2);
+    /// ```
+    pub fn set_frame_sink<F: FnMut(&[u32], usize, usize) + Send + Sync + 'static>(&mut self, sink: F) {
+        self.frame_sink = Some(Box::new(sink));
+    }
+
+    /// Remove the callback set by [`set_frame_sink`](TMS9918A::set_frame_sink)
+    #[inline]
+    pub fn clear_frame_sink(&mut self) {
+        self.frame_sink = None;
+    }
+
+    // get the pixels of a sprite on a line as (x position, pixel bits, color)
+    //
+    // bit 31 of the pixel bits is the leftmost pixel, magnification is already applied
+    // returns None if the sprite is not on this line
+    fn sprite_line(&self, sprite: usize, line: usize) -> Option<(isize, u32, usize)> {
+        // 16x16 sprites are each made of four 8x8 patterns
+        let size_16 = self.reg1_flags().contains(Reg1Flags::SIZE);
+        // magnified sprites have each sprite pixel doubled in both dimensions
+        let magnify = if self.reg1_flags().contains(Reg1Flags::MAG) { 1 } else { 0 };
+        let pattern_size = if size_16 { 16 } else { 8 };
+        let sprite_size = pattern_size << magnify;
+
+        let attribute_offset = self.display_address(self.vdp_sprite_attribute_offset) + (sprite * 4);
+        // sprites are displayed one line below their vertical position, so 0xFF is the top line
+        // positions wrap around, which allows sprites to slide in from the top of the screen
+        let sprite_row = (line as u8).wrapping_sub(self.vdp_ram[attribute_offset].wrapping_add(1)) as usize;
+        if sprite_row >= sprite_size {
+            return None;
+        }
+        let mut sprite_x = self.vdp_ram[attribute_offset + 1] as isize;
+        let mut sprite_name = self.vdp_ram[attribute_offset + 2] as usize;
+        let sprite_color = self.vdp_ram[attribute_offset + 3] as usize & 0x0F;
+        // early clock bit, shift the sprite 32 pixels to the left
+        if self.vdp_ram[attribute_offset + 3] & (1 << 7) != 0 {
+            sprite_x -= 32;
+        }
+        // 16x16 sprites ignore the low two bits of the pattern name
+        if size_16 {
+            sprite_name &= 0xFC;
+        }
+        // 16x16 patterns are stored as quadrants: top left, bottom left, top right, bottom right
+        // the left half is 16 consecutive bytes, and the right half is the next 16 bytes
+        let pattern_row = sprite_row >> magnify;
+        let offset = self.display_address(self.vdp_sprite_pattern_offset) + (sprite_name * 8) + pattern_row;
+        let mut pattern = (self.vdp_ram[offset] as u16) << 8;
+        if size_16 {
+            pattern |= self.vdp_ram[offset + 16] as u16;
+        }
+        let mut pixels = 0;
+        for sprite_pixel in 0..sprite_size {
+            if pattern & (0x8000 >> (sprite_pixel >> magnify)) != 0 {
+                pixels |= 0x80000000 >> sprite_pixel;
+            }
+        }
+        Some((sprite_x, pixels, sprite_color))
+    }
+
+    // composite sprites over one line of the tile layer, sprite 0 has the highest priority
+    //
+    // sprites are not displayed in text mode
+    fn render_sprites(&mut self, line: usize, row: &mut [u8], colors: &[u8; 16]) {
+        // sprites on this line, in priority order
+        let mut line_sprites = [0; 32];
+        let mut sprites_on_line = 0;
+        for sprite in 0..32 {
+            // a vertical position of 0xD0 stops processing of this sprite and all following sprites
+            let attribute_offset = self.display_address(self.vdp_sprite_attribute_offset) + (sprite * 4);
+            if self.vdp_ram[attribute_offset] == 0xD0 {
+                break;
+            }
+            if self.sprite_line(sprite, line).is_none() {
+                continue;
+            }
+            // more than four sprites on this line, latch the fifth sprite flag and number
+            // the first line with a fifth sprite wins until the status register is read
+            if sprites_on_line == 4 && self.vdp_status & (1 << 6) == 0 {
+                self.vdp_status = (self.vdp_status & 0b11100000) | (1 << 6) | sprite as u8;
+            }
+            line_sprites[sprites_on_line] = sprite;
+            sprites_on_line += 1;
+        }
+
+        // the real hardware stops processing sprites on this line after the fourth one
+        let evaluated = sprites_on_line.min(4);
+        let processed = if self.sprite_limit { evaluated } else { sprites_on_line };
+
+        // two overlapping sprites among the four the hardware evaluates set the coincidence flag,
+        // even when more are displayed, so the status register doesn't depend on the sprite limit setting
+        // pixels on this line occupied by any sprite, including transparent ones
+        let mut occupied = [false; 256];
+        for &sprite in &line_sprites[..evaluated] {
+            let (sprite_x, pixels, _) = self.sprite_line(sprite, line).unwrap();
+            for frame_x in Self::sprite_pixels(sprite_x, pixels) {
+                if occupied[frame_x] {
+                    self.vdp_status |= 1 << 5;
+                }
+                occupied[frame_x] = true;
+            }
+        }
+
+        // with flicker enabled, rotate which sprites are displayed on this line every frame
+        let rotation = if self.sprite_flicker && self.sprite_limit && sprites_on_line > 4 {
+            self.sprite_flicker_rotation % sprites_on_line
+        } else {
+            0
+        };
+
+        // pixels on this line already drawn by a higher priority sprite
+        let mut covered = [false; 256];
+        for (i, &sprite) in line_sprites[..sprites_on_line].iter().enumerate() {
+            if (i + sprites_on_line - rotation) % sprites_on_line >= processed {
+                continue;
+            }
+            let (sprite_x, pixels, sprite_color) = self.sprite_line(sprite, line).unwrap();
+            // color 0 is transparent, lower priority sprites show through
+            if sprite_color == 0 {
+                continue;
+            }
+            for frame_x in Self::sprite_pixels(sprite_x, pixels) {
+                if !covered[frame_x] {
+                    covered[frame_x] = true;
+                    row[frame_x] = colors[sprite_color];
+                }
+            }
+        }
+    }
+
+    // iterate over the on-screen x positions of the set pixels of a sprite line
+    fn sprite_pixels(sprite_x: isize, pixels: u32) -> impl Iterator<Item = usize> {
+        (0..32)
+            .filter(move |sprite_pixel| pixels & (0x80000000 >> sprite_pixel) != 0)
+            .map(move |sprite_pixel| sprite_x + sprite_pixel)
+            .filter(|&frame_x| (0..256).contains(&frame_x))
+            .map(|frame_x| frame_x as usize)
+    }
+
+    /// Enable or disable the four sprites per line limit
+    ///
+    /// The real TMS9918A only displays the four highest priority sprites on each line,
+    /// any additional sprites on that line are not displayed.
+    ///
+    /// The limit is enabled by default. Disabling it displays all sprites on every line, but the status register
+    /// is the same as with the limit: the fifth sprite flag is still set, and the coincidence flag is only set by
+    /// overlapping sprites among the first four on a line.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tms9918a_emu::TMS9918A;
+    /// # fn main() {
+    /// # let mut vdp = TMS9918A::new();
+    /// // display every sprite, even if more than four are on the same line
+    /// vdp.set_sprite_limit(false);
+    /// # }
+    /// ```
+    #[inline]
+    pub fn set_sprite_limit(&mut self, enable: bool) {
+        self.sprite_limit = enable;
+    }
+
+    /// Enable or disable sprite flicker
+    ///
+    /// When enabled, the sprites displayed on lines with more than four sprites are rotated every frame,
+    /// so all of them remain visible but flicker, similar to how many games handle the four sprites per line limit.
+    ///
+    /// This only changes which sprites are displayed, the status register behaves exactly like the real hardware.
+    /// Flicker has no effect while the four sprites per line limit is disabled.
+    ///
+    /// Sprite flicker is disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tms9918a_emu::TMS9918A;
+    /// # fn main() {
+    /// # let mut vdp = TMS9918A::new();
+    /// vdp.set_sprite_flicker(true);
+    /// # }
+    /// ```
+    #[inline]
+    pub fn set_sprite_flicker(&mut self, enable: bool) {
+        self.sprite_flicker = enable;
+    }
+
+    /// Enable or disable the video display by setting or clearing the blanking bit in register 1
+    /// 
+    /// The video display is disabled by default due to registers 0 and 1 being cleared on reset.
+    /// While the display is disabled, the whole screen shows the backdrop color from register 7,
+    /// which is black after a reset, similar to the behavior of a real TMS9918A.
+    ///
+    /// Only the blanking bit is changed, the 4K/16K selection bit in register 1 is left as it is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.write_register(7, 0x04);
+    /// vdp.enable_video(false);
+    /// vdp.update();
+    /// assert!(vdp.frame.iter().all(|&p| p == 0x5455ED));
+    /// ```
+    #[inline]
+    pub fn enable_video(&mut self, enable: bool) {
+        self.modify_register(Register::Control1.into(), |r1| {
+            if enable {
+                r1 | Reg1Flags::BLANK.bits()
+            } else {
+                r1 & !Reg1Flags::BLANK.bits()
+            }
+        });
+    }
+
+    /// Set the backdrop color in the low 4 bits of register 7
+    ///
+    /// The backdrop color is shown behind transparent pixels, in the border, and while the display is disabled.
+    /// In Text mode, it is also the text background color. The text foreground color is not changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::{Color, TMS9918A};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_text_color(Color::LightRed, Color::Black);
+    /// vdp.set_backdrop_color(Color::DarkBlue);
+    /// assert_eq!(vdp.read_register(7), 0x94);
+    /// assert_eq!(vdp.backdrop_color(), Color::DarkBlue);
+    /// assert_eq!(vdp.text_color(), (Color::LightRed, Color::DarkBlue));
+    /// ```
+    pub fn set_backdrop_color(&mut self, color: Color) {
+        let data = (self.vdp_register[7] & 0xF0) | u8::from(color);
+        self.write_register_typed(Register::TextColor, data);
+    }
+
+    /// Get the backdrop color from the low 4 bits of register 7
+    pub fn backdrop_color(&self) -> Color {
+        Color::from(self.vdp_register[7])
+    }
+
+    /// Set the Text mode foreground and background colors in register 7
+    ///
+    /// The background color is also the backdrop color used in every video mode.
+    pub fn set_text_color(&mut self, foreground: Color, background: Color) {
+        let data = (u8::from(foreground) << 4) | u8::from(background);
+        self.write_register_typed(Register::TextColor, data);
+    }
+
+    /// Get the Text mode foreground and background colors from register 7
+    pub fn text_color(&self) -> (Color, Color) {
+        (Color::from(self.vdp_register[7] >> 4), Color::from(self.vdp_register[7]))
+    }
+
+    /// Reset VDP to initial state without modifying video memory
+    /// 
+    /// On a real VDP the RESET input only clears registers 0 and 1, which selects Graphics I mode and blanks the
+    /// display, and leaves the other registers with whatever was last written. This clears all eight registers,
+    /// so the VDP ends up in the same state as a newly created one apart from video memory. The address pointer,
+    /// the control port latch, the read-ahead buffer and the status register are cleared, the frame timing
+    /// restarts at the first scanline, and the framebuffer is cleared on the next update.
+    /// 
+    /// Settings which aren't part of the VDP, such as the palette, the charmap and the text console, are not changed.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// vdp.print_text(0, 0, "Hello");
+    /// vdp.warm_reset();
+    /// assert_eq!(vdp.registers(), [0; 8]);
+    /// assert_eq!(vdp.read_name_table(0), b'H');
+    /// ```
+    pub fn warm_reset(&mut self) {
+        for register in 0..8 {
+            self.write_register(register, 0);
+        }
+        // writing the registers through write_register recalculates the table offsets, so they are all 0 now
+        self.frame_clear = true;
+        self.vdp_status = 0;
+        self.vdp_scanline = 0;
+        self.vdp_line_clock = 0;
+        self.vdp_temp_data = 0;
+        self.vdp_addr_pointer = 0;
+        self.vdp_read_ahead = 0;
+        self.vdp_first_byte_saved_flag = false;
+    }
+
+    /// Reset VDP to initial state and randomize video memory contents
+    pub fn cold_reset(&mut self) {
+        self.cold_reset_with(VramInit::Random { seed: random_seed() });
+    }
+
+    /// Reset VDP to initial state and set video memory contents
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VramInit};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.cold_reset_with(VramInit::Zero);
+    /// assert!(vdp.vram().iter().all(|&byte| byte == 0));
+    /// ```
+    pub fn cold_reset_with(&mut self, init: VramInit) {
+        self.warm_reset();
+        self.vdp_ram = init.memory(self.vdp_ram.len());
+        self.invalidate_tiles();
+    }
+
+    /// Set TMS9918A video mode
+    /// 
+    /// Valid video modes are Text, Graphics I, Graphics II, and Multicolor.
+    /// 
+    /// Graphics II and Multicolor modes are not currently implemented, they show the backdrop color.
+    /// Sprites are displayed in Graphics I mode and are never displayed in Text mode.
+    /// 
+    /// Undocumented modes (combining video modes by setting more than one mode bit) are not supported. When registers 0
+    /// and 1 are written with more than one mode bit set, M1 selects Text mode and otherwise M2 selects Multicolor mode.
+    /// 
+    /// The original TMS9918 doesn't support Graphics II mode, selecting it results in Graphics I mode.
+    /// 
+    /// # Examples
+    /// 
+    /// ```no_run
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// # fn main() {
+    /// # let mut vdp = TMS9918A::new();
+    /// // use text mode
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// # }
+    /// ```
+    pub fn set_video_mode(&mut self, mode: VideoMode) {
+        let (m3, m1_m2) = match mode {
+            VideoMode::Gfx1 => (Reg0Flags::empty(), Reg1Flags::empty()),
+            VideoMode::Gfx2 => (Reg0Flags::M3, Reg1Flags::empty()),
+            VideoMode::Multicolor => (Reg0Flags::empty(), Reg1Flags::M2),
+            VideoMode::Text => (Reg0Flags::empty(), Reg1Flags::M1)
+        };
+        self.modify_register(Register::Control0.into(), |r0| (r0 & !Reg0Flags::M3.bits()) | m3.bits());
+        self.modify_register(Register::Control1.into(), |r1| (r1 & !(Reg1Flags::M1 | Reg1Flags::M2).bits()) | m1_m2.bits());
+    }
+
+    /// Write register value
+    /// 
+    /// Register numbers are masked to 3 bits, the same as register writes through the control port.
+    /// Any value can be written to any register, unused bits of the table address registers are ignored.
+    pub fn write_register(&mut self, register: u8, data: u8) {
+        // write register value
+        let register = register & 0x07;
+        // the tables, the mode and the colors of every tile can change
+        if self.vdp_register[register as usize] != data {
+            self.invalidate_tiles();
+        }
+        self.vdp_register[register as usize] = data;
+
+        // write video mode
+        if register == 0 || register == 1 {
+            // M3 enables the bitmap graphics mode
+            // the original TMS9918 doesn't have a bitmap graphics mode and ignores this bit
+            let m3 = self.reg0_flags().contains(Reg0Flags::M3) && self.vdp_variant.has_gfx2();
+            // M1 enables text mode
+            let m1 = self.reg1_flags().contains(Reg1Flags::M1);
+            // M2 enables multicolor mode
+            let m2 = self.reg1_flags().contains(Reg1Flags::M2);
+
+            // the undocumented mode combinations are mixes of the modes, M1 wins over the others and M2 over M3
+            let mode = match (m1, m2, m3) {
+                (true, _, _) => VideoMode::Text,
+                (false, true, _) => VideoMode::Multicolor,
+                (false, false, true) => VideoMode::Gfx2,
+                (false, false, false) => VideoMode::Gfx1
+            };
+            // writing other bits of registers 0 and 1 doesn't change the mode, keep the framebuffer
+            if mode != self.vdp_mode {
+                self.vdp_mode = mode;
+                // clear framebuffer on next update
+                self.frame_clear = true;
+                self.resize_frame();
+            }
+
+            //println!("set graphics mode: {:?}", self.vdp_mode);
+        }
+
+        // write offset values
+        // only the address bits which fit in 16K are used, the same as the real hardware
+        self.vdp_name_table_offset = (self.vdp_register[2] & 0x0F) as u16 * 0x0400;
+        if self.vdp_mode == VideoMode::Gfx2 {
+            // the 6K color and pattern tables are at either 0x0000 or 0x2000, selected by the highest bit of the register
+            self.vdp_color_table_offset = (self.vdp_register[3] & 0x80) as u16 * 0x0040;
+            self.vdp_pattern_table_offset = (self.vdp_register[4] & 0x04) as u16 * 0x0800;
+        } else {
+            self.vdp_color_table_offset = self.vdp_register[3] as u16 * 0x0040;
+            self.vdp_pattern_table_offset = (self.vdp_register[4] & 0x07) as u16 * 0x0800;
+        }
+        self.vdp_sprite_attribute_offset = (self.vdp_register[5] & 0x7F) as u16 * 0x0080;
+        self.vdp_sprite_pattern_offset = (self.vdp_register[6] & 0x07) as u16 * 0x0800;
+    }
+
+    /// Read register value
+    /// 
+    /// Reading from VDP registers is not supported by the real hardware, the registers are write-only.
+    /// This is an emulator convenience for debuggers, save states, and front-ends, and it has no side effects.
+    /// 
+    /// Register numbers are masked to 3 bits, the same as [`write_register`](TMS9918A::write_register).
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.write_register(7, 0x91);
+    /// assert_eq!(vdp.read_register(7), 0x91);
+    /// assert_eq!(vdp.read_register(15), 0x91);
+    /// ```
+    pub fn read_register(&self, register: u8) -> u8 {
+        self.vdp_register[(register & 0x07) as usize]
+    }
+
+    /// Change some bits of a register value while keeping the rest
+    /// 
+    /// The function receives the current register value and returns the new value,
+    /// which is written with [`write_register`](TMS9918A::write_register) so the table addresses and video mode are updated.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{Reg1Flags, TMS9918A};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.enable_video(true);
+    /// // enable interrupts and 16x16 sprites
+    /// vdp.modify_register(1, |r1| r1 | (Reg1Flags::IE | Reg1Flags::SIZE).bits());
+    /// assert_eq!(vdp.reg1_flags(), Reg1Flags::BLANK | Reg1Flags::IE | Reg1Flags::SIZE);
+    /// ```
+    pub fn modify_register<F: FnOnce(u8) -> u8>(&mut self, register: u8, f: F) {
+        let data = f(self.read_register(register));
+        self.write_register(register, data);
+    }
+
+    /// Write register value using a named register
+    /// 
+    /// This is the same as [`write_register`](TMS9918A::write_register) with the register number.
+    #[inline]
+    pub fn write_register_typed(&mut self, register: Register, data: u8) {
+        self.write_register(register.into(), data);
+    }
+
+    /// Read register value using a named register
+    /// 
+    /// This is the same as [`read_register`](TMS9918A::read_register) with the register number.
+    #[inline]
+    pub fn read_register_typed(&self, register: Register) -> u8 {
+        self.read_register(register.into())
+    }
+
+    /// Get the bits of register 0
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{Reg0Flags, Reg1Flags, TMS9918A, VideoMode};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_video_mode(VideoMode::Gfx2);
+    /// assert_eq!(vdp.reg0_flags(), Reg0Flags::M3);
+    /// assert_eq!(vdp.read_register(0), 0x02);
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// vdp.enable_video(true);
+    /// assert_eq!(vdp.reg1_flags(), Reg1Flags::BLANK | Reg1Flags::M1);
+    /// ```
+    #[inline]
+    pub fn reg0_flags(&self) -> Reg0Flags {
+        Reg0Flags::from(self.vdp_register[0])
+    }
+
+    /// Get the bits of register 1
+    #[inline]
+    pub fn reg1_flags(&self) -> Reg1Flags {
+        Reg1Flags::from(self.vdp_register[1])
+    }
+
+    /// Read all eight register values
+    /// 
+    /// Like [`read_register`](TMS9918A::read_register), this is an emulator convenience.
+    pub fn registers(&self) -> [u8; 8] {
+        let mut registers = [0; 8];
+        registers.copy_from_slice(&self.vdp_register);
+        registers
+    }
+
+    /// Take a snapshot of the register values which can be printed for debugging
+    /// 
+    /// # Examples
+    /// 
+    /// ```no_run
+    /// # use tms9918a_emu::TMS9918A;
+    /// # fn main() {
+    /// # let vdp = TMS9918A::new();
+    /// // find out why the screen is black
+    /// println!("{}", vdp.register_dump());
+    /// # }
+    /// ```
+    pub fn register_dump(&self) -> RegisterDump {
+        RegisterDump { registers: self.registers() }
+    }
+
+    /// Write memory contents
+    /// 
+    /// Addresses are masked to 14 bits, so addresses above 0x3FFF wrap around.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.write_ram(0x4000, 0xAA);
+    /// assert_eq!(vdp.read_ram(0x0000), 0xAA);
+    /// ```
+    #[inline]
+    pub fn write_ram(&mut self, address: usize, data: u8) {
+        self.vdp_ram[address & 0x3FFF] = data;
+        self.mark_vram_written(address & 0x3FFF, 1);
+    }
+
+    /// Read memory contents
+    /// 
+    /// Addresses are masked to 14 bits, so addresses above 0x3FFF wrap around.
+    #[inline]
+    pub fn read_ram(&mut self, address: usize) -> u8 {
+        self.vdp_ram[address & 0x3FFF]
+    }
+
+    /// Get the whole 16KB video memory
+    #[inline]
+    pub fn vram(&self) -> &[u8] {
+        &self.vdp_ram
+    }
+
+    /// Get mutable access to the whole 16KB video memory, for example to patch it from a debugger
+    /// 
+    /// Every tile is drawn again on the next frame after the returned [`VramMut`] is dropped. Writes of a few bytes
+    /// are cheaper with [`write_ram`](TMS9918A::write_ram), which only draws the tiles using them again.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VramInit};
+    /// let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+    /// // 16K, the color table is above the first 4K
+    /// vdp.write_register(1, 0x80);
+    /// vdp.set_pattern_table_multiplier(1);
+    /// vdp.set_color_table_multiplier(0x80);
+    /// // white on dark blue, every tile uses pattern 0
+    /// vdp.write_ram(0x2000, 0xF4);
+    /// vdp.enable_video(true);
+    /// vdp.render();
+    /// assert_eq!(vdp.frame[0], 0x5455ED);
+    /// 
+    /// vdp.vram_mut()[0x0800..0x0808].fill(0xFF);
+    /// vdp.render();
+    /// assert_eq!(vdp.frame[0], 0xFFFFFF);
+    /// ```
+    pub fn vram_mut(&mut self) -> VramMut<'_> {
+        VramMut { vdp: self }
+    }
+
+    /// Replace the whole video memory with a 16KB dump, for example one saved by another emulator
+    /// 
+    /// Registers, the address pointer and the read-ahead buffer are not changed.
+    /// Returns an error if the dump is not exactly 16384 bytes, in which case the video memory is not changed.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, TMS9918AError};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.write_register(7, 0xF4);
+    /// let dump = vec![0x55; 0x4000];
+    /// vdp.load_vram_bytes(&dump).unwrap();
+    /// assert_eq!(vdp.vram(), &dump[..]);
+    /// assert_eq!(vdp.read_register(7), 0xF4);
+    /// 
+    /// assert_eq!(vdp.load_vram_bytes(&dump[1..]), Err(TMS9918AError::InvalidVramSize(0x3FFF)));
+    /// ```
+    pub fn load_vram_bytes(&mut self, data: &[u8]) -> Result<(), TMS9918AError> {
+        if data.len() != self.vdp_ram.len() {
+            return Err(TMS9918AError::InvalidVramSize(data.len()));
+        }
+        self.vdp_ram.copy_from_slice(data);
+        self.invalidate_tiles();
+        Ok(())
+    }
+
+    /// Set the name table address multiplier in register 2
+    /// 
+    /// Name table base address is equal to multiplier * 0x0400.
+    /// 
+    /// This function is equivalent to setting register 2 directly.
+    /// 
+    /// # Examples
+    /// 
+    /// ```no_run
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// # fn main() {
+    /// # let mut vdp = TMS9918A::new();
+    /// // set name table base address to 0x0400
+    /// vdp.set_name_table_multiplier(1);
+    /// # }
+    /// ```
+    #[inline]
+    pub fn set_name_table_multiplier(&mut self, mut multiplier: u8) {
+        if multiplier > 15 {
+            multiplier = 15;
+        }
+        self.write_register_typed(Register::NameTableBase, multiplier);
+    }
+
+    /// Fill name table contents from an array
+    /// 
+    /// Name table offset register must be set first.
+    /// 
+    /// Returns an error if the filled range is past the end of the name table for the current video mode, or past the end of video memory.
+    /// Nothing is written in that case.
+    /// 
+    /// The first `length` bytes of the array are copied to the name table starting at entry `offset`.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// # let mut vdp = TMS9918A::new();
+    /// // fill the first 5 name table entries
+    /// let name_table: [u8; 5] = [1, 2, 3, 4, 5];
+    /// vdp.fill_name_table(&name_table, 0, name_table.len()).unwrap();
+    /// 
+    /// // fill the first 3 entries of the second row
+    /// vdp.fill_name_table(&name_table, 32, 3).unwrap();
+    /// assert_eq!(vdp.read_ram(32), 1);
+    /// assert_eq!(vdp.read_ram(34), 3);
+    /// assert_eq!(vdp.read_ram(3), 4);
+    /// ```
+    pub fn fill_name_table(&mut self, array: &[u8], offset: usize, length: usize) -> Result<(), TMS9918AError> {
+        let length = length.min(array.len());
+        Self::check_table_range(self.vdp_name_table_offset, self.name_table_size(), offset, length)?;
+        self.fill_name_table_raw(array, offset, length);
+        Ok(())
+    }
+
+    /// Fill name table contents from an array without checking the size of the name table
+    /// 
+    /// This can write past the end of the name table into the video memory after it, and addresses wrap around at 16K.
+    pub fn fill_name_table_raw(&mut self, array: &[u8], offset: usize, length: usize) {
+        for (i, data) in array.iter().take(length).enumerate() {
+            self.write_name_table_raw(offset.wrapping_add(i), *data);
+        }
+    }
+
+    /// Clear the screen by zeroing the name table
+    /// 
+    /// Name table offset register must be set first.
+    #[inline]
+    pub fn clear_name_table(&mut self) {
+        for i in 0..self.name_table_size() {
+            self.write_name_table_raw(i, 0);
+        }
+    }
+
+    /// Write name table contents
+    /// 
+    /// Name table offset register must be set first.
+    /// 
+    /// Returns an error if the offset is past the end of the name table, or past the end of video memory.
+    #[inline]
+    pub fn write_name_table(&mut self, offset: usize, data: u8) -> Result<(), TMS9918AError> {
+        Self::check_table_range(self.vdp_name_table_offset, self.name_table_size(), offset, 1)?;
+        self.write_name_table_raw(offset, data);
+        Ok(())
+    }
+
+    /// Write name table contents without checking the size of the name table
+    /// 
+    /// This can write past the end of the name table into the video memory after it, and addresses wrap around at 16K.
+    #[inline]
+    pub fn write_name_table_raw(&mut self, offset: usize, data: u8) {
+        self.write_ram((self.vdp_name_table_offset as usize).wrapping_add(offset), data);
+    }
+
+    /// Read name table contents
+    /// 
+    /// Name table offset register must be set first.
+    #[inline]
+    pub fn read_name_table(&self, offset: usize) -> u8 {
+        self.vdp_ram[(self.vdp_name_table_offset as usize).wrapping_add(offset) & 0x3FFF]
+    }
+
+    /// Write a name table entry at a tile position
+    /// 
+    /// Name table offset register must be set first.
+    /// 
+    /// The screen is 32x24 tiles, or 40x24 tiles in Text mode. Returns an error if the position is outside of the screen.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.write_name_table_xy(1, 2, b'A').unwrap();
+    /// assert_eq!(vdp.read_name_table(2 * 32 + 1), b'A');
+    /// assert!(vdp.write_name_table_xy(32, 0, b'A').is_err());
+    /// 
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// vdp.write_name_table_xy(39, 1, b'B').unwrap();
+    /// assert_eq!(vdp.read_name_table_xy(39, 1), Ok(b'B'));
+    /// assert!(vdp.read_name_table_xy(0, 24).is_err());
+    /// ```
+    pub fn write_name_table_xy(&mut self, x: usize, y: usize, tile: u8) -> Result<(), TMS9918AError> {
+        let offset = self.name_table_xy_offset(x, y)?;
+        self.write_name_table(offset, tile)
+    }
+
+    /// Read a name table entry at a tile position
+    /// 
+    /// Name table offset register must be set first.
+    /// 
+    /// The screen is 32x24 tiles, or 40x24 tiles in Text mode. Returns an error if the position is outside of the screen.
+    pub fn read_name_table_xy(&self, x: usize, y: usize) -> Result<u8, TMS9918AError> {
+        let offset = self.name_table_xy_offset(x, y)?;
+        Ok(self.read_name_table(offset))
+    }
+
+    /// Write a string to the name table starting at a tile position
+    /// 
+    /// Name table offset register must be set first.
+    /// 
+    /// Each character is written as one name table entry, using the row stride of the current video mode.
+    /// Characters are translated to tiles by the charmap set by [`set_charmap`](TMS9918A::set_charmap),
+    /// by default characters up to 0xFF use the tile with the same number and other characters are written as '?'.
+    /// 
+    /// Text is clipped at the right edge of the screen, unless wrapping is enabled with
+    /// [`set_text_wrap`](TMS9918A::set_text_wrap). Text is always clipped at the bottom of the screen.
+    /// 
+    /// Returns the number of characters written.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// assert_eq!(vdp.print_text(0, 1, "Hello, world!"), 13);
+    /// assert_eq!(vdp.read_name_table_xy(4, 1), Ok(b'o'));
+    /// ```
+    pub fn print_text(&mut self, x: usize, y: usize, text: &str) -> usize {
+        let charmap = self.charmap.clone();
+        self.print_text_with(x, y, text, charmap.as_ref())
+    }
+
+    /// Write a string to the name table starting at a tile position, using a charmap for this string only
+    /// 
+    /// This is the same as [`print_text`](TMS9918A::print_text), except that characters are translated by `charmap`
+    /// instead of the charmap set by [`set_charmap`](TMS9918A::set_charmap).
+    pub fn print_text_with(&mut self, x: usize, y: usize, text: &str, charmap: &dyn CharMap) -> usize {
+        let columns = self.name_table_columns();
+        let (mut x, mut y) = (x, y);
+        let mut written = 0;
+        for c in text.chars() {
+            if x >= columns {
+                if !self.text_wrap {
+                    break;
+                }
+                x = 0;
+                y += 1;
+            }
+            if y >= 24 {
+                break;
+            }
+            let tile = charmap.map(c).unwrap_or(self.fallback_tile);
+            self.write_name_table_raw(y * columns + x, tile);
+            x += 1;
+            written += 1;
+        }
+        written
+    }
+
+    /// Fill a rectangle of tiles in the name table with one tile
+    /// 
+    /// Name table offset register must be set first.
+    /// 
+    /// The rectangle starts at column `x` and row `y`, and is clipped to the 32x24 screen, or 40x24 in Text mode.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.clear_name_table();
+    /// // a wall along the right edge, clipped to the screen
+    /// vdp.fill_tile_rect(30, 20, 8, 8, 1);
+    /// assert_eq!(vdp.read_name_table_xy(31, 23), Ok(1));
+    /// assert_eq!(vdp.read_name_table_xy(29, 23), Ok(0));
+    /// assert_eq!(vdp.read_name_table_xy(31, 19), Ok(0));
+    /// 
+    /// vdp.clear_tile_rect(31, 0, 1, 24);
+    /// assert_eq!(vdp.read_name_table_xy(31, 23), Ok(0));
+    /// assert_eq!(vdp.read_name_table_xy(30, 23), Ok(1));
+    /// ```
+    pub fn fill_tile_rect(&mut self, x: usize, y: usize, width: usize, height: usize, tile: u8) {
+        let columns = self.name_table_columns();
+        let x_end = x.saturating_add(width).min(columns);
+        let y_end = y.saturating_add(height).min(24);
+        for row in y..y_end {
+            for column in x..x_end {
+                self.write_name_table_raw(row * columns + column, tile);
+            }
+        }
+    }
+
+    /// Clear a rectangle of tiles in the name table by writing tile 0
+    /// 
+    /// This is the same as [`fill_tile_rect`](TMS9918A::fill_tile_rect) with tile 0.
+    #[inline]
+    pub fn clear_tile_rect(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        self.fill_tile_rect(x, y, width, height, 0);
+    }
+
+    /// Draw a horizontal line of tiles in the name table, clipped to the screen
+    /// 
+    /// Name table offset register must be set first.
+    #[inline]
+    pub fn draw_hline(&mut self, x: usize, y: usize, length: usize, tile: u8) {
+        self.fill_tile_rect(x, y, length, 1, tile);
+    }
+
+    /// Draw a vertical line of tiles in the name table, clipped to the screen
+    /// 
+    /// Name table offset register must be set first.
+    #[inline]
+    pub fn draw_vline(&mut self, x: usize, y: usize, length: usize, tile: u8) {
+        self.fill_tile_rect(x, y, 1, length, tile);
+    }
+
+    /// Draw the frame of a box of tiles in the name table, clipped to the screen
+    /// 
+    /// Name table offset register must be set first.
+    /// 
+    /// The box starts at column `x` and row `y` and includes its frame, the inside of the box is not changed.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{BoxTiles, TMS9918A, VideoMode};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// vdp.clear_name_table();
+    /// let tiles = BoxTiles { corners: *b"++++", edges: *b"--||" };
+    /// vdp.draw_box(0, 0, 10, 3, tiles);
+    /// assert_eq!(vdp.read_name_table_xy(9, 2), Ok(b'+'));
+    /// assert_eq!(vdp.read_name_table_xy(5, 2), Ok(b'-'));
+    /// assert_eq!(vdp.read_name_table_xy(9, 1), Ok(b'|'));
+    /// assert_eq!(vdp.read_name_table_xy(5, 1), Ok(0));
+    /// 
+    /// // partially off screen
+    /// vdp.draw_box(35, 20, 10, 10, tiles);
+    /// assert_eq!(vdp.read_name_table_xy(35, 20), Ok(b'+'));
+    /// assert_eq!(vdp.read_name_table_xy(39, 20), Ok(b'-'));
+    /// ```
+    pub fn draw_box(&mut self, x: usize, y: usize, width: usize, height: usize, tiles: BoxTiles) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let right = x.saturating_add(width - 1);
+        let bottom = y.saturating_add(height - 1);
+        let [top_left, top_right, bottom_left, bottom_right] = tiles.corners;
+        let [top_edge, bottom_edge, left_edge, right_edge] = tiles.edges;
+
+        self.draw_hline(x.saturating_add(1), y, width.saturating_sub(2), top_edge);
+        self.draw_hline(x.saturating_add(1), bottom, width.saturating_sub(2), bottom_edge);
+        self.draw_vline(x, y.saturating_add(1), height.saturating_sub(2), left_edge);
+        self.draw_vline(right, y.saturating_add(1), height.saturating_sub(2), right_edge);
+        self.fill_tile_rect(x, y, 1, 1, top_left);
+        self.fill_tile_rect(right, y, 1, 1, top_right);
+        self.fill_tile_rect(x, bottom, 1, 1, bottom_left);
+        self.fill_tile_rect(right, bottom, 1, 1, bottom_right);
+    }
+
+    /// Scroll the whole name table by `dx` columns and `dy` rows
+    /// 
+    /// Name table offset register must be set first.
+    /// 
+    /// Positive values scroll the screen contents right and down, negative values scroll left and up.
+    /// Tiles are either wrapped around to the opposite edge, or vacated tiles are filled with a tile, see [`ScrollFill`].
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{ScrollFill, TMS9918A, VideoMode};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// vdp.clear_name_table();
+    /// vdp.write_name_table_xy(0, 0, b'A').unwrap();
+    /// vdp.write_name_table_xy(39, 23, b'B').unwrap();
+    /// 
+    /// vdp.scroll_name_table(1, 1, ScrollFill::Wrap);
+    /// assert_eq!(vdp.read_name_table_xy(1, 1), Ok(b'A'));
+    /// assert_eq!(vdp.read_name_table_xy(0, 0), Ok(b'B'));
+    /// 
+    /// vdp.scroll_name_table(-1, 0, ScrollFill::Tile(b' '));
+    /// assert_eq!(vdp.read_name_table_xy(0, 1), Ok(b'A'));
+    /// assert_eq!(vdp.read_name_table_xy(39, 0), Ok(b' '));
+    /// ```
+    pub fn scroll_name_table(&mut self, dx: isize, dy: isize, fill: ScrollFill) {
+        let columns = self.name_table_columns();
+        let size = columns * 24;
+        // reduce the distances first so that computing the source position can't overflow,
+        // scrolling by a whole screen or more wraps back to the same position or fills every tile
+        let (dx, dy) = match fill {
+            ScrollFill::Wrap => (dx.rem_euclid(columns as isize), dy.rem_euclid(24)),
+            ScrollFill::Tile(_) => (dx.clamp(-(columns as isize), columns as isize), dy.clamp(-24, 24))
+        };
+        // take a copy first so the source tiles aren't overwritten before they are moved
+        let tiles: Vec<u8> = (0..size).map(|i| self.read_name_table(i)).collect();
+        for y in 0..24 {
+            for x in 0..columns {
+                let src_x = x as isize - dx;
+                let src_y = y as isize - dy;
+                let tile = match fill {
+                    ScrollFill::Wrap => {
+                        let src_x = src_x.rem_euclid(columns as isize) as usize;
+                        let src_y = src_y.rem_euclid(24) as usize;
+                        tiles[src_y * columns + src_x]
+                    }
+                    ScrollFill::Tile(tile) => {
+                        if src_x < 0 || src_y < 0 || src_x >= columns as isize || src_y >= 24 {
+                            tile
+                        } else {
+                            tiles[src_y as usize * columns + src_x as usize]
+                        }
+                    }
+                };
+                self.write_name_table_raw(y * columns + x, tile);
+            }
+        }
+    }
+
+    /// Copy a rectangle of tiles in the name table to another position
+    /// 
+    /// Name table offset register must be set first.
+    /// 
+    /// The rectangle is clipped so that both the source and the destination fit on the screen.
+    /// The source and destination can overlap, the tiles are copied in an order that doesn't overwrite source tiles before they are copied.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.clear_name_table();
+    /// vdp.print_text(0, 0, "ABCD");
+    /// // overlapping copy one tile to the right
+    /// vdp.copy_name_table_rect(0, 0, 4, 1, 1, 0);
+    /// assert_eq!(vdp.read_name_table_xy(1, 0), Ok(b'A'));
+    /// assert_eq!(vdp.read_name_table_xy(4, 0), Ok(b'D'));
+    /// ```
+    pub fn copy_name_table_rect(&mut self, src_x: usize, src_y: usize, width: usize, height: usize, dst_x: usize, dst_y: usize) {
+        let columns = self.name_table_columns();
+        let width = width.min(columns.saturating_sub(src_x)).min(columns.saturating_sub(dst_x));
+        let height = height.min(24usize.saturating_sub(src_y)).min(24usize.saturating_sub(dst_y));
+        let rows_reversed = dst_y > src_y;
+        let columns_reversed = dst_x > src_x;
+        for i in 0..height {
+            let row = if rows_reversed { height - 1 - i } else { i };
+            for j in 0..width {
+                let column = if columns_reversed { width - 1 - j } else { j };
+                let tile = self.read_name_table((src_y + row) * columns + src_x + column);
+                self.write_name_table_raw((dst_y + row) * columns + dst_x + column, tile);
+            }
+        }
+    }
+
+    /// Save a rectangle of tiles in the name table into a buffer
+    /// 
+    /// Name table offset register must be set first.
+    /// 
+    /// The tiles are stored row by row, so the buffer must be at least `width * height` bytes.
+    /// Returns an error if the rectangle doesn't fit on the screen, or if the buffer is too small.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{BoxTiles, TMS9918A};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.clear_name_table();
+    /// vdp.print_text(0, 5, "Hello, world!");
+    /// 
+    /// // save the tiles under a popup, then restore them after it is closed
+    /// let mut saved = [0; 8 * 3];
+    /// vdp.save_name_table_rect(2, 4, 8, 3, &mut saved).unwrap();
+    /// vdp.draw_box(2, 4, 8, 3, BoxTiles { corners: [1; 4], edges: [2; 4] });
+    /// vdp.clear_tile_rect(3, 5, 6, 1);
+    /// vdp.restore_name_table_rect(2, 4, 8, 3, &saved).unwrap();
+    /// assert_eq!(vdp.read_name_table_xy(2, 5), Ok(b'l'));
+    /// assert_eq!(vdp.read_name_table_xy(2, 4), Ok(0));
+    /// 
+    /// assert!(vdp.save_name_table_rect(30, 0, 8, 3, &mut saved).is_err());
+    /// ```
+    pub fn save_name_table_rect(&self, x: usize, y: usize, width: usize, height: usize, buffer: &mut [u8]) -> Result<(), TMS9918AError> {
+        let columns = self.name_table_rect_check(x, y, width, height, buffer.len())?;
+        for row in 0..height {
+            for column in 0..width {
+                buffer[row * width + column] = self.read_name_table((y + row) * columns + x + column);
+            }
+        }
+        Ok(())
+    }
+
+    /// Restore a rectangle of tiles in the name table from a buffer
+    /// 
+    /// Name table offset register must be set first.
+    /// 
+    /// This is the reverse of [`save_name_table_rect`](TMS9918A::save_name_table_rect), the tiles are read row by row from the buffer.
+    /// Returns an error if the rectangle doesn't fit on the screen, or if the buffer is too small.
+    pub fn restore_name_table_rect(&mut self, x: usize, y: usize, width: usize, height: usize, buffer: &[u8]) -> Result<(), TMS9918AError> {
+        let columns = self.name_table_rect_check(x, y, width, height, buffer.len())?;
+        for row in 0..height {
+            for column in 0..width {
+                self.write_name_table_raw((y + row) * columns + x + column, buffer[row * width + column]);
+            }
+        }
+        Ok(())
+    }
+
+    // check that a rectangle of tiles fits on the screen and in a buffer, returns the number of columns
+    fn name_table_rect_check(&self, x: usize, y: usize, width: usize, height: usize, buffer_length: usize) -> Result<usize, TMS9918AError> {
+        let columns = self.name_table_columns();
+        if width == 0 || height == 0 {
+            return Ok(columns);
+        }
+        let (x_last, y_last) = (x.saturating_add(width - 1), y.saturating_add(height - 1));
+        if x_last >= columns || y_last >= 24 {
+            return Err(TMS9918AError::TileOutOfRange { x: x_last, y: y_last });
+        }
+        let required_length = width * height;
+        if buffer_length < required_length {
+            return Err(TMS9918AError::BufferTooSmall(required_length));
+        }
+        Ok(columns)
+    }
+
+    /// Write a tile map to the name table, with the top left corner of the map at a tile position
+    /// 
+    /// Name table offset register must be set first.
+    /// 
+    /// The map is clipped to the 32x24 screen, or 40x24 in Text mode. Returns false if the map was clipped.
+    /// This is only available with the `tilemap` feature.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, Tilemap};
+    /// let mut vdp = TMS9918A::new();
+    /// let map = Tilemap::from_csv("1,2,3\n4,5,6").unwrap();
+    /// assert!(vdp.write_tilemap(&map, 10, 5));
+    /// assert_eq!(vdp.read_name_table_xy(12, 6), Ok(6));
+    /// 
+    /// // only the first column fits
+    /// assert!(!vdp.write_tilemap(&map, 31, 0));
+    /// assert_eq!(vdp.read_name_table_xy(31, 1), Ok(4));
+    /// ```
+    #[cfg(feature = "tilemap")]
+    pub fn write_tilemap(&mut self, map: &Tilemap, x: usize, y: usize) -> bool {
+        let columns = self.name_table_columns();
+        let width = map.width.min(columns.saturating_sub(x));
+        let height = map.height.min(24usize.saturating_sub(y));
+        for row in 0..height {
+            for column in 0..width {
+                self.write_name_table_raw((y + row) * columns + x + column, map.tiles[row * map.width + column]);
+            }
+        }
+        width == map.width && height == map.height
+    }
+
+    /// Write a tile map in the CSV format exported by Tiled to the name table
+    /// 
+    /// Name table offset register must be set first.
+    /// 
+    /// See [`Tilemap::from_csv`] for the format. Returns an error if the map is invalid, or larger than the 32x24 screen,
+    /// or 40x24 in Text mode. Use [`write_tilemap`](TMS9918A::write_tilemap) to write part of a larger map.
+    /// This is only available with the `tilemap` feature.
+    #[cfg(feature = "tilemap")]
+    pub fn load_tilemap_csv(&mut self, csv: &str) -> Result<(), TMS9918AError> {
+        let map = Tilemap::from_csv(csv)?;
+        if map.width > self.name_table_columns() || map.height > 24 {
+            return Err(TMS9918AError::TilemapTooLarge { width: map.width, height: map.height });
+        }
+        self.write_tilemap(&map, 0, 0);
+        Ok(())
+    }
+
+    /// Get a terminal-like text console writing to the name table
+    /// 
+    /// The cursor position is kept by the VDP, so output continues where it left off on the next call.
+    /// See [`TextConsole`] for details.
+    #[inline]
+    pub fn console(&mut self) -> TextConsole<'_> {
+        TextConsole::new(self)
+    }
+
+    /// Enable or disable wrapping text to the next row at the right edge of the screen in [`print_text`](TMS9918A::print_text)
+    /// 
+    /// Text is clipped at the right edge by default.
+    #[inline]
+    pub fn set_text_wrap(&mut self, enable: bool) {
+        self.text_wrap = enable;
+    }
+
+    /// Set the translation from characters to tiles used by [`print_text`](TMS9918A::print_text) and the text console
+    /// 
+    /// The charmap is [`Identity`] by default. See [`CharMap`].
+    pub fn set_charmap<C: CharMap + Send + Sync + 'static>(&mut self, charmap: C) {
+        self.charmap = Arc::new(charmap);
+    }
+
+    /// Set the character code of the first tile of an ASCII font used by [`print_text`](TMS9918A::print_text) and the text console
+    /// 
+    /// This is subtracted from each character, for example a font starting at the space character uses an offset of 0x20.
+    /// This is the same as setting an [`AsciiOffset`] charmap with the negative offset.
+    #[inline]
+    pub fn set_charmap_offset(&mut self, offset: u8) {
+        self.set_charmap(AsciiOffset(-(offset as i16)));
+    }
+
+    /// Set the tile written for characters which the charmap doesn't map
+    /// 
+    /// The fallback tile is '?' by default.
+    #[inline]
+    pub fn set_fallback_tile(&mut self, tile: u8) {
+        self.fallback_tile = tile;
+    }
+
+    // translate a character to a tile with the current charmap
+    pub(crate) fn map_char(&self, c: char) -> u8 {
+        self.charmap.map(c).unwrap_or(self.fallback_tile)
+    }
+
+    // get the name table offset of a tile position, or an error if it is outside of the screen
+    fn name_table_xy_offset(&self, x: usize, y: usize) -> Result<usize, TMS9918AError> {
+        let columns = self.name_table_columns();
+        if x >= columns || y >= 24 {
+            return Err(TMS9918AError::TileOutOfRange { x, y });
+        }
+        Ok(y * columns + x)
+    }
+
+    /// Set the color table address multiplier in register 3
+    /// 
+    /// Color table base address is equal to multiplier * 0x0040.
+    /// 
+    /// This function is equivalent to setting register 3 directly.
+    /// 
+    /// # Examples
+    /// 
+    /// ```no_run
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// # fn main() {
+    /// # let mut vdp = TMS9918A::new();
+    /// // set color table base address to 0x0040
+    /// vdp.set_color_table_multiplier(1);
+    /// # }
+    /// ```
+    #[inline]
+    pub fn set_color_table_multiplier(&mut self, multiplier: u8) {
+        self.write_register_typed(Register::ColorTableBase, multiplier);
+    }
+
+    /// Fill color table contents from an array
+    /// 
+    /// Color table offset register must be set first.
+    /// 
+    /// Returns an error if the filled range is past the end of the color table for the current video mode, or past the end of video memory.
+    /// Nothing is written in that case.
+    /// 
+    /// The first `length` bytes of the array are copied to the color table starting at `offset`.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// # let mut vdp = TMS9918A::new();
+    /// # vdp.set_color_table_multiplier(0x80);
+    /// // fill the first 5 color table entries
+    /// // black on white, white on black, light blue on dark blue, light red on cyan, black on gray
+    /// let color_table: [u8; 5] = [0x1F, 0xF1, 0x54, 0x97, 0x1E];
+    /// vdp.fill_color_table(&color_table, 0, color_table.len()).unwrap();
+    /// 
+    /// // use the same colors for the last two groups of tiles
+    /// vdp.fill_color_table(&color_table, 30, 2).unwrap();
+    /// assert_eq!(vdp.read_ram(0x2000 + 30), 0x1F);
+    /// assert_eq!(vdp.read_ram(0x2000 + 31), 0xF1);
+    /// 
+    /// // the Graphics I color table only has 32 entries
+    /// assert!(vdp.fill_color_table(&color_table, 30, 3).is_err());
+    /// ```
+    pub fn fill_color_table(&mut self, array: &[u8], offset: usize, length: usize) -> Result<(), TMS9918AError> {
+        let length = length.min(array.len());
+        Self::check_table_range(self.vdp_color_table_offset, self.color_table_size(), offset, length)?;
+        self.fill_color_table_raw(array, offset, length);
+        Ok(())
+    }
+
+    /// Fill color table contents from an array without checking the size of the color table
+    /// 
+    /// This can write past the end of the color table into the video memory after it, and addresses wrap around at 16K.
+    pub fn fill_color_table_raw(&mut self, array: &[u8], offset: usize, length: usize) {
+        for (i, data) in array.iter().take(length).enumerate() {
+            self.write_color_table_raw(offset.wrapping_add(i), *data);
+        }
+    }
+
+    /// Write color table contents
+    /// 
+    /// Color table offset register must be set first.
+    /// 
+    /// Returns an error if the offset is past the end of the color table, or past the end of video memory.
+    #[inline]
+    pub fn write_color_table(&mut self, offset: usize, data: u8) -> Result<(), TMS9918AError> {
+        Self::check_table_range(self.vdp_color_table_offset, self.color_table_size(), offset, 1)?;
+        self.write_color_table_raw(offset, data);
+        Ok(())
+    }
+
+    /// Write color table contents without checking the size of the color table
+    /// 
+    /// This can write past the end of the color table into the video memory after it, and addresses wrap around at 16K.
+    #[inline]
+    pub fn write_color_table_raw(&mut self, offset: usize, data: u8) {
+        self.write_ram((self.vdp_color_table_offset as usize).wrapping_add(offset), data);
+    }
+
+    /// Read color table contents
+    /// 
+    /// Color table offset register must be set first.
+    #[inline]
+    pub fn read_color_table(&self, offset: usize) -> u8 {
+        self.vdp_ram[(self.vdp_color_table_offset as usize).wrapping_add(offset) & 0x3FFF]
+    }
+
+    /// Set the foreground and background colors of a group of 8 tiles in Graphics I mode
+    /// 
+    /// Color table offset register must be set first.
+    /// 
+    /// Each of the 32 color table entries sets the colors of 8 consecutive tile patterns, group 0 is tiles 0-7, group 1 is tiles 8-15, etc.
+    /// Returns an error if the group is 32 or above.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{Color, TMS9918A};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_tile_group_colors(4, Color::White, Color::DarkBlue).unwrap();
+    /// assert_eq!(vdp.read_color_table(4), 0xF4);
+    /// assert_eq!(vdp.tile_group_colors(4), (Color::White, Color::DarkBlue));
+    /// assert!(vdp.set_tile_group_colors(32, Color::White, Color::DarkBlue).is_err());
+    /// 
+    /// // tile 'A' (0x41) is in group 8, along with the rest of '@' to 'G'
+    /// vdp.set_colors_for_tile(b'A', Color::LightRed, Color::Black).unwrap();
+    /// assert_eq!(vdp.tile_group_colors(8), (Color::LightRed, Color::Black));
+    /// ```
+    pub fn set_tile_group_colors(&mut self, group: u8, foreground: Color, background: Color) -> Result<(), TMS9918AError> {
+        if group >= 32 {
+            return Err(TMS9918AError::OutOfRange { end: group as usize + 1, size: 32 });
+        }
+        self.write_color_table(group as usize, u8::from(foreground) << 4 | u8::from(background))
+    }
+
+    /// Get the foreground and background colors of a group of 8 tiles in Graphics I mode
+    /// 
+    /// Color table offset register must be set first.
+    /// 
+    /// Groups above 31 wrap around.
+    #[inline]
+    pub fn tile_group_colors(&self, group: u8) -> (Color, Color) {
+        let colors = self.read_color_table(group as usize % 32);
+        (Color::from(colors >> 4), Color::from(colors))
+    }
+
+    /// Set the foreground and background colors of the group containing a tile in Graphics I mode
+    /// 
+    /// Color table offset register must be set first.
+    /// 
+    /// The hardware only has one color table entry for each group of 8 tiles, so this also changes the colors of the
+    /// 7 other tiles in the same group. See [`set_tile_group_colors`](TMS9918A::set_tile_group_colors).
+    #[inline]
+    pub fn set_colors_for_tile(&mut self, tile: u8, foreground: Color, background: Color) -> Result<(), TMS9918AError> {
+        self.set_tile_group_colors(tile / 8, foreground, background)
+    }
+
+    /// Set the pattern table address multiplier in register 4
+    /// 
+    /// Pattern table base address is equal to multiplier * 0x0800.
+    /// 
+    /// This function is equivalent to setting register 4 directly.
+    /// 
+    /// # Examples
+    /// 
     /// ```no_run
     /// # use tms9918a_emu::{TMS9918A, VideoMode};
     /// # fn main() {
     /// # let mut vdp = TMS9918A::new();
+    /// // set pattern table base address to 0x0800
+    /// vdp.set_pattern_table_multiplier(1);
+    /// # }
+    /// ```
+    #[inline]
+    pub fn set_pattern_table_multiplier(&mut self, mut multiplier: u8) {
+        if multiplier > 7 {
+            multiplier = 7;
+        }
+        self.write_register_typed(Register::PatternTableBase, multiplier);
+    }
+
+    /// Fill pattern table contents from an array
+    /// 
+    /// Pattern table offset register must be set first.
+    /// 
+    /// Returns an error if the filled range is past the end of the pattern table for the current video mode, or past the end of video memory.
+    /// Nothing is written in that case.
+    /// 
+    /// The first `length` bytes of the array are copied to the pattern table starting at `offset`.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// # let mut vdp = TMS9918A::new();
+    /// # vdp.set_pattern_table_multiplier(1);
     /// // fill 8 pattern table entries starting at offset 8
     /// // 8 pattern table entries make one tile
     /// // this makes tile 1 a nice smiley face :)
@@ -512,45 +3579,519 @@ impl TMS9918A {
     ///                               0b01000010,
     ///                               0b01111110,
     ///                               0b00000000];
-    /// vdp.fill_pattern_table(&pattern_table, 8, pattern_table.len());
-    /// # }
+    /// vdp.fill_pattern_table(&pattern_table, 8, pattern_table.len()).unwrap();
+    /// assert_eq!(vdp.read_ram(0x0800 + 9), 0b00100100);
+    /// assert_eq!(vdp.read_ram(0x0800 + 14), 0b01111110);
     /// ```
-    #[inline]
-    pub fn fill_pattern_table(&mut self, array: &[u8], offset: usize, length: usize) {
-        for i in offset..offset+length {
-            self.write_pattern_table(i, array[i]);
+    pub fn fill_pattern_table(&mut self, array: &[u8], offset: usize, length: usize) -> Result<(), TMS9918AError> {
+        let length = length.min(array.len());
+        Self::check_table_range(self.vdp_pattern_table_offset, self.pattern_table_size(), offset, length)?;
+        self.fill_pattern_table_raw(array, offset, length);
+        Ok(())
+    }
+
+    /// Fill pattern table contents from an array without checking the size of the pattern table
+    /// 
+    /// This can write past the end of the pattern table into the video memory after it, and addresses wrap around at 16K.
+    pub fn fill_pattern_table_raw(&mut self, array: &[u8], offset: usize, length: usize) {
+        for (i, data) in array.iter().take(length).enumerate() {
+            self.write_pattern_table_raw(offset.wrapping_add(i), *data);
         }
     }
 
     /// Write pattern table contents
     /// 
     /// Pattern table offset register must be set first.
+    /// 
+    /// Returns an error if the offset is past the end of the pattern table, or past the end of video memory.
+    #[inline]
+    pub fn write_pattern_table(&mut self, offset: usize, data: u8) -> Result<(), TMS9918AError> {
+        Self::check_table_range(self.vdp_pattern_table_offset, self.pattern_table_size(), offset, 1)?;
+        self.write_pattern_table_raw(offset, data);
+        Ok(())
+    }
+
+    /// Write pattern table contents without checking the size of the pattern table
+    /// 
+    /// This can write past the end of the pattern table into the video memory after it, and addresses wrap around at 16K.
+    #[inline]
+    pub fn write_pattern_table_raw(&mut self, offset: usize, data: u8) {
+        self.write_ram((self.vdp_pattern_table_offset as usize).wrapping_add(offset), data);
+    }
+
+    /// Read pattern table contents
+    /// 
+    /// Pattern table offset register must be set first.
+    #[inline]
+    pub fn read_pattern_table(&self, offset: usize) -> u8 {
+        self.vdp_ram[(self.vdp_pattern_table_offset as usize).wrapping_add(offset) & 0x3FFF]
+    }
+
+    /// Set the 8 rows of a tile pattern
+    /// 
+    /// Pattern table offset register must be set first.
+    /// 
+    /// The pattern table has 256 tiles, or 768 tiles in Graphics II mode where each third of the screen has its own 256 tiles.
+    /// In Graphics II mode the tile index is `third * 256 + tile`.
+    /// Returns an error if the tile is past the end of the pattern table, or past the end of video memory.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_pattern_table_multiplier(1);
+    /// // this makes tile 1 a nice smiley face :)
+    /// vdp.set_pattern(1, &[0x00, 0x24, 0x24, 0x24, 0x00, 0x42, 0x7E, 0x00]).unwrap();
+    /// assert_eq!(vdp.read_ram(0x0800 + 9), 0b00100100);
+    /// assert_eq!(vdp.pattern(1)[6], 0b01111110);
+    /// 
+    /// // only Graphics II mode has more than 256 tiles
+    /// assert!(vdp.set_pattern(256, &[0; 8]).is_err());
+    /// ```
+    pub fn set_pattern(&mut self, tile: usize, rows: &[u8; 8]) -> Result<(), TMS9918AError> {
+        self.fill_pattern_table(rows, tile.saturating_mul(8), 8)
+    }
+
+    /// Get the 8 rows of a tile pattern
+    /// 
+    /// Pattern table offset register must be set first.
+    /// 
+    /// See [`set_pattern`](TMS9918A::set_pattern) for the tile index.
+    pub fn pattern(&self, tile: usize) -> [u8; 8] {
+        let mut rows = [0; 8];
+        for (i, row) in rows.iter_mut().enumerate() {
+            *row = self.read_pattern_table(tile.wrapping_mul(8).wrapping_add(i));
+        }
+        rows
+    }
+
+    /// Load a font into the pattern table
+    /// 
+    /// Pattern table offset register must be set first.
+    /// 
+    /// The font is a list of 8 byte tile patterns, one for each glyph, copied to the pattern table starting at `offset`.
+    /// Returns the number of glyphs loaded, or an error if the length of the font is not a multiple of 8 bytes
+    /// or the font doesn't fit in the pattern table. Nothing is written in that case.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_pattern_table_multiplier(1);
+    /// // glyphs for '0' and '1', starting at tile 0x30
+    /// let font = [0x38, 0x44, 0x4C, 0x54, 0x64, 0x44, 0x38, 0x00,
+    ///             0x10, 0x30, 0x10, 0x10, 0x10, 0x10, 0x38, 0x00];
+    /// assert_eq!(vdp.load_font(&font, 0x30 * 8), Ok(2));
+    /// assert_eq!(vdp.pattern(0x31)[1], 0x30);
+    /// 
+    /// assert!(vdp.load_font(&font[..12], 0).is_err());
+    /// assert!(vdp.load_font(&font, 0xFF * 8).is_err());
+    /// ```
+    pub fn load_font(&mut self, font: &[u8], offset: usize) -> Result<usize, TMS9918AError> {
+        if font.len() & 7 != 0 {
+            return Err(TMS9918AError::InvalidFontLength(font.len()));
+        }
+        self.fill_pattern_table(font, offset, font.len())?;
+        Ok(font.len() / 8)
+    }
+
+    /// Load a built-in font into the pattern table
+    /// 
+    /// Pattern table offset register must be set first.
+    /// 
+    /// The font is loaded at the start of the pattern table, so each character uses the tile with the same character code.
+    /// Returns the number of glyphs loaded. This is only available with the `fonts` feature.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// use tms9918a_emu::fonts::Font;
+    /// 
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// vdp.set_pattern_table_multiplier(1);
+    /// assert_eq!(vdp.load_builtin_font(Font::Text6x8), Ok(128));
+    /// assert_eq!(vdp.pattern(b'A' as usize)[0], 0b01110000);
+    /// ```
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{BoxTiles, TMS9918A, VramInit};
+    /// use tms9918a_emu::fonts::Font;
+    /// 
+    /// // a double line box drawn with the code page 437 box drawing characters
+    /// let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+    /// assert_eq!(vdp.load_builtin_font(Font::Graphics8x8), Ok(256));
+    /// vdp.draw_box(0, 0, 10, 5, BoxTiles { corners: [0xC9, 0xBB, 0xC8, 0xBC], edges: [0xCD, 0xCD, 0xBA, 0xBA] });
+    /// assert_eq!(vdp.pattern(0xCD), [0x00, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0x00]);
+    /// ```
+    #[cfg(feature = "fonts")]
+    pub fn load_builtin_font(&mut self, font: fonts::Font) -> Result<usize, TMS9918AError> {
+        self.load_font(font.data(), 0)
+    }
+
+    /// Load an MSX Screen 2 image in the BSAVE format, usually an .SC2 file
+    /// 
+    /// The file starts with a 7 byte header: 0xFE, then the start address, end address and execution address in little endian.
+    /// The rest of the file is copied to video memory from the start address to the end address, which contains the tables at the
+    /// standard MSX Screen 2 addresses. The registers are then set to those addresses and the VDP is switched to Graphics II mode:
+    /// 
+    /// | Table            | Address | Register |
+    /// |------------------|---------|----------|
+    /// | Pattern          | 0x0000  | R4=0x03  |
+    /// | Name             | 0x1800  | R2=0x06  |
+    /// | Sprite attribute | 0x1B00  | R5=0x36  |
+    /// | Color            | 0x2000  | R3=0xFF  |
+    /// | Sprite pattern   | 0x3800  | R6=0x07  |
+    /// 
+    /// Returns an error if the header is invalid or the file is shorter than the header says. Nothing is written in that case.
+    /// The TMS9918 variant doesn't have Graphics II mode and stays in Graphics I mode.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// let mut sc2 = vec![0xFE, 0x00, 0x00, 0xFF, 0x37, 0x00, 0x00];
+    /// sc2.resize(7 + 0x3800, 0);
+    /// sc2[7 + 0x1800] = 0x42;
+    /// sc2[7 + 0x2000] = 0xF1;
+    /// 
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.load_sc2(&sc2).unwrap();
+    /// assert_eq!(vdp.read_name_table(0), 0x42);
+    /// assert_eq!(vdp.read_color_table(0), 0xF1);
+    /// 
+    /// assert!(vdp.load_sc2(&sc2[..0x1000]).is_err());
+    /// ```
+    pub fn load_sc2(&mut self, data: &[u8]) -> Result<(), TMS9918AError> {
+        if data.len() < 7 || data[0] != 0xFE {
+            return Err(TMS9918AError::InvalidSc2("missing BSAVE header".to_string()));
+        }
+        let start = u16::from_le_bytes([data[1], data[2]]) as usize;
+        let end = u16::from_le_bytes([data[3], data[4]]) as usize;
+        if end < start || end > 0x3FFF {
+            return Err(TMS9918AError::InvalidSc2(format!("invalid address range {:#06X}-{:#06X}", start, end)));
+        }
+        let length = end - start + 1;
+        if data.len() < 7 + length {
+            return Err(TMS9918AError::InvalidSc2(format!("{} bytes of video memory expected, {} found", length, data.len() - 7)));
+        }
+
+        for (i, byte) in data[7..7 + length].iter().enumerate() {
+            self.write_ram(start + i, *byte);
+        }
+        self.set_video_mode(VideoMode::Gfx2);
+        self.write_register_typed(Register::NameTableBase, 0x06);
+        self.write_register_typed(Register::ColorTableBase, 0xFF);
+        self.write_register_typed(Register::PatternTableBase, 0x03);
+        self.write_register_typed(Register::SpriteAttributeBase, 0x36);
+        self.write_register_typed(Register::SpritePatternBase, 0x07);
+        Ok(())
+    }
+
+    /// Load an MSX Screen 2 image file in the BSAVE format, usually an .SC2 file
+    /// 
+    /// This reads the file and loads it with [`load_sc2`](TMS9918A::load_sc2).
+    /// Returns an error if the file can't be read, as well as the errors returned by `load_sc2`.
+    /// This is only available with the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn load_sc2_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), TMS9918AError> {
+        let data = fs::read(path).map_err(|error| TMS9918AError::Io(error.to_string()))?;
+        self.load_sc2(&data)
+    }
+
+    /// Load a font file into the pattern table
+    /// 
+    /// Pattern table offset register must be set first.
+    /// 
+    /// This reads the file and loads it with [`load_font`](TMS9918A::load_font), returning the number of glyphs loaded.
+    /// Returns an error if the file can't be read, as well as the errors returned by `load_font`.
+    /// This is only available with the `std` feature.
+    /// 
+    /// # Examples
+    /// 
+    /// ```no_run
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// # fn main() -> Result<(), tms9918a_emu::TMS9918AError> {
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_video_mode(VideoMode::Text);
+    /// vdp.set_pattern_table_multiplier(1);
+    /// let glyphs = vdp.load_font_file("font.bin", 0)?;
+    /// println!("loaded {} glyphs", glyphs);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn load_font_file<P: AsRef<Path>>(&mut self, path: P, offset: usize) -> Result<usize, TMS9918AError> {
+        let font = fs::read(path).map_err(|error| TMS9918AError::Io(error.to_string()))?;
+        self.load_font(&font, offset)
+    }
+
+    /// Write the whole video memory to a file, exactly 16384 bytes
+    /// 
+    /// The file can be inspected with a hex editor, or loaded again with [`load_vram`](TMS9918A::load_vram).
+    /// Returns an error if the file can't be written. This is only available with the `std` feature.
+    /// 
+    /// # Examples
+    /// 
+    /// ```no_run
+    /// # use tms9918a_emu::TMS9918A;
+    /// # fn main() -> Result<(), tms9918a_emu::TMS9918AError> {
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.dump_vram("vram.bin")?;
+    /// vdp.load_vram("vram.bin")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn dump_vram<P: AsRef<Path>>(&self, path: P) -> Result<(), TMS9918AError> {
+        fs::write(path, &self.vdp_ram).map_err(|error| TMS9918AError::Io(error.to_string()))
+    }
+
+    /// Load a video memory dump file, replacing the whole video memory
+    /// 
+    /// This reads the file and loads it with [`load_vram_bytes`](TMS9918A::load_vram_bytes), so registers and the
+    /// address pointer are not changed. Returns an error if the file can't be read or is not exactly 16384 bytes.
+    /// This is only available with the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn load_vram<P: AsRef<Path>>(&mut self, path: P) -> Result<(), TMS9918AError> {
+        let data = fs::read(path).map_err(|error| TMS9918AError::Io(error.to_string()))?;
+        self.load_vram_bytes(&data)
+    }
+
+    /// Set the sprite attribute table address multiplier in register 5
+    /// 
+    /// Sprite attribute table base address is equal to multiplier * 0x0080.
+    /// 
+    /// This function is equivalent to setting register 5 directly.
+    /// 
+    /// # Examples
+    /// 
+    /// ```no_run
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// # fn main() {
+    /// # let mut vdp = TMS9918A::new();
+    /// // set sprite attribute table base address to 0x1000
+    /// vdp.set_sprite_attribute_table_multiplier(0x20);
+    /// # }
+    /// ```
+    #[inline]
+    pub fn set_sprite_attribute_table_multiplier(&mut self, mut multiplier: u8) {
+        if multiplier > 127 {
+            multiplier = 127;
+        }
+        self.write_register_typed(Register::SpriteAttributeBase, multiplier);
+    }
+
+    /// Fill sprite attribute table contents from an array
+    /// 
+    /// Sprite attribute table offset register must be set first.
+    /// 
+    /// Returns an error if the filled range is past the end of the sprite attribute table for the current video mode, or past the end of video memory.
+    /// Nothing is written in that case.
+    /// 
+    /// The first `length` bytes of the array are copied to the sprite attribute table starting at `offset`.
+    /// 
+    /// Each sprite uses 4 bytes: vertical position, horizontal position, pattern number,
+    /// and early clock bit (bit 7) | color.
+    /// 
+    /// # Examples
+    /// 
+    /// ```no_run
+    /// # use tms9918a_emu::{TMS9918A, VideoMode};
+    /// # fn main() {
+    /// # let mut vdp = TMS9918A::new();
+    /// // place sprite 0 at (16, 32) using pattern 1 in white,
+    /// // and stop processing sprites after it
+    /// let sprite_attribute_table: [u8; 5] = [32, 16, 1, 0x0F, 0xD0];
+    /// vdp.fill_sprite_attribute_table(&sprite_attribute_table, 0, sprite_attribute_table.len()).unwrap();
+    /// # }
+    /// ```
+    pub fn fill_sprite_attribute_table(&mut self, array: &[u8], offset: usize, length: usize) -> Result<(), TMS9918AError> {
+        let length = length.min(array.len());
+        Self::check_table_range(self.vdp_sprite_attribute_offset, SPRITE_ATTRIBUTE_TABLE_SIZE, offset, length)?;
+        self.fill_sprite_attribute_table_raw(array, offset, length);
+        Ok(())
+    }
+
+    /// Fill sprite attribute table contents from an array without checking the size of the sprite attribute table
+    /// 
+    /// This can write past the end of the sprite attribute table into the video memory after it, and addresses wrap around at 16K.
+    pub fn fill_sprite_attribute_table_raw(&mut self, array: &[u8], offset: usize, length: usize) {
+        for (i, data) in array.iter().take(length).enumerate() {
+            self.write_sprite_attribute_table_raw(offset.wrapping_add(i), *data);
+        }
+    }
+
+    /// Write sprite attribute table contents
+    /// 
+    /// Sprite attribute table offset register must be set first.
+    /// 
+    /// Returns an error if the offset is past the end of the sprite attribute table, or past the end of video memory.
+    #[inline]
+    pub fn write_sprite_attribute_table(&mut self, offset: usize, data: u8) -> Result<(), TMS9918AError> {
+        Self::check_table_range(self.vdp_sprite_attribute_offset, SPRITE_ATTRIBUTE_TABLE_SIZE, offset, 1)?;
+        self.write_sprite_attribute_table_raw(offset, data);
+        Ok(())
+    }
+
+    /// Write sprite attribute table contents without checking the size of the sprite attribute table
+    /// 
+    /// This can write past the end of the sprite attribute table into the video memory after it, and addresses wrap around at 16K.
+    #[inline]
+    pub fn write_sprite_attribute_table_raw(&mut self, offset: usize, data: u8) {
+        self.write_ram((self.vdp_sprite_attribute_offset as usize).wrapping_add(offset), data);
+    }
+
+    /// Read sprite attribute table contents
+    /// 
+    /// Sprite attribute table offset register must be set first.
+    #[inline]
+    pub fn read_sprite_attribute_table(&self, offset: usize) -> u8 {
+        self.vdp_ram[(self.vdp_sprite_attribute_offset as usize).wrapping_add(offset) & 0x3FFF]
+    }
+
+    /// Define a sprite's position, pattern, and color in the sprite attribute table
+    ///
+    /// Sprite attribute table offset register must be set first. Sprite index must be 0-31.
+    ///
+    /// Like the real hardware, sprites are displayed one line below their vertical position:
+    /// a vertical position of 0xFF places the top of the sprite on the first line of the screen.
+    /// Vertical positions wrap around, so positions near 0xFF allow sprites to slide in from the top of the screen.
+    ///
+    /// A vertical position of 0xD0 hides the sprite and all sprites after it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tms9918a_emu::{TMS9918A, SpriteDef};
+    /// # fn main() {
+    /// # let mut vdp = TMS9918A::new();
+    /// // display sprite pattern 0 in white near the top left corner of the screen
+    /// vdp.define_sprite(0, SpriteDef { x: 16, y: 16, pattern: 0, color: 0xF, early_clock: false }).unwrap();
+    /// # }
+    /// ```
+    pub fn define_sprite(&mut self, index: u8, sprite: SpriteDef) -> Result<(), TMS9918AError> {
+        let offset = self.sprite_attribute_offset(index)?;
+        let early_clock = if sprite.early_clock { 1 << 7 } else { 0 };
+        self.write_sprite_attribute_table_raw(offset, sprite.y);
+        self.write_sprite_attribute_table_raw(offset + 1, sprite.x);
+        self.write_sprite_attribute_table_raw(offset + 2, sprite.pattern);
+        self.write_sprite_attribute_table_raw(offset + 3, early_clock | (sprite.color & 0x0F));
+        Ok(())
+    }
+
+    /// Move a sprite to a new position without changing its pattern or color
+    ///
+    /// Sprite attribute table offset register must be set first.
+    ///
+    /// Returns an error if the sprite index is above 31, or the sprite is past the end of video memory.
+    #[inline]
+    pub fn move_sprite(&mut self, index: u8, x: u8, y: u8) -> Result<(), TMS9918AError> {
+        let offset = self.sprite_attribute_offset(index)?;
+        self.write_sprite_attribute_table_raw(offset, y);
+        self.write_sprite_attribute_table_raw(offset + 1, x);
+        Ok(())
+    }
+
+    /// Hide a sprite by moving it below the bottom of the screen
+    ///
+    /// Unlike writing the 0xD0 terminator to its vertical position, this does not hide the sprites after it.
+    ///
+    /// Sprite attribute table offset register must be set first.
+    ///
+    /// Returns an error if the sprite index is above 31, or the sprite is past the end of video memory.
     #[inline]
-    pub fn write_pattern_table(&mut self, offset: usize, data: u8) {
-        self.vdp_ram[self.vdp_pattern_table_offset as usize + offset] = data;
+    pub fn hide_sprite(&mut self, index: u8) -> Result<(), TMS9918AError> {
+        let offset = self.sprite_attribute_offset(index)?;
+        self.write_sprite_attribute_table_raw(offset, 0xC0);
+        Ok(())
     }
 
-    /// Read pattern table contents
-    /// 
-    /// Pattern table offset register must be set first.
-    #[inline]
-    pub fn read_pattern_table(&self, offset: usize) -> u8 {
-        self.vdp_ram[self.vdp_pattern_table_offset as usize + offset]
+    // get the sprite attribute table offset of a sprite, checking that all 4 bytes of it are in the table
+    fn sprite_attribute_offset(&self, index: u8) -> Result<usize, TMS9918AError> {
+        let offset = index as usize * 4;
+        Self::check_table_range(self.vdp_sprite_attribute_offset, SPRITE_ATTRIBUTE_TABLE_SIZE, offset, 4)?;
+        Ok(offset)
+    }
+
+    /// Set the contents of a sprite pattern in the sprite pattern table
+    ///
+    /// 8x8 sprite patterns are 8 bytes long. 16x16 sprite patterns are 32 bytes long and use four consecutive
+    /// pattern numbers, stored as quadrants in the order top left, bottom left, top right, bottom right.
+    ///
+    /// Sprite pattern table offset register must be set first.
+    ///
+    /// Returns an error if the pattern is past the end of the sprite pattern table, or past the end of video memory.
+    /// Nothing is written in that case.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tms9918a_emu::TMS9918A;
+    /// # fn main() {
+    /// # let mut vdp = TMS9918A::new();
+    /// // make sprite pattern 0 a ball
+    /// let ball: [u8; 8] = [0b00111100,
+    ///                      0b01111110,
+    ///                      0b11111111,
+    ///                      0b11111111,
+    ///                      0b11111111,
+    ///                      0b11111111,
+    ///                      0b01111110,
+    ///                      0b00111100];
+    /// vdp.set_sprite_pattern(0, &ball).unwrap();
+    /// # }
+    /// ```
+    pub fn set_sprite_pattern(&mut self, index: u8, pattern: &[u8]) -> Result<(), TMS9918AError> {
+        let offset = index as usize * 8;
+        Self::check_table_range(self.vdp_sprite_pattern_offset, SPRITE_PATTERN_TABLE_SIZE, offset, pattern.len())?;
+        let base = self.vdp_sprite_pattern_offset as usize + offset;
+        for (i, data) in pattern.iter().enumerate() {
+            self.write_ram(base + i, *data);
+        }
+        Ok(())
     }
 
     /// Write to the TMS9918A control port
     /// 
     /// This expects standard TMS9918A commands,
     /// see the [TMS9918A Data Manual](http://www.bitsavers.org/components/ti/TMS9900/TMS9918A_TMS9928A_TMS9929A_Video_Display_Processors_Data_Manual_Nov82.pdf) for details.
+    /// 
+    /// A second byte with bit 7 set is always a register write, even if bit 6 is also set.
+    /// 
+    /// Like the real hardware, the first byte of a command immediately replaces the low byte of the address pointer.
+    /// 
+    /// No sequence of bytes written to or read from the ports can panic, addresses wrap around at 16K and every
+    /// register value selects a defined video mode and table addresses. The `fuzz` directory has a fuzz target
+    /// checking this with `cargo fuzz run ports`.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// // write 0x04 to register 7
+    /// vdp.write_control_port(0x04);
+    /// vdp.write_control_port(0x87);
+    /// assert_eq!(vdp.read_register(7), 0x04);
+    /// ```
     pub fn write_control_port(&mut self, data: u8) {
-        if self.vdp_first_byte_saved_flag == false {
+        #[cfg(feature = "std")]
+        self.trace_event(trace::TraceEvent::WriteControl(data));
+        if !self.vdp_first_byte_saved_flag {
             // this is the first byte of the command, save it
+            // the real hardware also immediately replaces the low byte of the address pointer with it
             self.vdp_temp_data = data;
+            self.vdp_addr_pointer = (self.vdp_addr_pointer & 0x3F00) | data as u16;
             self.vdp_first_byte_saved_flag = true;
         } else {
             // this is the second byte of the command, execute the command
-            if (data & (1 << 7) != 0) && (data & (1 << 6) == 0) {
-                // bit 7 is set and bit 6 is clear, this is a write a register
+            if data & (1 << 7) != 0 {
+                // bit 7 is set, this is a write to a register
+                // bit 6 is ignored, like on the real hardware
                 let register = data & 0b00000111;
                 let register_value = self.vdp_temp_data;
                 self.write_register(register, register_value);
@@ -568,33 +4109,888 @@ impl TMS9918A {
                 // bit 7 is clear and bit 6 is clear, this is a read from memory
                 let address = ((data as u16 & 0b00111111) << 8) | (self.vdp_temp_data as u16 & 0x00FF);
                 self.vdp_addr_pointer = address;
-                self.vdp_read_ahead = self.read_ram(address as usize);
+                self.vdp_read_ahead = self.read_ram(self.port_address(address));
                 self.vdp_first_byte_saved_flag = false;
-                return;
             }
         }
     }
 
+    /// Write to the TMS9918A as seen from the CPU bus, selecting the port with the MODE pin
+    /// 
+    /// When `mode` is true (MODE pin high), this writes to the control port.
+    /// When `mode` is false (MODE pin low), this writes to the data port.
+    /// 
+    /// On most systems the MODE pin is connected to the lowest address line, so the data port is
+    /// at the even address and the control port is at the odd address.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// // write 0x04 to register 7
+    /// vdp.io_write(true, 0x04);
+    /// vdp.io_write(true, 0x87);
+    /// assert_eq!(vdp.read_register(7), 0x04);
+    /// ```
+    #[inline]
+    pub fn io_write(&mut self, mode: bool, data: u8) {
+        if mode {
+            self.write_control_port(data);
+        } else {
+            self.write_data_port(data);
+        }
+    }
+
+    /// Read from the TMS9918A as seen from the CPU bus, selecting the port with the MODE pin
+    /// 
+    /// When `mode` is true (MODE pin high), this reads the status register.
+    /// When `mode` is false (MODE pin low), this reads from the data port.
+    #[inline]
+    pub fn io_read(&mut self, mode: bool) -> u8 {
+        if mode {
+            self.read_status_port()
+        } else {
+            self.read_data_port()
+        }
+    }
+
+    /// Set the address pointer for reading from or writing to video memory through the data port
+    /// 
+    /// This writes the same two bytes to the control port that a program running on real hardware would,
+    /// so when reading, the read-ahead register is loaded from the new address.
+    /// 
+    /// Returns an error if the address is above 0x3FFF.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::{TMS9918A, AccessDirection};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.write_register(1, 0x80);
+    /// vdp.set_vram_address(0x0800, AccessDirection::Write).unwrap();
+    /// vdp.write_data_block(&[1, 2, 3]);
+    /// vdp.set_vram_address(0x0801, AccessDirection::Read).unwrap();
+    /// assert_eq!(vdp.read_data_port(), 2);
+    /// assert!(vdp.set_vram_address(0x4000, AccessDirection::Read).is_err());
+    /// ```
+    pub fn set_vram_address(&mut self, address: u16, direction: AccessDirection) -> Result<(), TMS9918AError> {
+        if address > 0x3FFF {
+            return Err(TMS9918AError::AddressOutOfRange(address as usize));
+        }
+        // make sure the low byte is treated as the first byte of the command
+        self.vdp_first_byte_saved_flag = false;
+        let high_byte = (address >> 8) as u8;
+        self.write_control_port(address as u8);
+        match direction {
+            AccessDirection::Read => self.write_control_port(high_byte),
+            AccessDirection::Write => self.write_control_port(high_byte | 0x40)
+        }
+        Ok(())
+    }
+
+    /// Read from the TMS9918A status register
+    ///
+    /// Bit 7 is the frame flag, which is set at the end of each frame by [`update`](TMS9918A::update).
+    ///
+    /// Bit 6 is the fifth sprite flag, which is set when more than four sprites occupy the same line.
+    /// Bits 0-4 contain the number of the fifth sprite on the first such line.
+    ///
+    /// Bit 5 is the coincidence flag, which is set when two sprites overlap on screen.
+    ///
+    /// Reading the status register clears the flags, and resets the control port
+    /// so the next byte written to it is treated as the first byte of a command.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.update();
+    /// // the frame flag is set at the end of each frame, and cleared by the read
+    /// assert_eq!(vdp.read_status_port() & 0x80, 0x80);
+    /// assert_eq!(vdp.read_status_port() & 0x80, 0);
+    /// ```
+    pub fn read_status_port(&mut self) -> u8 {
+        #[cfg(feature = "std")]
+        self.trace_event(trace::TraceEvent::ReadStatus);
+        self.vdp_first_byte_saved_flag = false;
+        let status = self.vdp_status;
+        self.vdp_status = 0;
+        status
+    }
+
+    /// Check the state of the TMS9918A interrupt output
+    ///
+    /// The interrupt output is asserted while the frame flag in the status register is set
+    /// and interrupts are enabled by bit 5 of register 1. Reading the status register clears the frame flag,
+    /// which deasserts the interrupt output.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.update();
+    /// assert!(!vdp.interrupt_pending());
+    /// // enabling interrupts while the frame flag is set asserts the interrupt output immediately
+    /// vdp.write_register(1, 0x20);
+    /// assert!(vdp.interrupt_pending());
+    /// vdp.read_status_port();
+    /// assert!(!vdp.interrupt_pending());
+    /// ```
+    #[inline]
+    pub fn interrupt_pending(&self) -> bool {
+        (self.vdp_status & (1 << 7) != 0) && self.reg1_flags().contains(Reg1Flags::IE)
+    }
+
     /// Write to the TMS9918A data port
     /// 
-    /// This follows the standard TMS9918A behavior of incrementing the addr. pointer after each write,
+    /// This follows the standard TMS9918A behavior of incrementing the addr. pointer after each write, wrapping around at 0x3FFF,
     /// see the [TMS9918A Data Manual](http://www.bitsavers.org/components/ti/TMS9900/TMS9918A_TMS9928A_TMS9929A_Video_Display_Processors_Data_Manual_Nov82.pdf) for details.
+    /// 
+    /// Bit 7 of register 1 selects between 4K and 16K of video memory. When it is clear (4K, the default after reset),
+    /// the data port only addresses the first 4K of video memory, and higher addresses alias it. The display reads its
+    /// tables through the same mapping.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// // 4K mode, writing to 0x1000 writes to 0x0000
+    /// vdp.write_register(1, 0x00);
+    /// vdp.write_control_port(0x00);
+    /// vdp.write_control_port(0x50); // 0x10 | 0x40
+    /// vdp.write_data_port(0xAA);
+    /// vdp.write_control_port(0x00);
+    /// vdp.write_control_port(0x00);
+    /// assert_eq!(vdp.read_data_port(), 0xAA);
+    /// assert_eq!(vdp.read_ram(0x0000), 0xAA);
+    /// 
+    /// // 16K mode, writing to 0x1000 writes to 0x1000
+    /// vdp.write_register(1, 0x80);
+    /// vdp.write_control_port(0x00);
+    /// vdp.write_control_port(0x50);
+    /// vdp.write_data_port(0x55);
+    /// assert_eq!(vdp.read_ram(0x1000), 0x55);
+    /// assert_eq!(vdp.read_ram(0x0000), 0xAA);
+    /// ```
     pub fn write_data_port(&mut self, data: u8) {
+        #[cfg(feature = "std")]
+        self.trace_event(trace::TraceEvent::WriteData(data));
         self.vdp_first_byte_saved_flag = false;
-        let address = self.vdp_addr_pointer;
-        self.write_ram(address as usize, data);
-        self.vdp_addr_pointer += 1;
+        let address = self.port_address(self.vdp_addr_pointer);
+        self.write_ram(address, data);
+        self.vdp_addr_pointer = (self.vdp_addr_pointer + 1) & 0x3FFF;
     }
 
     /// Read from the TMS9918A data port
     /// 
-    /// This follows the standard TMS9918A behavior of incrementing the addr. pointer after each read,
+    /// This follows the standard TMS9918A behavior of incrementing the addr. pointer after each read, wrapping around at 0x3FFF,
     /// see the [TMS9918A Data Manual](http://www.bitsavers.org/components/ti/TMS9900/TMS9918A_TMS9928A_TMS9929A_Video_Display_Processors_Data_Manual_Nov82.pdf) for details.
+    /// 
+    /// Like [`write_data_port`](TMS9918A::write_data_port), only the first 4K of video memory is addressed
+    /// when bit 7 of register 1 is clear.
     pub fn read_data_port(&mut self) -> u8 {
+        #[cfg(feature = "std")]
+        self.trace_event(trace::TraceEvent::ReadData);
         self.vdp_first_byte_saved_flag = false;
         let data = self.vdp_read_ahead;
-        self.vdp_addr_pointer += 1;
-        self.vdp_read_ahead = self.read_ram(self.vdp_addr_pointer as usize);
+        self.vdp_addr_pointer = (self.vdp_addr_pointer + 1) & 0x3FFF;
+        self.vdp_read_ahead = self.read_ram(self.port_address(self.vdp_addr_pointer));
         data
     }
+
+    /// Write a block of bytes to the TMS9918A data port
+    /// 
+    /// This behaves exactly like calling [`write_data_port`](TMS9918A::write_data_port) for each byte,
+    /// including incrementing and wrapping around the address pointer, but is much faster for large blocks.
+    /// The address pointer must be set through the control port first.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.write_register(1, 0x80);
+    /// // write to 0x0800
+    /// vdp.write_control_port(0x00);
+    /// vdp.write_control_port(0x48);
+    /// vdp.write_data_block(&[1, 2, 3]);
+    /// assert_eq!(vdp.read_ram(0x0802), 3);
+    /// ```
+    pub fn write_data_block(&mut self, data: &[u8]) {
+        #[cfg(feature = "std")]
+        self.trace_event(trace::TraceEvent::WriteDataBlock(data));
+        self.vdp_first_byte_saved_flag = false;
+        let mut data = data;
+        while !data.is_empty() {
+            let address = self.port_address(self.vdp_addr_pointer);
+            let length = data.len().min(self.port_run_length(self.vdp_addr_pointer));
+            self.vdp_ram[address..address + length].copy_from_slice(&data[..length]);
+            self.mark_vram_written(address, length);
+            self.vdp_addr_pointer = ((self.vdp_addr_pointer as usize + length) & 0x3FFF) as u16;
+            data = &data[length..];
+        }
+    }
+
+    /// Read a block of bytes from the TMS9918A data port
+    /// 
+    /// This behaves exactly like calling [`read_data_port`](TMS9918A::read_data_port) for each byte,
+    /// including the read-ahead behavior and incrementing and wrapping around the address pointer,
+    /// but is much faster for large blocks. The address pointer must be set through the control port first.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.write_register(1, 0x80);
+    /// // read 6K starting at 0x3000, wrapping around to 0x0000
+    /// vdp.write_control_port(0x00);
+    /// vdp.write_control_port(0x30);
+    /// let mut block = [0; 0x1800];
+    /// vdp.read_data_block(&mut block);
+    /// assert_eq!(&block[..0x1000], &vdp.vram()[0x3000..0x4000]);
+    /// assert_eq!(&block[0x1000..], &vdp.vram()[0x0000..0x0800]);
+    /// assert_eq!(vdp.read_data_port(), vdp.vram()[0x0800]);
+    /// ```
+    pub fn read_data_block(&mut self, data: &mut [u8]) {
+        #[cfg(feature = "std")]
+        self.trace_event(trace::TraceEvent::ReadDataBlock(data.len()));
+        self.vdp_first_byte_saved_flag = false;
+        if data.is_empty() {
+            return;
+        }
+        // the first byte comes from the read-ahead register
+        data[0] = self.vdp_read_ahead;
+        self.vdp_addr_pointer = (self.vdp_addr_pointer + 1) & 0x3FFF;
+        let mut data = &mut data[1..];
+        while !data.is_empty() {
+            let address = self.port_address(self.vdp_addr_pointer);
+            let length = data.len().min(self.port_run_length(self.vdp_addr_pointer));
+            data[..length].copy_from_slice(&self.vdp_ram[address..address + length]);
+            self.vdp_addr_pointer = ((self.vdp_addr_pointer as usize + length) & 0x3FFF) as u16;
+            data = &mut data[length..];
+        }
+        self.vdp_read_ahead = self.read_ram(self.port_address(self.vdp_addr_pointer));
+    }
+
+    // get the mask of the video memory addresses accessible with the 4K/16K selection bit
+    fn address_mask(&self) -> usize {
+        if self.reg1_flags().contains(Reg1Flags::MEM_16K) { 0x3FFF } else { 0x0FFF }
+    }
+
+    // get the number of bytes the data port can access contiguously starting at an address pointer value
+    fn port_run_length(&self, address: u16) -> usize {
+        let mask = self.address_mask();
+        mask + 1 - (address as usize & mask)
+    }
+
+    // get the video memory address accessed by the data port for an address pointer value
+    //
+    // when the 4K/16K selection bit is clear, only the first 4K of video memory is addressed
+    fn port_address(&self, address: u16) -> usize {
+        address as usize & self.address_mask()
+    }
+
+    // get the video memory address the display reads a table from
+    //
+    // the display fetches go through the same address mapping as the data port, so in 4K mode every table is read
+    // from the first 4K of video memory. The tables never cross a 4K boundary, so folding their base address is enough
+    fn display_address(&self, table_offset: u16) -> usize {
+        table_offset as usize & self.address_mask()
+    }
+}
+
+/// The data port is at even addresses, and the control port and status register are at odd addresses,
+/// matching systems that connect the MODE pin to the lowest address line.
+/// 
+/// # Examples
+/// 
+/// ```
+/// # use tms9918a_emu::{TMS9918A, IoDevice};
+/// let mut devices: Vec<Box<dyn IoDevice>> = vec![Box::new(TMS9918A::new())];
+/// // ColecoVision VDP ports: data at 0xBE, control at 0xBF
+/// devices[0].write(0xBF, 0x80);
+/// devices[0].write(0xBF, 0x81);
+/// devices[0].write(0xBF, 0x00);
+/// devices[0].write(0xBF, 0x40);
+/// devices[0].write(0xBE, 0xAA);
+/// devices[0].write(0xBF, 0x00);
+/// devices[0].write(0xBF, 0x00);
+/// assert_eq!(devices[0].read(0xBE), 0xAA);
+/// ```
+impl IoDevice for TMS9918A {
+    #[inline]
+    fn read(&mut self, port: u16) -> u8 {
+        self.io_read(port & 1 != 0)
+    }
+
+    #[inline]
+    fn write(&mut self, port: u16, data: u8) {
+        self.io_write(port & 1 != 0, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clone_is_equal_until_changed() {
+        let mut vdp = TMS9918A::new();
+        vdp.write_register(7, 0xF4);
+        vdp.print_text(0, 0, "Hello");
+        vdp.render();
+        vdp.write_control_port(0x00);
+        vdp.write_control_port(0x40);
+        let mut copy = vdp.clone();
+        assert!(copy == vdp);
+
+        vdp.write_data_port(0x55);
+        assert!(copy != vdp);
+
+        copy.write_data_port(0x55);
+        assert!(copy == vdp);
+    }
+
+    #[test]
+    fn sprites_are_clipped_at_right_and_bottom_edges() {
+        let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+        vdp.write_register(5, 0x20); // sprite attribute table at 0x1000
+        vdp.write_register(6, 0x03); // sprite pattern table at 0x1800
+        vdp.write_register(7, 0x01);
+        // solid 16x16 sprite pattern 0
+        for i in 0..32 {
+            vdp.write_ram(0x1800 + i, 0xFF);
+        }
+        // white sprite 0 at the bottom right corner, white sprite 1 at the top left corner
+        for (i, &byte) in [183, 248, 0, 0x0F, 0xFF, 0, 0, 0x0F, 0xD0].iter().enumerate() {
+            vdp.write_ram(0x1000 + i, byte);
+        }
+
+        // (size bit, magnification bit, sprite size in pixels)
+        for &(size, mag, pixels) in &[(0, 0, 8), (0, 1, 16), (1, 0, 16), (1, 1, 32)] {
+            vdp.write_register(1, 0xC0 | (size << 1) | mag);
+            vdp.update();
+            let white = vdp.frame.iter().filter(|&&p| p == 0xFFFFFF).count();
+            // only the top left 8x8 pixels of sprite 0 are on screen
+            assert_eq!(white, (pixels * pixels) + 64);
+            // nothing wraps around to the left edge
+            assert_eq!(vdp.frame[184 * 256], 0x000000);
+        }
+    }
+
+    #[test]
+    fn early_clock_shifts_sprites_left() {
+        let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+        vdp.write_register(1, 0xC0);
+        vdp.write_register(5, 0x20); // sprite attribute table at 0x1000
+        vdp.write_register(6, 0x03); // sprite pattern table at 0x1800
+        vdp.write_register(7, 0x01);
+        for sprite in 0..32 {
+            vdp.write_ram(0x1000 + (sprite * 4), 192);
+        }
+        // solid white 8x8 sprite with the early clock bit set
+        for i in 0..8 {
+            vdp.write_ram(0x1800 + i, 0xFF);
+        }
+        vdp.write_ram(0x1000, 100);
+        vdp.write_ram(0x1003, 0x8F);
+
+        for x in 0..40 {
+            vdp.write_ram(0x1001, x);
+            vdp.update();
+            let white = vdp.frame.iter().filter(|&&p| p == 0xFFFFFF).count();
+            let columns = (x as usize + 8).saturating_sub(32).min(8);
+            assert_eq!(white, columns * 8);
+        }
+    }
+
+    #[test]
+    fn framebuffer_holds_active_display_area() {
+        let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+        vdp.write_register(2, 0x01); // name table at 0x0400
+        vdp.write_register(3, 0x80); // color table at 0x2000
+        vdp.write_register(4, 0x01); // pattern table at 0x0800
+        // solid pattern 1 in white on black, used only by tile row 23
+        for i in 0..8 {
+            vdp.write_ram(0x0808 + i, 0xFF);
+        }
+        vdp.write_ram(0x2000, 0xF1);
+        for i in 0..32 {
+            vdp.write_ram(0x0400 + 23 * 32 + i, 1);
+        }
+        vdp.write_register(1, 0x80);
+        vdp.enable_video(true);
+        vdp.render();
+
+        assert_eq!(vdp.frame.len(), 256 * 192);
+        assert_eq!(vdp.frame_height(), 192);
+        assert!(vdp.frame[256 * 184..].iter().all(|&p| p == 0xFFFFFF));
+        assert!(vdp.frame[..256 * 184].iter().all(|&p| p == 0x000000));
+    }
+
+    #[test]
+    fn transparent_color_shows_backdrop() {
+        let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+        vdp.write_register(2, 0x01); // name table at 0x0400
+        vdp.write_register(3, 0x80); // color table at 0x2000
+        vdp.write_register(4, 0x01); // pattern table at 0x0800
+        // light blue foreground on a transparent background, white backdrop
+        vdp.write_ram(0x2000, 0x50);
+        vdp.write_register(7, 0x0F);
+        vdp.enable_video(true);
+        vdp.render();
+        assert!(vdp.frame.iter().all(|&p| p == 0xFFFFFF));
+    }
+
+    #[test]
+    fn render_into_checks_buffer_size() {
+        let mut vdp = TMS9918A::new();
+        let mut surface = vec![0; 320 * 200];
+        assert!(vdp.render_into(&mut surface, 320, 64, 8).is_ok());
+        assert_eq!(vdp.render_into(&mut surface, 320, 65, 8), Err(TMS9918AError::StrideTooSmall(321)));
+        assert_eq!(vdp.render_into(&mut surface, 320, 64, 9), Err(TMS9918AError::BufferTooSmall(320 * 201)));
+    }
+
+    #[test]
+    fn text_mode_is_padded_with_backdrop() {
+        let mut vdp = TMS9918A::with_vram_init(VramInit::Fill(0xFF));
+        vdp.set_video_mode(VideoMode::Text);
+        vdp.enable_video(true);
+        // solid white tiles with a dark blue backdrop
+        vdp.write_register(7, 0xF4);
+        vdp.update();
+        assert_eq!(vdp.frame_width(), 256);
+        for row in vdp.frame.chunks(256) {
+            assert!(row[..8].iter().all(|&p| p == 0x5455ED));
+            assert!(row[8..248].iter().all(|&p| p == 0xFFFFFF));
+            assert!(row[248..].iter().all(|&p| p == 0x5455ED));
+        }
+    }
+
+    #[test]
+    fn status_read_discards_half_written_command() {
+        let mut vdp = TMS9918A::new();
+        vdp.write_control_port(0x12);
+        vdp.read_status_port();
+        vdp.write_control_port(0x04);
+        vdp.write_control_port(0x87);
+        assert_eq!(vdp.read_register(7), 0x04);
+    }
+
+    #[test]
+    fn warm_reset_matches_new_vdp() {
+        let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+        vdp.set_video_mode(VideoMode::Text);
+        vdp.set_name_table_multiplier(2);
+        vdp.set_pattern_table_multiplier(1);
+        vdp.write_register(7, 0xF4);
+        vdp.print_text(0, 0, "Hello");
+        vdp.update();
+        vdp.write_control_port(0x12);
+
+        vdp.warm_reset();
+        let mut fresh = TMS9918A::with_vram_init(VramInit::Zero);
+        fresh.load_vram_bytes(vdp.vram()).unwrap();
+        vdp.render();
+        fresh.render();
+        assert!(vdp == fresh);
+    }
+
+    #[test]
+    fn write_ram_wraps_at_16k() {
+        let mut vdp = TMS9918A::new();
+        vdp.write_ram(0xFFFF, 0x55);
+        assert_eq!(vdp.read_ram(0x3FFF), 0x55);
+        vdp.write_ram(0x4001, 0xAA);
+        assert_eq!(vdp.read_ram(0x0001), 0xAA);
+    }
+
+    #[test]
+    fn name_table_size_depends_on_mode() {
+        let mut vdp = TMS9918A::new();
+        // the Graphics I name table has 768 entries, Text mode has 960
+        assert_eq!(vdp.write_name_table(800, 1), Err(TMS9918AError::OutOfRange { end: 801, size: 768 }));
+        assert!(vdp.fill_name_table(&[1, 2, 3], 766, 3).is_err());
+        vdp.set_video_mode(VideoMode::Text);
+        assert!(vdp.write_name_table(800, 1).is_ok());
+        assert!(vdp.fill_name_table(&[1, 2, 3], 957, 3).is_ok());
+    }
+
+    #[test]
+    fn print_text_clips_or_wraps() {
+        let mut vdp = TMS9918A::new();
+        vdp.set_video_mode(VideoMode::Text);
+        // clipped at the right edge
+        assert_eq!(vdp.print_text(36, 2, "Hello"), 4);
+
+        // a font starting at the space character
+        vdp.set_charmap_offset(b' ');
+        vdp.set_text_wrap(true);
+        assert_eq!(vdp.print_text(36, 2, "Hello"), 5);
+        assert_eq!(vdp.read_name_table_xy(0, 3), Ok(b'o' - b' '));
+
+        // always clipped at the bottom of the screen
+        assert_eq!(vdp.print_text(38, 23, "Hello"), 2);
+    }
+
+    #[test]
+    fn sprite_vertical_position_and_terminator() {
+        let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+        vdp.write_register(1, 0xC0);
+        vdp.write_register(5, 0x20); // sprite attribute table at 0x1000
+        vdp.write_register(6, 0x03); // sprite pattern table at 0x1800
+        vdp.write_register(7, 0x01);
+        vdp.set_sprite_pattern(0, &[0xFF; 8]).unwrap();
+        let first_row = |vdp: &TMS9918A| (0..192).find(|&line| vdp.frame[line * 256 + 100] == 0xFFFFFF);
+
+        // a vertical position of 0xFF is the first line of the screen
+        vdp.define_sprite(0, SpriteDef { x: 100, y: 0xFF, pattern: 0, color: 0xF, early_clock: false }).unwrap();
+        vdp.write_ram(0x1004, 0xD0);
+        vdp.update();
+        assert_eq!(first_row(&vdp), Some(0));
+
+        vdp.move_sprite(0, 100, 0x00).unwrap();
+        vdp.update();
+        assert_eq!(first_row(&vdp), Some(1));
+
+        // sprites after the terminator are not displayed
+        vdp.define_sprite(0, SpriteDef { x: 100, y: 0xD0, pattern: 0, color: 0xF, early_clock: false }).unwrap();
+        vdp.define_sprite(1, SpriteDef { x: 100, y: 50, pattern: 0, color: 0xF, early_clock: false }).unwrap();
+        vdp.update();
+        assert_eq!(first_row(&vdp), None);
+    }
+
+    #[test]
+    fn second_byte_with_bits_7_and_6_set_writes_register() {
+        let mut vdp = TMS9918A::new();
+        vdp.write_control_port(0x04);
+        vdp.write_control_port(0xC7);
+        assert_eq!(vdp.read_register(7), 0x04);
+        // and the following commands are still interpreted correctly
+        vdp.write_control_port(0x0F);
+        vdp.write_control_port(0x87);
+        assert_eq!(vdp.read_register(7), 0x0F);
+    }
+
+    #[test]
+    fn first_control_byte_replaces_address_low_byte() {
+        let mut vdp = TMS9918A::new();
+        vdp.write_register(1, 0x80);
+        // set the address pointer to 0x0100 for writing
+        vdp.write_control_port(0x00);
+        vdp.write_control_port(0x41);
+        // a single byte changes the low byte of the address pointer to 0x05
+        vdp.write_control_port(0x05);
+        vdp.write_data_port(0xAA);
+        assert_eq!(vdp.read_ram(0x0105), 0xAA);
+    }
+
+    // a VDP with every sprite below the screen, sprite pattern 0 is a single pixel
+    fn single_pixel_sprites() -> TMS9918A {
+        let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+        vdp.write_register(1, 0xC0);
+        vdp.write_register(5, 0x20); // sprite attribute table at 0x1000
+        vdp.write_register(6, 0x03); // sprite pattern table at 0x1800
+        vdp.write_ram(0x1800, 0x80);
+        for sprite in 0..32 {
+            vdp.write_ram(0x1000 + (sprite * 4), 192);
+        }
+        vdp
+    }
+
+    #[test]
+    fn coincidence_flag() {
+        let mut vdp = single_pixel_sprites();
+        vdp.write_ram(0x1000, 0);
+        vdp.write_ram(0x1004, 0);
+
+        // two sprites next to each other don't collide
+        vdp.write_ram(0x1001, 10);
+        vdp.write_ram(0x1005, 11);
+        vdp.update();
+        assert_eq!(vdp.read_status_port() & 0x20, 0);
+
+        // two sprites on top of each other collide, even though both are transparent
+        vdp.write_ram(0x1005, 10);
+        vdp.update();
+        assert_eq!(vdp.read_status_port() & 0x20, 0x20);
+        // the flag was cleared by the read
+        assert_eq!(vdp.read_status_port() & 0x20, 0);
+    }
+
+    #[test]
+    fn fifth_sprite_flag() {
+        let mut vdp = single_pixel_sprites();
+        // six sprites on the same line report sprite 4 as the fifth sprite
+        for sprite in 0..6 {
+            vdp.write_ram(0x1000 + (sprite * 4), 100);
+            vdp.write_ram(0x1001 + (sprite * 4), sprite as u8 * 16);
+        }
+        vdp.update();
+        assert_eq!(vdp.read_status_port() & 0x5F, 0x40 | 4);
+    }
+
+    #[test]
+    fn data_port_address_wraps_at_16k() {
+        let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+        vdp.write_register(1, 0x80);
+        vdp.write_control_port(0x00);
+        vdp.write_control_port(0x40);
+        for _ in 0..0x4000 {
+            vdp.write_data_port(0x00);
+        }
+        vdp.write_data_port(0xFF);
+        assert_eq!(vdp.read_ram(0x0000), 0xFF);
+        assert_eq!(vdp.read_ram(0x0001), 0x00);
+    }
+
+    #[test]
+    fn data_block_matches_data_port() {
+        let data: Vec<u8> = (0..20000).map(|i| (i * 7) as u8).collect();
+        // in 16K and 4K mode
+        for &reg1 in &[0x80, 0x00] {
+            let mut block = TMS9918A::with_vram_init(VramInit::Zero);
+            let mut bytes = TMS9918A::with_vram_init(VramInit::Zero);
+            for vdp in [&mut block, &mut bytes].iter_mut() {
+                vdp.write_register(1, reg1);
+                vdp.write_control_port(0x34);
+                vdp.write_control_port(0x52);
+            }
+            block.write_data_block(&data);
+            for byte in data.iter() {
+                bytes.write_data_port(*byte);
+            }
+            assert!(block == bytes);
+        }
+    }
+
+    #[test]
+    fn sprite_functions_check_ranges() {
+        let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+        vdp.write_register(5, 0x20); // sprite attribute table at 0x1000
+        vdp.write_register(6, 0x03); // sprite pattern table at 0x1800
+        let sprite = SpriteDef { x: 0, y: 0, pattern: 0, color: 0xF, early_clock: false };
+        assert!(vdp.define_sprite(31, sprite).is_ok());
+        assert_eq!(vdp.define_sprite(32, sprite), Err(TMS9918AError::OutOfRange { end: 132, size: 128 }));
+        assert!(vdp.move_sprite(32, 0, 0).is_err());
+        assert!(vdp.hide_sprite(255).is_err());
+
+        // the last 16x16 pattern fits, one more byte doesn't
+        assert!(vdp.set_sprite_pattern(252, &[0xFF; 32]).is_ok());
+        assert_eq!(vdp.set_sprite_pattern(252, &[0xFF; 33]), Err(TMS9918AError::OutOfRange { end: 2049, size: 2048 }));
+        assert_eq!(vdp.read_ram(0x2000), 0x00);
+
+        // a sprite pattern table at 0x3800 ends at the end of video memory
+        vdp.write_register(6, 0x07);
+        assert!(vdp.set_sprite_pattern(255, &[0xFF; 8]).is_ok());
+        assert_eq!(vdp.read_ram(0x3FFF), 0xFF);
+    }
+
+    #[test]
+    fn draw_box_far_off_screen_does_not_overflow() {
+        let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+        let tiles = BoxTiles { corners: [1; 4], edges: [2; 4] };
+        vdp.draw_box(usize::MAX, usize::MAX, 5, 5, tiles);
+        vdp.draw_box(usize::MAX - 1, 0, usize::MAX, usize::MAX, tiles);
+        assert!((0..768).all(|i| vdp.read_name_table(i) == 0));
+    }
+
+    #[test]
+    fn scroll_by_extreme_distances() {
+        let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+        vdp.write_name_table_xy(0, 0, b'A').unwrap();
+
+        // isize::MIN is a multiple of 32, and 16 more than a multiple of 24
+        vdp.scroll_name_table(isize::MIN, isize::MIN, ScrollFill::Wrap);
+        assert_eq!(vdp.read_name_table_xy(0, 16), Ok(b'A'));
+        vdp.scroll_name_table(isize::MAX, 0, ScrollFill::Wrap);
+        assert_eq!(vdp.read_name_table_xy(31, 16), Ok(b'A'));
+
+        vdp.scroll_name_table(isize::MIN, 0, ScrollFill::Tile(b' '));
+        assert!((0..768).all(|i| vdp.read_name_table(i) == b' '));
+    }
+
+    // xorshift pseudo-random number generator, so the property tests are reproducible
+    fn xorshift(state: &mut u32) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
+
+    #[test]
+    fn random_port_accesses_never_panic() {
+        // accesses to every port, including mode combinations and table addresses past 16K
+        for seed in 1..=10 {
+            let mut vdp = TMS9918A::new();
+            let mut state = seed;
+            for i in 0..50_000 {
+                let random = xorshift(&mut state);
+                let data = random as u8;
+                match random >> 29 {
+                    0..=2 => vdp.write_control_port(data),
+                    3 => vdp.write_data_port(data),
+                    4 => vdp.write_data_block(&[data; 300]),
+                    5 => {
+                        vdp.read_data_port();
+                        vdp.read_data_block(&mut [0; 300]);
+                    },
+                    6 => { vdp.read_status_port(); },
+                    _ => vdp.write_register(data, (random >> 8) as u8)
+                }
+                if i % 5_000 == 0 {
+                    vdp.render();
+                }
+            }
+        }
+    }
+
+    // a VDP in Graphics I mode with white text on dark blue, with its first frame rendered
+    fn rendered_text_screen() -> TMS9918A {
+        let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+        vdp.set_pattern_table_multiplier(1); // pattern table at 0x0800
+        vdp.set_color_table_multiplier(0x80); // color table at 0x2000
+        for group in 0..32 {
+            vdp.write_ram(0x2000 + group, 0xF4);
+        }
+        for (i, byte) in (0..2048).map(|i| (i * 37 % 251) as u8).enumerate() {
+            vdp.write_ram(0x0800 + i, byte);
+        }
+        vdp.print_text(0, 0, "Hello, world!");
+        vdp.write_register(1, 0x80);
+        vdp.enable_video(true);
+        vdp.render();
+        vdp
+    }
+
+    #[test]
+    fn only_tiles_using_written_memory_are_drawn() {
+        let mut vdp = rendered_text_screen();
+        vdp.render();
+        assert_eq!(vdp.tile_lines_drawn(), 0);
+
+        // a name table entry draws one tile again
+        vdp.write_name_table_xy(5, 5, b'x').unwrap();
+        vdp.render();
+        assert_eq!(vdp.tile_lines_drawn(), 8);
+
+        // one row of a pattern draws that row of every tile using it, 'l' is used 3 times
+        vdp.write_ram(0x0800 + b'l' as usize * 8 + 2, 0x81);
+        vdp.render();
+        assert_eq!(vdp.tile_lines_drawn(), 3);
+
+        // a color table entry draws every tile of its group of 8 patterns, here the tiles using patterns 0-7
+        vdp.write_ram(0x2000, 0x1F);
+        vdp.render();
+        assert_eq!(vdp.tile_lines_drawn(), (768 - 14) * 8);
+
+        // a register change draws everything
+        vdp.write_register(7, 0x05);
+        vdp.render();
+        assert_eq!(vdp.tile_lines_drawn(), 768 * 8);
+    }
+
+    #[test]
+    fn vram_mut_draws_every_tile_again() {
+        let mut vdp = rendered_text_screen();
+        let before = vdp.frame.clone();
+        vdp.vram_mut()[0x0800 + b'H' as usize * 8] ^= 0xFF;
+        vdp.render();
+        assert_eq!(vdp.tile_lines_drawn(), 768 * 8);
+        assert!(vdp.frame != before);
+    }
+
+    #[test]
+    fn cached_tiles_match_a_full_redraw() {
+        let mut vdp = rendered_text_screen();
+        let mut state = 7;
+        for frame in 0..200 {
+            // a few random writes to the name, pattern and color tables each frame
+            for _ in 0..(frame % 5) {
+                let random = xorshift(&mut state);
+                let address = match random % 3 {
+                    0 => (random >> 8) as usize % 768,
+                    1 => 0x0800 + (random >> 8) as usize % 2048,
+                    _ => 0x2000 + (random >> 8) as usize % 32
+                };
+                vdp.write_ram(address, (random >> 20) as u8);
+            }
+            vdp.render();
+            let mut full = vdp.clone();
+            full.invalidate_tiles();
+            full.render();
+            assert!(vdp.frame == full.frame, "frame {} differs from a full redraw", frame);
+        }
+    }
+
+    #[test]
+    fn coincidence_ignores_sprites_after_the_fourth() {
+        for &limit in &[true, false] {
+            let mut vdp = single_pixel_sprites();
+            vdp.set_sprite_limit(limit);
+            // four sprites side by side, then the fifth and sixth on top of each other
+            for sprite in 0..6 {
+                vdp.write_ram(0x1000 + (sprite * 4), 100);
+                vdp.write_ram(0x1001 + (sprite * 4), [0, 10, 20, 30, 40, 40][sprite]);
+            }
+            vdp.update();
+            assert_eq!(vdp.read_status_port() & 0x60, 0x40);
+        }
+    }
+
+    #[test]
+    fn component_variants_start_with_the_component_palette() {
+        let mut vdp = TMS9918A::with_variant_and_vram_init(VdpVariant::Tms9928a, VramInit::Zero);
+        // dark blue backdrop with the display disabled
+        vdp.write_register(7, 0x04);
+        vdp.update();
+        assert_eq!(vdp.frame[0], 0x2B2DE3);
+
+        let mut vdp = TMS9918A::with_variant_and_vram_init(VdpVariant::Tms9918a, VramInit::Zero);
+        vdp.write_register(7, 0x04);
+        vdp.update();
+        assert_eq!(vdp.frame[0], 0x5455ED);
+    }
+
+    #[test]
+    fn display_reads_tables_from_the_first_4k_in_4k_mode() {
+        let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+        let write_address = |vdp: &mut TMS9918A, address: u16| {
+            vdp.write_control_port(address as u8);
+            vdp.write_control_port((address >> 8) as u8 | 0x40);
+        };
+        // 4K, display on, name table at 0x1000, color table at 0x1C00, pattern tables at 0x1800,
+        // sprite attribute table at 0x1F00
+        for (register, &data) in [0x00, 0x40, 0x04, 0x70, 0x03, 0x3E, 0x03, 0x04].iter().enumerate() {
+            vdp.write_control_port(data);
+            vdp.write_control_port(0x80 | register as u8);
+        }
+        // every tile uses the solid pattern 1 in white on dark blue
+        write_address(&mut vdp, 0x1000);
+        vdp.write_data_block(&[1; 768]);
+        write_address(&mut vdp, 0x1808);
+        vdp.write_data_block(&[0xFF; 8]);
+        write_address(&mut vdp, 0x1C00);
+        vdp.write_data_port(0xF4);
+        // a dark red sprite using the same solid pattern
+        write_address(&mut vdp, 0x1F00);
+        vdp.write_data_block(&[99, 100, 1, 0x06, 0xD0]);
+        vdp.render();
+
+        assert!(vdp.vram()[0x1000..].iter().all(|&byte| byte == 0));
+        assert_eq!(vdp.frame[0], 0xFFFFFF);
+        assert_eq!(vdp.frame[100 * 256 + 100], 0xD3524D);
+
+        // the display draws tiles again when their alias is written
+        write_address(&mut vdp, 0x1005);
+        vdp.write_data_port(0);
+        vdp.render();
+        assert_eq!(vdp.tile_lines_drawn(), 8);
+        assert_eq!(vdp.frame[40], 0x5455ED);
+    }
 }
@@ -0,0 +1,250 @@
+//! Streaming rendered frames to viewers over TCP, only built with the `net` feature
+//!
+//! [`TMS9918A::serve_frames`] sends every frame completed by [`update`](TMS9918A::update),
+//! [`render`](TMS9918A::render) or [`tick`](TMS9918A::tick) to the connected clients, and
+//! [`receive_frame`] reads them on the other end.
+//!
+//! Each frame is sent as a header followed by the pixels, with all numbers little endian:
+//!
+//! | Bytes | Contents |
+//! |-------|----------|
+//! | 2     | frame width in pixels |
+//! | 2     | frame height in pixels |
+//! | 1     | encoding, 0 for raw or 1 for run-length encoded |
+//! | 4     | length of the pixel data in bytes |
+//!
+//! Raw pixel data is one `u32` per pixel in the framebuffer's `0x00RRGGBB` format, row by row from the top left.
+//! Run-length encoded pixel data is a list of runs, each a `u16` count followed by the `u32` color repeated
+//! that many times. The server sends whichever encoding is smaller.
+
+use crate::{TMS9918A, TMS9918AError};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const ENCODING_RAW: u8 = 0;
+const ENCODING_RLE: u8 = 1;
+// size of the frame header in bytes
+const HEADER_SIZE: usize = 9;
+// time between checks for new connections and for stopping
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
+// a frame encoded with its header, shared by all clients
+type EncodedFrame = Arc<Vec<u8>>;
+
+// server state kept in the VDP while frames are being served
+pub(crate) struct FrameServer {
+    // one sender for each connected client
+    clients: Arc<Mutex<Vec<SyncSender<EncodedFrame>>>>,
+    stop: Arc<AtomicBool>
+}
+
+impl Drop for FrameServer {
+    fn drop(&mut self) {
+        // the accept thread stops, and clients disconnect once their senders are dropped
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl TMS9918A {
+    /// Start sending rendered frames to clients connecting to a TCP address
+    ///
+    /// Every frame completed by [`update`](TMS9918A::update), [`render`](TMS9918A::render) or [`tick`](TMS9918A::tick)
+    /// is sent to all connected clients, see the [`net`](crate::net) module for the format. Any number of clients can
+    /// connect and disconnect while frames are being served.
+    ///
+    /// Frames are sent from a separate thread for each client, so a slow client never blocks emulation. If a client
+    /// hasn't received the previous frame yet, the new frame is dropped for that client. A running server is stopped
+    /// first. Returns an error if the address can't be bound. This is only available with the `net` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tms9918a_emu::TMS9918A;
+    /// # fn main() -> Result<(), tms9918a_emu::TMS9918AError> {
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.serve_frames("0.0.0.0:7911")?;
+    /// loop {
+    ///     vdp.update();
+    ///     std::thread::sleep(vdp.frame_duration());
+    /// }
+    /// # }
+    /// ```
+    pub fn serve_frames<A: ToSocketAddrs>(&mut self, address: A) -> Result<(), TMS9918AError> {
+        self.stop_serving_frames();
+        let to_error = |error: std::io::Error| TMS9918AError::Io(error.to_string());
+        let listener = TcpListener::bind(address).map_err(to_error)?;
+        // poll for connections so the thread can notice when the server is stopped
+        listener.set_nonblocking(true).map_err(to_error)?;
+
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (thread_clients, thread_stop) = (clients.clone(), stop.clone());
+        thread::spawn(move || accept_clients(listener, thread_clients, thread_stop));
+        self.frame_server = Some(FrameServer { clients, stop });
+        Ok(())
+    }
+
+    /// Stop sending frames and disconnect all clients
+    ///
+    /// Nothing happens if no server is running.
+    pub fn stop_serving_frames(&mut self) {
+        self.frame_server = None;
+    }
+
+    /// Check if a server started by [`serve_frames`](TMS9918A::serve_frames) is running
+    #[inline]
+    pub fn is_serving_frames(&self) -> bool {
+        self.frame_server.is_some()
+    }
+
+    // send the completed frame to every client which is ready for it
+    pub(crate) fn serve_frame(&mut self) {
+        if let Some(server) = &self.frame_server {
+            let mut clients = server.clients.lock().unwrap();
+            if clients.is_empty() {
+                return;
+            }
+            let frame = Arc::new(encode_frame(&self.frame, self.frame_width, self.frame_height));
+            clients.retain(|client| match client.try_send(frame.clone()) {
+                Ok(()) | Err(TrySendError::Full(_)) => true,
+                Err(TrySendError::Disconnected(_)) => false
+            });
+        }
+    }
+}
+
+// accept connections until the server is stopped
+fn accept_clients(listener: TcpListener, clients: Arc<Mutex<Vec<SyncSender<EncodedFrame>>>>, stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                // room for one frame, newer frames are dropped until the client has taken it
+                let (sender, receiver) = mpsc::sync_channel(1);
+                clients.lock().unwrap().push(sender);
+                thread::spawn(move || send_frames(stream, receiver));
+            }
+            // no connection waiting, or a connection which failed before it was accepted
+            Err(_) => thread::sleep(ACCEPT_INTERVAL)
+        }
+    }
+}
+
+// write frames to a client until it disconnects or the server is stopped
+fn send_frames(mut stream: TcpStream, frames: Receiver<EncodedFrame>) {
+    if stream.set_nonblocking(false).is_err() {
+        return;
+    }
+    let _ = stream.set_nodelay(true);
+    for frame in frames {
+        if stream.write_all(&frame).is_err() {
+            return;
+        }
+    }
+}
+
+// encode a frame with its header, using run-length encoding if it is smaller
+fn encode_frame(frame: &[u32], width: usize, height: usize) -> Vec<u8> {
+    let mut runs: Vec<(u16, u32)> = Vec::new();
+    for &color in frame {
+        match runs.last_mut() {
+            Some((count, run_color)) if *run_color == color && *count < u16::MAX => *count += 1,
+            _ => runs.push((1, color))
+        }
+    }
+
+    let (encoding, length) = if runs.len() * 6 < frame.len() * 4 {
+        (ENCODING_RLE, runs.len() * 6)
+    } else {
+        (ENCODING_RAW, frame.len() * 4)
+    };
+    let mut data = Vec::with_capacity(HEADER_SIZE + length);
+    data.extend_from_slice(&(width as u16).to_le_bytes());
+    data.extend_from_slice(&(height as u16).to_le_bytes());
+    data.push(encoding);
+    data.extend_from_slice(&(length as u32).to_le_bytes());
+    if encoding == ENCODING_RLE {
+        for (count, color) in runs {
+            data.extend_from_slice(&count.to_le_bytes());
+            data.extend_from_slice(&color.to_le_bytes());
+        }
+    } else {
+        for color in frame {
+            data.extend_from_slice(&color.to_le_bytes());
+        }
+    }
+    data
+}
+
+/// Read one frame sent by [`TMS9918A::serve_frames`] into a buffer, returning its width and height
+///
+/// The buffer is resized to the frame size and reuses its allocation, so it can be passed straight to a window
+/// every frame. This blocks until a whole frame has been read. Returns an error if reading fails or the data
+/// isn't a valid frame.
+///
+/// # Examples
+///
+/// ```
+/// // a 2x2 frame as a single run of 4 white pixels
+/// let data = [2, 0, 2, 0, 1, 6, 0, 0, 0, 4, 0, 0xFF, 0xFF, 0xFF, 0x00];
+/// let mut frame = Vec::new();
+/// let size = tms9918a_emu::net::receive_frame(&mut &data[..], &mut frame).unwrap();
+/// assert_eq!(size, (2, 2));
+/// assert_eq!(frame, [0xFFFFFF; 4]);
+/// ```
+///
+/// Receiving frames from a server:
+///
+/// ```no_run
+/// # use std::net::TcpStream;
+/// # fn main() -> Result<(), tms9918a_emu::TMS9918AError> {
+/// let mut stream = TcpStream::connect("127.0.0.1:7911").unwrap();
+/// let mut frame = Vec::new();
+/// loop {
+///     let (width, height) = tms9918a_emu::net::receive_frame(&mut stream, &mut frame)?;
+///     // display frame
+/// }
+/// # }
+/// ```
+pub fn receive_frame<R: Read>(reader: &mut R, frame: &mut Vec<u32>) -> Result<(usize, usize), TMS9918AError> {
+    let invalid = || TMS9918AError::Io("invalid frame data".to_string());
+    let to_error = |error: std::io::Error| TMS9918AError::Io(error.to_string());
+
+    let mut header = [0; HEADER_SIZE];
+    reader.read_exact(&mut header).map_err(to_error)?;
+    let width = u16::from_le_bytes([header[0], header[1]]) as usize;
+    let height = u16::from_le_bytes([header[2], header[3]]) as usize;
+    let encoding = header[4];
+    let length = u32::from_le_bytes([header[5], header[6], header[7], header[8]]) as usize;
+
+    let pixels = width * height;
+    let expected_length = match encoding {
+        ENCODING_RAW if length == pixels * 4 => length,
+        ENCODING_RLE if length % 6 == 0 && length <= pixels * 6 => length,
+        _ => return Err(invalid())
+    };
+    let mut data = vec![0; expected_length];
+    reader.read_exact(&mut data).map_err(to_error)?;
+
+    frame.clear();
+    if encoding == ENCODING_RAW {
+        frame.extend(data.chunks_exact(4).map(|color| u32::from_le_bytes([color[0], color[1], color[2], color[3]])));
+    } else {
+        for run in data.chunks_exact(6) {
+            let count = u16::from_le_bytes([run[0], run[1]]) as usize;
+            let color = u32::from_le_bytes([run[2], run[3], run[4], run[5]]);
+            if frame.len() + count > pixels {
+                return Err(invalid());
+            }
+            frame.extend(std::iter::repeat(color).take(count));
+        }
+    }
+    if frame.len() != pixels {
+        return Err(invalid());
+    }
+    Ok((width, height))
+}
@@ -0,0 +1,177 @@
+//! Tile and sprite patterns defined as string art
+
+use crate::TMS9918AError;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Conversion between tile patterns and string art
+///
+/// String art draws a pattern with one string per row and one character per pixel, which is easier
+/// to read in source code than binary literals. By default `'X'` is a set pixel and `'.'` is a clear pixel.
+///
+/// # Examples
+///
+/// ```
+/// # use tms9918a_emu::{Pattern, TMS9918A};
+/// let smiley = Pattern::from_str_art(&[
+///     "........",
+///     "..X..X..",
+///     "..X..X..",
+///     "..X..X..",
+///     "........",
+///     ".X....X.",
+///     ".XXXXXX.",
+///     "........",
+/// ]).unwrap();
+/// assert_eq!(smiley, [0x00, 0x24, 0x24, 0x24, 0x00, 0x42, 0x7E, 0x00]);
+///
+/// let mut vdp = TMS9918A::new();
+/// vdp.set_pattern(1, &smiley).unwrap();
+/// assert_eq!(Pattern::to_string_art(&vdp.pattern(1))[1], "..X..X..");
+///
+/// // the wrong number of pixels in a row is reported
+/// assert!(Pattern::from_str_art(&["XXXXXXXXX"; 8]).is_err());
+/// ```
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Pattern;
+
+impl Pattern {
+    /// Convert 8 rows of 8 characters into an 8x8 pattern, using `'X'` for set pixels and `'.'` for clear pixels
+    ///
+    /// Returns an error with the position of the first invalid character, missing pixel or extra pixel.
+    pub fn from_str_art(art: &[&str]) -> Result<[u8; 8], TMS9918AError> {
+        Pattern::from_str_art_with(art, 'X', '.')
+    }
+
+    /// Convert 8 rows of 8 characters into an 8x8 pattern, using custom characters for set and clear pixels
+    pub fn from_str_art_with(art: &[&str], on: char, off: char) -> Result<[u8; 8], TMS9918AError> {
+        let mut pattern = [0; 8];
+        parse_art(art, 8, on, off, |x, y| pattern[y] |= 0x80 >> x)?;
+        Ok(pattern)
+    }
+
+    /// Convert 8 rows of 8 characters into an 8x8 pattern in a const context, using `'X'` for set pixels and `'.'` for clear pixels
+    ///
+    /// This is used by the [`pattern!`](crate::pattern!) macro. Invalid art panics, which is a compile error when evaluated in a const.
+    pub const fn from_str_art_const(art: &[&str]) -> [u8; 8] {
+        if art.len() != 8 {
+            panic!("pattern art must have 8 rows");
+        }
+        let mut pattern = [0; 8];
+        let mut y = 0;
+        while y < 8 {
+            let row = art[y].as_bytes();
+            if row.len() != 8 {
+                panic!("pattern art rows must have 8 pixels");
+            }
+            let mut x = 0;
+            while x < 8 {
+                match row[x] {
+                    b'X' => pattern[y] |= 0x80 >> x,
+                    b'.' => {}
+                    _ => panic!("pattern art pixels must be 'X' or '.'")
+                }
+                x += 1;
+            }
+            y += 1;
+        }
+        pattern
+    }
+
+    /// Convert 16 rows of 16 characters into a 16x16 sprite pattern, using `'X'` for set pixels and `'.'` for clear pixels
+    ///
+    /// The 32 bytes are four 8x8 blocks in the order used by 16x16 sprites: top left, bottom left, top right, bottom right.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::Pattern;
+    /// let mut art = ["................"; 16];
+    /// art[0] = "X..............X";
+    /// let pattern = Pattern::from_str_art_16x16(&art).unwrap();
+    /// assert_eq!(pattern[0], 0x80);
+    /// assert_eq!(pattern[16], 0x01);
+    /// ```
+    pub fn from_str_art_16x16(art: &[&str]) -> Result<[u8; 32], TMS9918AError> {
+        Pattern::from_str_art_16x16_with(art, 'X', '.')
+    }
+
+    /// Convert 16 rows of 16 characters into a 16x16 sprite pattern, using custom characters for set and clear pixels
+    pub fn from_str_art_16x16_with(art: &[&str], on: char, off: char) -> Result<[u8; 32], TMS9918AError> {
+        let mut pattern = [0; 32];
+        parse_art(art, 16, on, off, |x, y| pattern[(x / 8) * 16 + y] |= 0x80 >> (x % 8))?;
+        Ok(pattern)
+    }
+
+    /// Convert an 8x8 pattern into 8 rows of string art, using `'X'` for set pixels and `'.'` for clear pixels
+    pub fn to_string_art(pattern: &[u8; 8]) -> Vec<String> {
+        Pattern::to_string_art_with(pattern, 'X', '.')
+    }
+
+    /// Convert an 8x8 pattern into 8 rows of string art, using custom characters for set and clear pixels
+    pub fn to_string_art_with(pattern: &[u8; 8], on: char, off: char) -> Vec<String> {
+        pattern.iter()
+            .map(|row| (0..8).map(|x| if row & (0x80 >> x) != 0 { on } else { off }).collect())
+            .collect()
+    }
+}
+
+// check the dimensions and characters of square string art, calling set_pixel for each set pixel
+fn parse_art<F: FnMut(usize, usize)>(art: &[&str], size: usize, on: char, off: char, mut set_pixel: F) -> Result<(), TMS9918AError> {
+    if art.len() != size {
+        return Err(TMS9918AError::InvalidPatternArt { row: art.len().min(size), column: 0 });
+    }
+    for (y, row) in art.iter().enumerate() {
+        let mut columns = 0;
+        for (x, c) in row.chars().enumerate() {
+            if x >= size || (c != on && c != off) {
+                return Err(TMS9918AError::InvalidPatternArt { row: y, column: x });
+            }
+            if c == on {
+                set_pixel(x, y);
+            }
+            columns += 1;
+        }
+        if columns != size {
+            return Err(TMS9918AError::InvalidPatternArt { row: y, column: columns });
+        }
+    }
+    Ok(())
+}
+
+/// Define an 8x8 pattern from string art at compile time
+///
+/// Each row is a string of 8 characters, `'X'` for set pixels and `'.'` for clear pixels, see [`Pattern`].
+/// The art is checked and converted at compile time, so the wrong number of rows, pixels or an invalid
+/// character is a compile error.
+///
+/// # Examples
+///
+/// ```
+/// use tms9918a_emu::pattern;
+///
+/// const SMILEY: [u8; 8] = pattern![
+///     "........",
+///     "..X..X..",
+///     "..X..X..",
+///     "..X..X..",
+///     "........",
+///     ".X....X.",
+///     ".XXXXXX.",
+///     "........",
+/// ];
+/// assert_eq!(SMILEY, [0x00, 0x24, 0x24, 0x24, 0x00, 0x42, 0x7E, 0x00]);
+/// ```
+///
+/// ```compile_fail
+/// # use tms9918a_emu::pattern;
+/// // only 7 pixels in the first row
+/// const BROKEN: [u8; 8] = pattern![".......", "........", "........", "........", "........", "........", "........", "........"];
+/// ```
+#[macro_export]
+macro_rules! pattern {
+    ($($row:expr),* $(,)?) => {{
+        const PATTERN: [u8; 8] = $crate::Pattern::from_str_art_const(&[$($row),*]);
+        PATTERN
+    }};
+}
@@ -0,0 +1,126 @@
+//! GPU presentation of the TMS9918A framebuffer with the `pixels` crate, for embedding in winit applications
+//!
+//! This module is only available with the `pixels` feature. Unlike [`window::Window`](crate::window),
+//! it doesn't own a window or an event loop: the application creates the window, for example with winit,
+//! and calls into [`PixelsView`] from its own event handling. This makes it possible to show the VDP in an
+//! application which already has a window, such as an egui app or a wgpu game.
+//!
+//! A [`PixelsView`] needs three calls from the event loop:
+//!
+//! * [`resize_surface`](PixelsView::resize_surface) when the window is resized or its scale factor changes,
+//!   with the new size in physical pixels
+//! * [`update`](PixelsView::update) or [`present`](PixelsView::present) when the window is redrawn
+//! * [`window_pos_to_pixel`](PixelsView::window_pos_to_pixel) to map mouse positions to the frame, if needed
+//!
+//! See the `pixels_view` example for the complete wiring with winit.
+
+use crate::display::DisplayBackend;
+use crate::{TMS9918A, TMS9918AError};
+use ::pixels::raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+use ::pixels::SurfaceTexture;
+
+pub use ::pixels::Pixels;
+
+/// The framebuffer of a TMS9918A drawn on a window surface with the GPU
+///
+/// The frame is scaled by the largest whole number that fits in the surface and centered, with a black border
+/// around it. When the frame size changes, for example when the border is shown, the pixel buffer is resized
+/// on the next [`present`](PixelsView::present).
+pub struct PixelsView {
+    pixels: Pixels,
+    // size of the pixel buffer, which follows the frame size
+    buffer_size: (usize, usize)
+}
+
+impl PixelsView {
+    /// Create a view drawing to a window's surface
+    ///
+    /// `surface_width` and `surface_height` are the size of the window's drawing area in physical pixels, for
+    /// example `window.inner_size()` with winit, so the frame stays sharp on high DPI screens.
+    /// Returns an error if no GPU adapter is found or the surface can't be created.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let window = winit::window::Window::new(&event_loop)?;
+    /// let size = window.inner_size();
+    /// let mut view = PixelsView::new(&window, size.width, size.height, &vdp)?;
+    /// ```
+    pub fn new<W: HasRawWindowHandle + HasRawDisplayHandle>(window: &W, surface_width: u32, surface_height: u32, vdp: &TMS9918A) -> Result<Self, TMS9918AError> {
+        let buffer_size = (vdp.frame_width(), vdp.frame_height());
+        let surface = SurfaceTexture::new(surface_width.max(1), surface_height.max(1), window);
+        let pixels = Pixels::new(buffer_size.0 as u32, buffer_size.1 as u32, surface)
+            .map_err(|error| TMS9918AError::WindowCreation(error.to_string()))?;
+        Ok(PixelsView { pixels, buffer_size })
+    }
+
+    /// Resize the surface after the window is resized or moved to a screen with a different scale factor
+    ///
+    /// The size is in physical pixels. A size of 0, for example from a minimized window, is ignored.
+    pub fn resize_surface(&mut self, width: u32, height: u32) -> Result<(), TMS9918AError> {
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        self.pixels.resize_surface(width, height).map_err(|error| TMS9918AError::WindowUpdate(error.to_string()))
+    }
+
+    /// Render the VDP framebuffer and draw it on the surface
+    ///
+    /// This is the same as calling [`TMS9918A::render`] followed by [`present`](PixelsView::present).
+    pub fn update(&mut self, vdp: &mut TMS9918A) -> Result<(), TMS9918AError> {
+        vdp.render();
+        self.present(vdp)
+    }
+
+    /// Draw the current VDP framebuffer on the surface without rendering a new frame
+    pub fn present(&mut self, vdp: &TMS9918A) -> Result<(), TMS9918AError> {
+        DisplayBackend::present(self, &vdp.frame, vdp.frame_width(), vdp.frame_height())
+    }
+
+    /// Copy the VDP framebuffer into the pixel buffer without drawing it
+    ///
+    /// This is for applications which draw the pixel buffer themselves, for example with
+    /// [`Pixels::render_with`] to add their own render passes on top of the frame.
+    pub fn copy_frame(&mut self, vdp: &TMS9918A) -> Result<(), TMS9918AError> {
+        self.copy_pixels(&vdp.frame, vdp.frame_width(), vdp.frame_height())
+    }
+
+    // copy a frame into the pixel buffer, resizing the buffer to the frame size
+    fn copy_pixels(&mut self, frame: &[u32], width: usize, height: usize) -> Result<(), TMS9918AError> {
+        let frame_size = (width, height);
+        if self.buffer_size != frame_size {
+            self.pixels.resize_buffer(frame_size.0 as u32, frame_size.1 as u32)
+                .map_err(|error| TMS9918AError::WindowUpdate(error.to_string()))?;
+            self.buffer_size = frame_size;
+        }
+        // the pixel buffer is RGBA with 8 bits per channel
+        for (pixel, color) in self.pixels.frame_mut().chunks_exact_mut(4).zip(frame.iter()) {
+            pixel.copy_from_slice(&[(color >> 16) as u8, (color >> 8) as u8, *color as u8, 0xFF]);
+        }
+        Ok(())
+    }
+
+    /// Map a position in the window, in physical pixels, to a pixel in the frame
+    ///
+    /// Returns `None` if the position is outside of the frame, for example in the border around a scaled frame.
+    pub fn window_pos_to_pixel(&self, x: f32, y: f32) -> Option<(usize, usize)> {
+        self.pixels.window_pos_to_pixel((x, y)).ok()
+    }
+
+    /// Return a reference to the underlying pixels instance
+    pub fn inner(&self) -> &Pixels {
+        &self.pixels
+    }
+
+    /// Return a mutable reference to the underlying pixels instance, for example to change the clear color
+    pub fn inner_mut(&mut self) -> &mut Pixels {
+        &mut self.pixels
+    }
+}
+
+impl DisplayBackend for PixelsView {
+    fn present(&mut self, frame: &[u32], width: usize, height: usize) -> Result<(), TMS9918AError> {
+        self.copy_pixels(frame, width, height)?;
+        self.pixels.render().map_err(|error| TMS9918AError::WindowUpdate(error.to_string()))
+    }
+}
@@ -0,0 +1,148 @@
+//! Animated GIF recording of the framebuffer, only built with the `gif` feature
+
+use crate::{TMS9918A, TMS9918AError};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// a frame copied from the framebuffer, as (width, height, pixels)
+type RecordedFrame = (usize, usize, Vec<u32>);
+
+// recording state kept in the VDP while a recording is running
+pub(crate) struct Recorder {
+    every_n_frames: u32,
+    // frames since the last recorded frame
+    skipped: u32,
+    // None if the encoder thread stopped early because of an error
+    sender: Option<Sender<RecordedFrame>>,
+    encoder: JoinHandle<Result<(), TMS9918AError>>
+}
+
+impl TMS9918A {
+    /// Start recording the rendered frames to an animated GIF file
+    ///
+    /// Every `every_n_frames`th frame completed by [`update`](TMS9918A::update), [`render`](TMS9918A::render) or
+    /// [`tick`](TMS9918A::tick) is added to the GIF, starting with the next frame. The frame delay follows the
+    /// frame rate of the current video standard, about 60 Hz for NTSC or 50 Hz for PAL, divided by `every_n_frames`.
+    /// The GIF has the size of the framebuffer when recording starts, frames of a different size are cropped or padded.
+    ///
+    /// Frames are encoded on a separate thread, so recording doesn't stall rendering. The recording is finished by
+    /// [`stop_recording`](TMS9918A::stop_recording), and a running recording is stopped first.
+    /// Returns an error if the file can't be created. This is only available with the `gif` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tms9918a_emu::TMS9918A;
+    /// # fn main() -> Result<(), tms9918a_emu::TMS9918AError> {
+    /// let mut vdp = TMS9918A::new();
+    /// // record at 30 fps for 2 seconds
+    /// vdp.start_recording("demo.gif", 2)?;
+    /// for _ in 0..120 {
+    ///     vdp.update();
+    /// }
+    /// vdp.stop_recording()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn start_recording<P: AsRef<Path>>(&mut self, path: P, every_n_frames: u32) -> Result<(), TMS9918AError> {
+        self.stop_recording()?;
+        let every_n_frames = every_n_frames.max(1);
+        let file = File::create(path).map_err(|error| TMS9918AError::Io(error.to_string()))?;
+        let frame_delay = self.frame_duration() * every_n_frames;
+        let (width, height) = (self.frame_width, self.frame_height);
+        let (sender, receiver) = mpsc::channel();
+        let encoder = thread::spawn(move || encode_gif(file, width, height, frame_delay, receiver));
+        self.recorder = Some(Recorder {
+            every_n_frames,
+            // record the next frame
+            skipped: every_n_frames - 1,
+            sender: Some(sender),
+            encoder
+        });
+        Ok(())
+    }
+
+    /// Stop recording and finish the GIF file
+    ///
+    /// This waits for the remaining frames to be encoded. Returns an error if writing the file failed.
+    /// Nothing happens if no recording is running.
+    pub fn stop_recording(&mut self) -> Result<(), TMS9918AError> {
+        match self.recorder.take() {
+            Some(recorder) => {
+                // closing the channel ends the encoder thread
+                drop(recorder.sender);
+                recorder.encoder.join().map_err(|_| TMS9918AError::Image("GIF encoder thread panicked".to_string()))?
+            }
+            None => Ok(())
+        }
+    }
+
+    /// Check if a recording started by [`start_recording`](TMS9918A::start_recording) is running
+    #[inline]
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    // send the completed frame to the encoder thread if it is due
+    pub(crate) fn record_frame(&mut self) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.skipped += 1;
+            if recorder.skipped < recorder.every_n_frames {
+                return;
+            }
+            recorder.skipped = 0;
+            if let Some(sender) = &recorder.sender {
+                let frame = (self.frame_width, self.frame_height, self.frame.clone());
+                if sender.send(frame).is_err() {
+                    // the encoder failed, the error is returned by stop_recording
+                    recorder.sender = None;
+                }
+            }
+        }
+    }
+}
+
+// encode frames from the channel until it is closed
+fn encode_gif(file: File, width: usize, height: usize, frame_delay: Duration, frames: Receiver<RecordedFrame>) -> Result<(), TMS9918AError> {
+    let to_error = |error: gif::EncodingError| TMS9918AError::Image(error.to_string());
+    let mut encoder = gif::Encoder::new(BufWriter::new(file), width as u16, height as u16, &[]).map_err(to_error)?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(to_error)?;
+
+    // GIF delays are in 1/100 s, round the total time so the delays average out to the frame rate
+    let frame_delay = frame_delay.as_secs_f64() * 100.0;
+    let mut elapsed = 0.0;
+    for (frame_width, frame_height, pixels) in frames {
+        let mut palette: Vec<u32> = Vec::new();
+        let mut indices = vec![0; width * height];
+        for y in 0..height.min(frame_height) {
+            for x in 0..width.min(frame_width) {
+                let color = pixels[y * frame_width + x];
+                let index = match palette.iter().position(|&c| c == color) {
+                    Some(index) => index,
+                    None => {
+                        palette.push(color);
+                        palette.len() - 1
+                    }
+                };
+                indices[y * width + x] = index as u8;
+            }
+        }
+        if palette.is_empty() {
+            palette.push(0);
+        }
+        let palette_bytes: Vec<u8> = palette.iter()
+            .flat_map(|color| vec![(color >> 16) as u8, (color >> 8) as u8, *color as u8])
+            .collect();
+
+        let mut frame = gif::Frame::from_palette_pixels(width as u16, height as u16, indices, palette_bytes, None);
+        let start = elapsed;
+        elapsed += frame_delay;
+        frame.delay = (elapsed.round() - start.round()) as u16;
+        encoder.write_frame(&frame).map_err(to_error)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+//! Rendering the framebuffer as text for terminals without a display
+
+use crate::TMS9918A;
+use alloc::string::String;
+use core::fmt::Write;
+
+// levels of each channel in the 6x6x6 color cube of the 256 color ANSI palette
+const CUBE_LEVELS: [u32; 6] = [0x00, 0x5F, 0x87, 0xAF, 0xD7, 0xFF];
+
+impl TMS9918A {
+    /// Draw the framebuffer as Unicode half block characters with ANSI 256 color escapes
+    ///
+    /// Each character shows two pixels stacked on top of each other, using the upper half block character with the
+    /// upper pixel as the foreground color and the lower pixel as the background color. Each pixel covers a 2x2 block
+    /// of the framebuffer, so a 256x192 frame is 128 characters wide and 48 lines tall, which keeps the aspect ratio
+    /// on terminals with character cells twice as tall as they are wide. A block shows its most common color, and if
+    /// colors are tied a color other than the backdrop color wins, so thin lines and text stay visible.
+    ///
+    /// This works on the last frame rendered by [`update`](TMS9918A::update) or [`render`](TMS9918A::render),
+    /// so no window is needed. Every line ends by resetting the colors and a newline.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::{Color, TMS9918A};
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.set_backdrop_color(Color::LightBlue);
+    /// vdp.render();
+    /// let text = vdp.render_to_terminal_string();
+    /// assert_eq!(text.lines().count(), 48);
+    /// assert!(text.starts_with("\x1b[38;5;105;48;5;105m\u{2580}\u{2580}"));
+    /// ```
+    pub fn render_to_terminal_string(&self) -> String {
+        let (width, height) = (self.frame_width, self.frame_height);
+        let backdrop = self.palette[self.vdp_register[7] as usize & 0x0F];
+        let columns = width.div_ceil(2);
+        let lines = height.div_ceil(4);
+
+        let mut text = String::with_capacity(lines * (columns * 4 + 16));
+        for line in 0..lines {
+            let mut current = None;
+            for column in 0..columns {
+                let upper = self.block_color(column * 2, line * 4, backdrop);
+                // frames with an odd number of pixel rows have no lower pixel on the last line
+                let lower = if line * 4 + 2 < height { self.block_color(column * 2, line * 4 + 2, backdrop) } else { 0 };
+                let colors = (ansi_color(upper), ansi_color(lower));
+                if current != Some(colors) {
+                    // writing to a String can't fail
+                    let _ = write!(text, "\x1b[38;5;{};48;5;{}m", colors.0, colors.1);
+                    current = Some(colors);
+                }
+                text.push('\u{2580}');
+            }
+            text.push_str("\x1b[0m\n");
+        }
+        text
+    }
+
+    /// Print the framebuffer to the standard output as Unicode half block characters with ANSI 256 color escapes
+    ///
+    /// See [`render_to_terminal_string`](TMS9918A::render_to_terminal_string). This is only available with the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn print_to_terminal(&self) {
+        print!("{}", self.render_to_terminal_string());
+    }
+
+    // most common color of the 2x2 block of pixels starting at x, y, preferring other colors over the backdrop color
+    fn block_color(&self, x: usize, y: usize, backdrop: u32) -> u32 {
+        let mut colors = [(0, 0); 4];
+        let mut count = 0;
+        for pixel_y in y..(y + 2).min(self.frame_height) {
+            for pixel_x in x..(x + 2).min(self.frame_width) {
+                let color = self.frame[pixel_y * self.frame_width + pixel_x];
+                match colors[..count].iter_mut().find(|(c, _)| *c == color) {
+                    Some((_, n)) => *n += 1,
+                    None => {
+                        colors[count] = (color, 1);
+                        count += 1;
+                    }
+                }
+            }
+        }
+        colors[..count].iter()
+            .max_by_key(|&&(color, n)| (n, color != backdrop))
+            .map_or(backdrop, |&(color, _)| color)
+    }
+}
+
+// nearest color in the color cube or grayscale ramp of the 256 color ANSI palette
+fn ansi_color(color: u32) -> u8 {
+    let channels = [color >> 16 & 0xFF, color >> 8 & 0xFF, color & 0xFF];
+    let distance = |other: [u32; 3]| -> u32 {
+        channels.iter().zip(other.iter()).map(|(&a, &b)| (a as i32 - b as i32).pow(2) as u32).sum()
+    };
+
+    let cube = channels.map(|channel| (0..6).min_by_key(|&i| (CUBE_LEVELS[i] as i32 - channel as i32).abs()).unwrap());
+    let cube_color = [CUBE_LEVELS[cube[0]], CUBE_LEVELS[cube[1]], CUBE_LEVELS[cube[2]]];
+    let cube_index = 16 + 36 * cube[0] + 6 * cube[1] + cube[2];
+
+    // the grayscale ramp is 0x08 to 0xEE in steps of 10
+    let average = channels.iter().sum::<u32>() / 3;
+    let gray = (average.saturating_sub(3) / 10).min(23);
+    let gray_level = 8 + gray * 10;
+
+    if distance([gray_level; 3]) < distance(cube_color) {
+        (232 + gray) as u8
+    } else {
+        cube_index as u8
+    }
+}
@@ -0,0 +1,220 @@
+//! Golden image testing of rendered frames, only built with the `testing` feature
+//!
+//! A golden image test renders a known screen and compares it against a reference image checked in next to the
+//! tests, so changes to the renderer or to the drawing code of a game are caught. [`render_headless`] renders a
+//! screen without a window, [`Frame`] saves and loads reference images as PNG files, and
+//! [`assert_frames_equal`] fails with a description of the differences.
+//!
+//! To create or update a reference image, render the screen and save it with [`Frame::save_png`], then check
+//! that the image looks right before committing it.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use tms9918a_emu::testing::{assert_frames_equal, render_headless, Frame};
+//! # fn main() -> Result<(), tms9918a_emu::TMS9918AError> {
+//!
+//! let frame = render_headless(|vdp| {
+//!     vdp.print_text(2, 2, "HELLO");
+//!     vdp.enable_video(true);
+//! });
+//! assert_frames_equal(&frame, &Frame::load_png("testdata/hello.png")?);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The reference screens of this crate are compared the same way in `tests/golden.rs`.
+
+use crate::{TMS9918A, TMS9918AError, VramInit};
+use std::fmt;
+use std::path::Path;
+
+/// A rendered frame: `width` x `height` pixels in the `0x00RRGGBB` format, row by row from the top left
+#[derive(Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// Width in pixels
+    pub width: usize,
+    /// Height in pixels
+    pub height: usize,
+    /// Pixels in the `0x00RRGGBB` format
+    pub pixels: Vec<u32>
+}
+
+impl Frame {
+    /// Copy the last frame rendered by a VDP
+    pub fn from_vdp(vdp: &TMS9918A) -> Self {
+        let (width, height) = (vdp.frame_width(), vdp.frame_height());
+        Frame { width, height, pixels: vdp.frame[..width * height].to_vec() }
+    }
+
+    /// Save the frame as a PNG image
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), TMS9918AError> {
+        rgb_image(self.width, self.height, &self.pixels)
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(|error| TMS9918AError::Image(error.to_string()))
+    }
+
+    /// Load a frame from a PNG image, or any other image format supported by the `image` crate
+    ///
+    /// The alpha channel is ignored.
+    pub fn load_png<P: AsRef<Path>>(path: P) -> Result<Self, TMS9918AError> {
+        let image = image::open(path).map_err(|error| TMS9918AError::Image(error.to_string()))?.into_rgb8();
+        let pixels = image.pixels().map(|pixel| {
+            let [r, g, b] = pixel.0;
+            (r as u32) << 16 | (g as u32) << 8 | b as u32
+        }).collect();
+        Ok(Frame { width: image.width() as usize, height: image.height() as usize, pixels })
+    }
+
+    /// Compute a hash of the frame, the same as [`TMS9918A::frame_hash`] of the VDP it was rendered by
+    pub fn hash(&self) -> u64 {
+        crate::hash_frame(&self.pixels, self.width, self.height)
+    }
+
+    /// Get the color of a pixel, `None` if the position is outside of the frame
+    pub fn pixel(&self, x: usize, y: usize) -> Option<u32> {
+        if x < self.width && y < self.height {
+            Some(self.pixels[y * self.width + x])
+        } else {
+            None
+        }
+    }
+}
+
+// convert 0x00RRGGBB pixels to an RGB image
+fn rgb_image(width: usize, height: usize, pixels: &[u32]) -> image::RgbImage {
+    image::RgbImage::from_fn(width as u32, height as u32, |x, y| {
+        let color = pixels[y as usize * width + x as usize];
+        image::Rgb([(color >> 16) as u8, (color >> 8) as u8, color as u8])
+    })
+}
+
+// a frame has tens of thousands of pixels, so only show the size and a hash of them
+impl fmt::Debug for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Frame {{ width: {}, height: {}, hash: {:#018X} }}", self.width, self.height, self.hash())
+    }
+}
+
+/// Render a frame without a window, after setting up a VDP with a function
+///
+/// The VDP starts with video memory cleared to 0, so the frame only depends on what the function writes.
+pub fn render_headless<F: FnOnce(&mut TMS9918A)>(setup: F) -> Frame {
+    let mut vdp = TMS9918A::with_vram_init(VramInit::Zero);
+    setup(&mut vdp);
+    vdp.render();
+    Frame::from_vdp(&vdp)
+}
+
+/// Differences between two frames, returned by [`compare_frames`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum FrameDiff {
+    /// The frames have different sizes, as (width, height)
+    Size {
+        /// Size of the actual frame
+        actual: (usize, usize),
+        /// Size of the expected frame
+        expected: (usize, usize)
+    },
+    /// The frames have the same size but some pixels are different
+    Pixels {
+        /// Number of different pixels
+        count: usize,
+        /// Smallest rectangle containing all different pixels, as (left, top, right, bottom), inclusive
+        bounds: (usize, usize, usize, usize),
+        /// First different pixel, as (x, y, actual color, expected color)
+        first: (usize, usize, u32, u32)
+    }
+}
+
+impl fmt::Display for FrameDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameDiff::Size { actual, expected } => {
+                write!(f, "frame is {}x{} pixels, expected {}x{}", actual.0, actual.1, expected.0, expected.1)
+            },
+            FrameDiff::Pixels { count, bounds, first } => {
+                let (left, top, right, bottom) = bounds;
+                let (x, y, actual, expected) = first;
+                write!(f, "{} pixels are different in the area from ({}, {}) to ({}, {}), the first at ({}, {}) is {:06X}, expected {:06X}",
+                    count, left, top, right, bottom, x, y, actual, expected)
+            }
+        }
+    }
+}
+
+/// Compare two frames, returns `None` if they are equal
+///
+/// # Examples
+///
+/// ```
+/// use tms9918a_emu::testing::{compare_frames, render_headless, FrameDiff};
+/// use tms9918a_emu::Color;
+///
+/// let expected = render_headless(|vdp| vdp.set_backdrop_color(Color::Black));
+/// let actual = render_headless(|vdp| {
+///     vdp.set_backdrop_color(Color::Black);
+///     // every name table entry is tile 0, set the top row of its pattern
+///     vdp.set_pattern_table_multiplier(1);
+///     vdp.set_color_table_multiplier(0x80);
+///     vdp.write_ram(0x0800, 0xFF);
+///     vdp.write_ram(0x2000, 0xF1);
+///     // 16K, the color table is above the first 4K
+///     vdp.write_register(1, 0x80);
+///     vdp.enable_video(true);
+/// });
+/// assert_eq!(compare_frames(&expected, &expected), None);
+/// match compare_frames(&actual, &expected) {
+///     Some(FrameDiff::Pixels { count, bounds, .. }) => {
+///         assert_eq!(count, 256 * 24);
+///         assert_eq!(bounds, (0, 0, 255, 184));
+///     },
+///     diff => panic!("unexpected difference: {:?}", diff)
+/// }
+/// ```
+pub fn compare_frames(actual: &Frame, expected: &Frame) -> Option<FrameDiff> {
+    if (actual.width, actual.height) != (expected.width, expected.height) {
+        return Some(FrameDiff::Size { actual: (actual.width, actual.height), expected: (expected.width, expected.height) });
+    }
+
+    let mut count = 0;
+    let mut bounds = (usize::MAX, usize::MAX, 0, 0);
+    let mut first = None;
+    for (i, (&a, &b)) in actual.pixels.iter().zip(expected.pixels.iter()).enumerate() {
+        if a != b {
+            let (x, y) = (i % actual.width, i / actual.width);
+            count += 1;
+            bounds = (bounds.0.min(x), bounds.1.min(y), bounds.2.max(x), bounds.3.max(y));
+            first.get_or_insert((x, y, a, b));
+        }
+    }
+    first.map(|first| FrameDiff::Pixels { count, bounds, first })
+}
+
+/// Assert that two frames are equal, panicking with a description of the differences if they aren't
+pub fn assert_frames_equal(actual: &Frame, expected: &Frame) {
+    if let Some(diff) = compare_frames(actual, expected) {
+        panic!("frames are different: {}", diff);
+    }
+}
+
+/// Assert that two frames are equal, saving an image of the differences to a PNG file if they aren't
+///
+/// The difference image shows different pixels in red and equal pixels darkened. Nothing is saved if the frames
+/// have different sizes. This panics the same way as [`assert_frames_equal`], also if the image can't be saved.
+pub fn assert_frames_equal_with_diff<P: AsRef<Path>>(actual: &Frame, expected: &Frame, diff_path: P) {
+    let diff = match compare_frames(actual, expected) {
+        Some(diff) => diff,
+        None => return
+    };
+    if let FrameDiff::Pixels { .. } = diff {
+        let pixels: Vec<u32> = actual.pixels.iter().zip(expected.pixels.iter())
+            .map(|(&a, &b)| if a == b { a >> 2 & 0x3F3F3F } else { 0xFF0000 })
+            .collect();
+        if let Err(error) = rgb_image(actual.width, actual.height, &pixels).save_with_format(diff_path.as_ref(), image::ImageFormat::Png) {
+            panic!("frames are different: {}, and the difference image could not be saved: {}", diff, error);
+        }
+        panic!("frames are different: {}, see {}", diff, diff_path.as_ref().display());
+    }
+    panic!("frames are different: {}", diff);
+}
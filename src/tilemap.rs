@@ -0,0 +1,73 @@
+//! Tile maps exported by the Tiled map editor, only built with the `tilemap` feature
+
+use crate::TMS9918AError;
+use alloc::vec::Vec;
+
+/// A map of tile indices, read from a CSV layer exported by [Tiled](https://www.mapeditor.org/)
+///
+/// Maps can be any size, for example larger than the screen for scrolling. Use
+/// [`TMS9918A::write_tilemap`](crate::TMS9918A::write_tilemap) to write a map to the name table.
+///
+/// # Examples
+///
+/// ```
+/// # use tms9918a_emu::Tilemap;
+/// let map = Tilemap::from_csv("1,2,3\n4,-1,255\n").unwrap();
+/// assert_eq!((map.width, map.height), (3, 2));
+/// assert_eq!(map.tile(2, 1), Some(255));
+/// // empty cells are tile 0
+/// assert_eq!(map.tile(1, 1), Some(0));
+///
+/// assert!(Tilemap::from_csv("1,2,3\n4,5\n").is_err());
+/// assert!(Tilemap::from_csv("256").is_err());
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Tilemap {
+    /// Width in tiles
+    pub width: usize,
+    /// Height in tiles
+    pub height: usize,
+    /// Tile indices, row by row
+    pub tiles: Vec<u8>
+}
+
+impl Tilemap {
+    /// Read a map from comma separated tile indices, one row of the map on each line
+    ///
+    /// Empty cells, which Tiled exports as -1, are tile 0. A comma at the end of a row and empty lines are ignored.
+    /// Returns an error with the position of the first tile index which isn't 0-255, or the first row with a different
+    /// length than the first row.
+    pub fn from_csv(csv: &str) -> Result<Tilemap, TMS9918AError> {
+        let mut map = Tilemap::default();
+        for line in csv.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let row = map.height;
+            let values = line.strip_suffix(',').unwrap_or(line).split(',');
+            let mut columns = 0;
+            for (column, value) in values.enumerate() {
+                let tile = match value.trim().parse::<i32>() {
+                    Ok(-1) => 0,
+                    Ok(tile) if (0..=0xFF).contains(&tile) => tile as u8,
+                    _ => return Err(TMS9918AError::InvalidTilemap { row, column })
+                };
+                map.tiles.push(tile);
+                columns += 1;
+            }
+            if row == 0 {
+                map.width = columns;
+            } else if columns != map.width {
+                return Err(TMS9918AError::InvalidTilemap { row, column: columns.min(map.width) });
+            }
+            map.height += 1;
+        }
+        Ok(map)
+    }
+
+    /// Get the tile at a position, or `None` if it is outside of the map
+    pub fn tile(&self, x: usize, y: usize) -> Option<u8> {
+        if x < self.width && y < self.height {
+            Some(self.tiles[y * self.width + x])
+        } else {
+            None
+        }
+    }
+}
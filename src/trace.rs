@@ -0,0 +1,281 @@
+//! Recording port accesses to a trace file and replaying them
+
+use crate::{TMS9918A, TMS9918AError};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+// the file starts with the magic bytes and the format version as a little endian u16
+const MAGIC: &[u8; 8] = b"TMSTRACE";
+const VERSION: u16 = 1;
+
+// each event starts with one of these tags
+// port accesses follow it with the scanline as a little endian u16, then their data
+const FRAME_END: u8 = 0;
+// data: the byte written
+const WRITE_CONTROL: u8 = 1;
+// data: the byte written
+const WRITE_DATA: u8 = 2;
+// no data
+const READ_DATA: u8 = 3;
+// no data
+const READ_STATUS: u8 = 4;
+// data: the length as a little endian u32, then the bytes written
+const WRITE_DATA_BLOCK: u8 = 5;
+// data: the length as a little endian u32
+const READ_DATA_BLOCK: u8 = 6;
+
+// a port access recorded in a trace
+pub(crate) enum TraceEvent<'a> {
+    WriteControl(u8),
+    WriteData(u8),
+    ReadData,
+    ReadStatus,
+    WriteDataBlock(&'a [u8]),
+    ReadDataBlock(usize)
+}
+
+// trace file being written while a trace is recorded
+pub(crate) struct TraceRecorder {
+    writer: BufWriter<File>,
+    // the first error while writing, returned by stop_trace
+    error: Option<io::Error>
+}
+
+impl TraceRecorder {
+    fn write(&mut self, bytes: &[u8]) {
+        if self.error.is_none() {
+            if let Err(error) = self.writer.write_all(bytes) {
+                self.error = Some(error);
+            }
+        }
+    }
+}
+
+impl TMS9918A {
+    /// Start recording every port access to a trace file
+    ///
+    /// The trace starts with a snapshot of video memory, the registers and the port state, followed by each access
+    /// to the control and data ports with the scanline it happened on, and a marker at the end of each frame
+    /// completed by [`update`](TMS9918A::update), [`render`](TMS9918A::render) or [`tick`](TMS9918A::tick).
+    /// Block accesses are recorded as one event. Changes made through other functions, such as
+    /// [`write_register`](TMS9918A::write_register) or [`write_ram`](TMS9918A::write_ram), are not recorded, so only
+    /// the port accesses of the guest software are replayed.
+    ///
+    /// The trace is finished by [`stop_trace`](TMS9918A::stop_trace), and a running trace is stopped first.
+    /// Returns an error if the file can't be created or written. This is only available with the `std` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tms9918a_emu::TMS9918A;
+    /// # fn main() -> Result<(), tms9918a_emu::TMS9918AError> {
+    /// let path = std::env::temp_dir().join("tms9918a_trace_example.bin");
+    /// let mut vdp = TMS9918A::new();
+    /// vdp.record_trace(&path)?;
+    /// // write to register 7, then a frame, then fill the start of video memory
+    /// vdp.write_control_port(0xF4);
+    /// vdp.write_control_port(0x87);
+    /// vdp.update();
+    /// vdp.write_control_port(0x00);
+    /// vdp.write_control_port(0x40);
+    /// vdp.write_data_block(&[0x55; 100]);
+    /// vdp.update();
+    /// vdp.stop_trace()?;
+    ///
+    /// // replay only the first frame
+    /// let mut replay = TMS9918A::new();
+    /// assert_eq!(replay.replay_trace(&path, Some(1))?, 1);
+    /// assert_eq!(replay.read_register(7), 0xF4);
+    /// assert_ne!(replay.vram(), vdp.vram());
+    ///
+    /// // replay the whole trace
+    /// let mut replay = TMS9918A::new();
+    /// assert_eq!(replay.replay_trace(&path, None)?, 2);
+    /// assert_eq!(replay.vram(), vdp.vram());
+    /// assert_eq!(replay.registers(), vdp.registers());
+    /// # std::fs::remove_file(&path).unwrap();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn record_trace<P: AsRef<Path>>(&mut self, path: P) -> Result<(), TMS9918AError> {
+        self.stop_trace()?;
+        let file = File::create(path).map_err(|error| TMS9918AError::Io(error.to_string()))?;
+        let mut recorder = TraceRecorder { writer: BufWriter::new(file), error: None };
+
+        recorder.write(MAGIC);
+        recorder.write(&VERSION.to_le_bytes());
+        recorder.write(&self.vdp_register);
+        recorder.write(&[self.vdp_status, self.vdp_temp_data, self.vdp_first_byte_saved_flag as u8, self.vdp_read_ahead]);
+        recorder.write(&self.vdp_addr_pointer.to_le_bytes());
+        recorder.write(&self.vdp_ram);
+        match recorder.error.take() {
+            Some(error) => Err(TMS9918AError::Io(error.to_string())),
+            None => {
+                self.tracer = Some(recorder);
+                Ok(())
+            }
+        }
+    }
+
+    /// Stop recording the trace started by [`record_trace`](TMS9918A::record_trace) and finish the file
+    ///
+    /// Returns an error if writing the file failed. Nothing happens if no trace is being recorded.
+    pub fn stop_trace(&mut self) -> Result<(), TMS9918AError> {
+        match self.tracer.take() {
+            Some(mut recorder) => {
+                let result = match recorder.error.take() {
+                    Some(error) => Err(error),
+                    None => recorder.writer.flush()
+                };
+                result.map_err(|error| TMS9918AError::Io(error.to_string()))
+            }
+            None => Ok(())
+        }
+    }
+
+    /// Check if a trace started by [`record_trace`](TMS9918A::record_trace) is being recorded
+    #[inline]
+    pub fn is_tracing(&self) -> bool {
+        self.tracer.is_some()
+    }
+
+    /// Replay a trace file recorded by [`record_trace`](TMS9918A::record_trace)
+    ///
+    /// Video memory, the registers and the port state are set from the snapshot at the start of the trace, then
+    /// each port access is repeated in order and each frame marker renders a frame with [`render`](TMS9918A::render).
+    /// This stops after `max_frames` frames, or at the end of the trace if it is `None`, and returns the number of
+    /// frames replayed. Replaying on a VDP of the same variant as the recorded one gives the same video memory,
+    /// registers and port state, and the same frames unless the guest changed the screen in the middle of a frame.
+    ///
+    /// Returns an error if the file can't be read or isn't a valid trace. A trace cut off in the middle of an event,
+    /// for example because the program recording it crashed, is replayed up to that event.
+    /// This is only available with the `std` feature.
+    pub fn replay_trace<P: AsRef<Path>>(&mut self, path: P, max_frames: Option<u64>) -> Result<u64, TMS9918AError> {
+        let file = File::open(path).map_err(|error| TMS9918AError::Io(error.to_string()))?;
+        let mut reader = BufReader::new(file);
+        let invalid = |reason: &str| TMS9918AError::InvalidTrace(reason.to_string());
+
+        let mut header = [0; 10];
+        reader.read_exact(&mut header).map_err(|_| invalid("missing header"))?;
+        if &header[..8] != MAGIC {
+            return Err(invalid("missing header"));
+        }
+        let version = u16::from_le_bytes([header[8], header[9]]);
+        if version != VERSION {
+            return Err(TMS9918AError::InvalidTrace(format!("unsupported version {}", version)));
+        }
+
+        let mut snapshot = [0; 8 + 4 + 2];
+        let mut vram = vec![0; self.vdp_ram.len()];
+        reader.read_exact(&mut snapshot).and_then(|_| reader.read_exact(&mut vram)).map_err(|_| invalid("truncated snapshot"))?;
+        for (register, &data) in snapshot[..8].iter().enumerate() {
+            self.write_register(register as u8, data);
+        }
+        self.vdp_status = snapshot[8];
+        self.vdp_temp_data = snapshot[9];
+        self.vdp_first_byte_saved_flag = snapshot[10] != 0;
+        self.vdp_read_ahead = snapshot[11];
+        self.vdp_addr_pointer = u16::from_le_bytes([snapshot[12], snapshot[13]]) & 0x3FFF;
+        self.vdp_ram = vram;
+        self.invalidate_tiles();
+
+        let mut frames = 0;
+        let mut block = Vec::new();
+        while max_frames.map_or(true, |max_frames| frames < max_frames) {
+            let mut tag = [0; 1];
+            if reader.read_exact(&mut tag).is_err() {
+                break;
+            }
+            if tag[0] == FRAME_END {
+                self.render();
+                frames += 1;
+                continue;
+            }
+
+            // the scanline is only informational, the frame markers keep the timing
+            let mut scanline = [0; 2];
+            if reader.read_exact(&mut scanline).is_err() {
+                break;
+            }
+            let result = match tag[0] {
+                WRITE_CONTROL => read_byte(&mut reader).map(|data| self.write_control_port(data)),
+                WRITE_DATA => read_byte(&mut reader).map(|data| self.write_data_port(data)),
+                READ_DATA => {
+                    self.read_data_port();
+                    Ok(())
+                },
+                READ_STATUS => {
+                    self.read_status_port();
+                    Ok(())
+                },
+                WRITE_DATA_BLOCK => read_length(&mut reader).and_then(|length| {
+                    // reading through take doesn't allocate the whole length up front if the trace is corrupted
+                    block.clear();
+                    (&mut reader).take(length as u64).read_to_end(&mut block)?;
+                    if block.len() == length {
+                        self.write_data_block(&block);
+                        Ok(())
+                    } else {
+                        Err(io::ErrorKind::UnexpectedEof.into())
+                    }
+                }),
+                READ_DATA_BLOCK => read_length(&mut reader).map(|length| {
+                    // reading in pieces is the same as reading the whole block at once
+                    let mut remaining = length;
+                    while remaining > 0 {
+                        block.resize(remaining.min(0x4000), 0);
+                        self.read_data_block(&mut block);
+                        remaining -= block.len();
+                    }
+                }),
+                tag => return Err(TMS9918AError::InvalidTrace(format!("unknown event {}", tag)))
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        Ok(frames)
+    }
+
+    // add a port access to the trace, if a trace is being recorded
+    pub(crate) fn trace_event(&mut self, event: TraceEvent) {
+        let scanline = self.vdp_scanline.to_le_bytes();
+        if let Some(recorder) = &mut self.tracer {
+            match event {
+                TraceEvent::WriteControl(data) => recorder.write(&[WRITE_CONTROL, scanline[0], scanline[1], data]),
+                TraceEvent::WriteData(data) => recorder.write(&[WRITE_DATA, scanline[0], scanline[1], data]),
+                TraceEvent::ReadData => recorder.write(&[READ_DATA, scanline[0], scanline[1]]),
+                TraceEvent::ReadStatus => recorder.write(&[READ_STATUS, scanline[0], scanline[1]]),
+                TraceEvent::WriteDataBlock(data) => {
+                    recorder.write(&[WRITE_DATA_BLOCK, scanline[0], scanline[1]]);
+                    recorder.write(&(data.len() as u32).to_le_bytes());
+                    recorder.write(data);
+                },
+                TraceEvent::ReadDataBlock(length) => {
+                    recorder.write(&[READ_DATA_BLOCK, scanline[0], scanline[1]]);
+                    recorder.write(&(length as u32).to_le_bytes());
+                }
+            }
+        }
+    }
+
+    // add a frame marker to the trace, if a trace is being recorded
+    pub(crate) fn trace_frame(&mut self) {
+        if let Some(recorder) = &mut self.tracer {
+            recorder.write(&[FRAME_END]);
+        }
+    }
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut byte = [0; 1];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_length<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+    Ok(u32::from_le_bytes(length) as usize)
+}
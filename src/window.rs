@@ -0,0 +1,254 @@
+//! minifb window for displaying the TMS9918A framebuffer
+//!
+//! This module is only available with the `window` feature, which is enabled by default.
+//! The emulation core in [`TMS9918A`] never touches the display, so applications that
+//! already own a window (or run without a display) can disable default features.
+
+use crate::display::{DisplayBackend, Event, Key};
+use crate::{TMS9918A, TMS9918AError, VideoStandard};
+use minifb::KeyRepeat;
+use std::time::Duration;
+
+pub use minifb::{Scale, ScaleMode, WindowOptions};
+
+/// A window displaying the framebuffer of a TMS9918A
+pub struct Window {
+    window: minifb::Window,
+    update_rate: Option<Duration>,
+    // window size when events were last polled
+    size: (usize, usize),
+    // set once the window has been reported as closed
+    closed: bool
+}
+
+impl Window {
+    /// Create a new window
+    ///
+    /// The window is scaled 4x, keeps its aspect ratio when resized, and its update rate is limited to ~60 fps.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tms9918a_emu::TMS9918A;
+    /// # use tms9918a_emu::window::Window;
+    /// # fn main() -> Result<(), tms9918a_emu::TMS9918AError> {
+    /// let mut vdp = TMS9918A::new();
+    /// let mut window = Window::new("TMS9918A")?;
+    ///
+    /// while window.is_open() {
+    ///     window.update(&mut vdp)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(title: &str) -> Result<Self, TMS9918AError> {
+        Self::with_options(title, WindowOptions {
+            resize: true,
+            scale_mode: ScaleMode::AspectRatioStretch,
+            scale: Scale::X4,
+            ..WindowOptions::default()
+        })
+    }
+
+    /// Create a new window with custom minifb window options
+    ///
+    /// The update rate is limited to ~60 fps, the same as [`new`](Window::new).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tms9918a_emu::window::{Scale, ScaleMode, Window, WindowOptions};
+    /// # fn main() -> Result<(), tms9918a_emu::TMS9918AError> {
+    /// // integer scaling that fits on smaller screens
+    /// let mut window = Window::with_options("TMS9918A", WindowOptions {
+    ///     scale: Scale::X2,
+    ///     scale_mode: ScaleMode::Center,
+    ///     resize: false,
+    ///     ..WindowOptions::default()
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_options(title: &str, options: WindowOptions) -> Result<Self, TMS9918AError> {
+        let mut window = minifb::Window::new(title, 256, 192, options)
+            .map_err(|error| TMS9918AError::WindowCreation(error.to_string()))?;
+        let update_rate = Some(VideoStandard::Ntsc.frame_duration());
+        window.limit_update_rate(update_rate);
+        let size = window.get_size();
+        Ok(Window { window, update_rate, size, closed: false })
+    }
+
+    /// Set the minimum time between window updates, or `None` to disable the limit
+    ///
+    /// This takes effect on the next [`update`](Window::update) or [`present`](Window::present).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tms9918a_emu::TMS9918A;
+    /// # use tms9918a_emu::window::Window;
+    /// # fn main() -> Result<(), tms9918a_emu::TMS9918AError> {
+    /// let vdp = TMS9918A::new();
+    /// let mut window = Window::new("TMS9918A")?;
+    ///
+    /// // match the frame rate of the VDP's video standard
+    /// window.set_update_rate(Some(vdp.frame_duration()));
+    ///
+    /// // fast-forward
+    /// window.set_update_rate(None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_update_rate(&mut self, rate: Option<Duration>) {
+        self.update_rate = rate;
+        self.window.limit_update_rate(rate);
+    }
+
+    /// Get the minimum time between window updates, or `None` if updates are not limited
+    pub fn update_rate(&self) -> Option<Duration> {
+        self.update_rate
+    }
+
+    /// Return true if the window is still open
+    pub fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+
+    /// Render the VDP framebuffer and display it in the window
+    ///
+    /// This is the same as calling [`TMS9918A::render`] followed by [`present`](Window::present).
+    pub fn update(&mut self, vdp: &mut TMS9918A) -> Result<(), TMS9918AError> {
+        vdp.render();
+        self.present(vdp)
+    }
+
+    /// Display the current VDP framebuffer in the window without rendering a new frame
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tms9918a_emu::TMS9918A;
+    /// # use tms9918a_emu::window::Window;
+    /// # fn main() -> Result<(), tms9918a_emu::TMS9918AError> {
+    /// let mut vdp = TMS9918A::new();
+    /// let mut window = Window::new("TMS9918A")?;
+    ///
+    /// while window.is_open() {
+    ///     // fast-forward: render four frames for every frame displayed
+    ///     for _ in 0..4 {
+    ///         vdp.render();
+    ///     }
+    ///     window.present(&vdp)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn present(&mut self, vdp: &TMS9918A) -> Result<(), TMS9918AError> {
+        DisplayBackend::present(self, &vdp.frame, vdp.frame_width(), vdp.frame_height())
+    }
+
+    /// Return a reference to the underlying minifb window, for example to read keyboard input
+    pub fn inner(&self) -> &minifb::Window {
+        &self.window
+    }
+
+    /// Return a mutable reference to the underlying minifb window
+    pub fn inner_mut(&mut self) -> &mut minifb::Window {
+        &mut self.window
+    }
+}
+
+impl DisplayBackend for Window {
+    fn present(&mut self, frame: &[u32], width: usize, height: usize) -> Result<(), TMS9918AError> {
+        self.window.update_with_buffer(frame, width, height)
+            .map_err(|error| TMS9918AError::WindowUpdate(error.to_string()))
+    }
+
+    fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+
+    // minifb updates its input state when the window is updated, so this reports the input since the last present
+    fn poll(&mut self) -> Vec<Event> {
+        let mut events = Vec::new();
+        if !self.window.is_open() {
+            if !self.closed {
+                self.closed = true;
+                events.push(Event::Closed);
+            }
+            return events;
+        }
+
+        let size = self.window.get_size();
+        if size != self.size {
+            self.size = size;
+            events.push(Event::Resized { width: size.0, height: size.1 });
+        }
+        events.extend(self.window.get_keys_pressed(KeyRepeat::No).into_iter().map(|key| Event::KeyDown(convert_key(key))));
+        events.extend(self.window.get_keys_released().into_iter().map(|key| Event::KeyUp(convert_key(key))));
+        events
+    }
+}
+
+// convert a minifb key to a display key
+fn convert_key(key: minifb::Key) -> Key {
+    use minifb::Key as K;
+    match key {
+        K::Key0 | K::NumPad0 => Key::Char('0'),
+        K::Key1 | K::NumPad1 => Key::Char('1'),
+        K::Key2 | K::NumPad2 => Key::Char('2'),
+        K::Key3 | K::NumPad3 => Key::Char('3'),
+        K::Key4 | K::NumPad4 => Key::Char('4'),
+        K::Key5 | K::NumPad5 => Key::Char('5'),
+        K::Key6 | K::NumPad6 => Key::Char('6'),
+        K::Key7 | K::NumPad7 => Key::Char('7'),
+        K::Key8 | K::NumPad8 => Key::Char('8'),
+        K::Key9 | K::NumPad9 => Key::Char('9'),
+        // letters are in order from A = 10
+        K::A | K::B | K::C | K::D | K::E | K::F | K::G | K::H | K::I | K::J | K::K | K::L | K::M |
+        K::N | K::O | K::P | K::Q | K::R | K::S | K::T | K::U | K::V | K::W | K::X | K::Y | K::Z => {
+            Key::Char((b'a' + (key as u8 - K::A as u8)) as char)
+        }
+        K::F1 => Key::Function(1),
+        K::F2 => Key::Function(2),
+        K::F3 => Key::Function(3),
+        K::F4 => Key::Function(4),
+        K::F5 => Key::Function(5),
+        K::F6 => Key::Function(6),
+        K::F7 => Key::Function(7),
+        K::F8 => Key::Function(8),
+        K::F9 => Key::Function(9),
+        K::F10 => Key::Function(10),
+        K::F11 => Key::Function(11),
+        K::F12 => Key::Function(12),
+        K::F13 => Key::Function(13),
+        K::F14 => Key::Function(14),
+        K::F15 => Key::Function(15),
+        K::Space => Key::Char(' '),
+        K::Apostrophe => Key::Char('\''),
+        K::Backquote => Key::Char('`'),
+        K::Backslash => Key::Char('\\'),
+        K::Comma => Key::Char(','),
+        K::Equal => Key::Char('='),
+        K::LeftBracket => Key::Char('['),
+        K::RightBracket => Key::Char(']'),
+        K::Minus | K::NumPadMinus => Key::Char('-'),
+        K::Period | K::NumPadDot => Key::Char('.'),
+        K::Semicolon => Key::Char(';'),
+        K::Slash | K::NumPadSlash => Key::Char('/'),
+        K::NumPadAsterisk => Key::Char('*'),
+        K::NumPadPlus => Key::Char('+'),
+        K::Enter | K::NumPadEnter => Key::Enter,
+        K::Escape => Key::Escape,
+        K::Backspace => Key::Backspace,
+        K::Tab => Key::Tab,
+        K::Up => Key::Up,
+        K::Down => Key::Down,
+        K::Left => Key::Left,
+        K::Right => Key::Right,
+        K::LeftShift | K::RightShift => Key::Shift,
+        K::LeftCtrl | K::RightCtrl => Key::Control,
+        K::LeftAlt | K::RightAlt => Key::Alt,
+        _ => Key::Other
+    }
+}
@@ -0,0 +1,51 @@
+//! Golden image tests of the reference screens in `testdata`, see the `testing` module
+
+#![cfg(feature = "testing")]
+
+use tms9918a_emu::fonts::Font;
+use tms9918a_emu::testing::{assert_frames_equal, render_headless, Frame};
+use tms9918a_emu::{Color, SpriteDef, VideoMode};
+
+fn reference(name: &str) -> Frame {
+    Frame::load_png(format!("{}/testdata/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+#[test]
+fn gfx1_with_sprite() {
+    let gfx1 = render_headless(|vdp| {
+        // 16K, the pattern and sprite tables are above the first 4K
+        vdp.write_register(1, 0x80);
+        vdp.set_name_table_multiplier(0);
+        vdp.set_color_table_multiplier(0x20);
+        vdp.set_pattern_table_multiplier(2);
+        vdp.set_sprite_attribute_table_multiplier(0x0E);
+        vdp.write_register(6, 0x03);
+        vdp.load_builtin_font(Font::Text6x8).unwrap();
+        vdp.fill_color_table(&[0xF6, 0xF6, 0xF6, 0xF6, 0xB4, 0xB4, 0xB4, 0xB4, 0x71, 0x71, 0x71, 0x71], 4, 12).unwrap();
+        vdp.set_backdrop_color(Color::DarkBlue);
+        vdp.print_text(2, 2, "GRAPHICS I MODE");
+        vdp.print_text(2, 4, "0123456789 !\"#$%&'()*+,-./");
+        vdp.print_text(2, 6, "abcdefghijklmnopqrstuvwxyz");
+        vdp.set_sprite_pattern(0, &[0x3C, 0x7E, 0xFF, 0xFF, 0xFF, 0xFF, 0x7E, 0x3C]).unwrap();
+        vdp.define_sprite(0, SpriteDef { x: 120, y: 100, pattern: 0, color: 0x9, early_clock: false }).unwrap();
+        vdp.define_sprite(1, SpriteDef { x: 0, y: 0xD0, pattern: 0, color: 0, early_clock: false }).unwrap();
+        vdp.enable_video(true);
+    });
+    assert_frames_equal(&gfx1, &reference("gfx1.png"));
+}
+
+#[test]
+fn text_40_columns() {
+    let text = render_headless(|vdp| {
+        vdp.set_video_mode(VideoMode::Text);
+        vdp.set_name_table_multiplier(0);
+        vdp.set_pattern_table_multiplier(1);
+        vdp.set_text_color(Color::LightGreen, Color::Black);
+        vdp.load_builtin_font(Font::Text6x8).unwrap();
+        vdp.print_text(0, 0, "TEXT MODE, 40 COLUMNS");
+        vdp.print_text(0, 2, "Pack my box with five dozen liquor jugs.");
+        vdp.print_text(39, 23, "#");
+        vdp.enable_video(true);
+    });
+    assert_frames_equal(&text, &reference("text.png"));
+}